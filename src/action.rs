@@ -6,6 +6,8 @@ use serde::{
 };
 use strum::Display;
 
+use crate::mode::Mode;
+
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Display, Deserialize)]
 pub enum Action {
   Tick,
@@ -17,4 +19,9 @@ pub enum Action {
   Refresh,
   Error(String),
   Help,
+  ChangeMode(Mode),
+  /// Hands the text to `$VISUAL`/`$EDITOR` while the TUI is released.
+  EditExternally(String),
+  /// The external editor exited, with the edited text unless it failed.
+  ExternalEditFinished(Option<String>),
 }
@@ -1,3 +1,5 @@
+use std::sync::Arc;
+
 use color_eyre::eyre::Result;
 use crossterm::event::KeyEvent;
 use ratatui::prelude::Rect;
@@ -6,14 +8,19 @@ use tokio::sync::mpsc;
 
 use crate::{
   action::Action,
-  components::{home::Home, fps::FpsCounter, Component},
+  backup::Backups,
+  components::{auto_backup::AutoBackup, home::Home, fps::FpsCounter, Component},
   config::Config,
+  crypto::PASSPHRASE_ENV,
+  db_reader::{DbReader, NativeDbReader},
+  external_editor,
   mode::Mode,
   tui,
 };
 
 pub struct App {
   pub config: Config,
+  pub db: Arc<dyn DbReader>,
   pub tick_rate: f64,
   pub frame_rate: f64,
   pub components: Vec<Box<dyn Component>>,
@@ -28,14 +35,22 @@ impl App {
     let home = Home::new();
     // let fps = FpsCounter::default();
     let config = Config::new()?;
+    let db = Arc::new(NativeDbReader::new()?);
+    // A missing or wrong passphrase leaves the journal locked, and Home asks for it.
+    if let Ok(passphrase) = std::env::var(PASSPHRASE_ENV) {
+      if db.is_locked()? {
+        let _ = db.unlock(&passphrase);
+      }
+    }
     let mode = Mode::Home;
     Ok(Self {
       tick_rate,
       frame_rate,
-      components: vec![Box::new(home)],
+      components: vec![Box::new(home), Box::new(AutoBackup::new(Backups::in_data_dir()))],
       should_quit: false,
       should_suspend: false,
       config,
+      db,
       mode,
       last_tick_key_events: Vec::new(),
     })
@@ -56,6 +71,10 @@ impl App {
       component.register_config_handler(self.config.clone())?;
     }
 
+    for component in self.components.iter_mut() {
+      component.register_db_handler(self.db.clone())?;
+    }
+
     for component in self.components.iter_mut() {
       component.init(tui.size()?)?;
     }
@@ -105,6 +124,21 @@ impl App {
           Action::Quit => self.should_quit = true,
           Action::Suspend => self.should_suspend = true,
           Action::Resume => self.should_suspend = false,
+          Action::ChangeMode(mode) => self.mode = mode,
+          Action::EditExternally(ref content) => {
+            tui.exit()?;
+            let edited = external_editor::edit(content);
+            tui = tui::Tui::new()?.tick_rate(self.tick_rate).frame_rate(self.frame_rate);
+            // tui.mouse(true);
+            tui.enter()?;
+            match edited {
+              Ok(edited) => action_tx.send(Action::ExternalEditFinished(Some(edited)))?,
+              Err(e) => {
+                action_tx.send(Action::Error(format!("Failed to edit externally: {:?}", e)))?;
+                action_tx.send(Action::ExternalEditFinished(None))?;
+              },
+            }
+          },
           Action::Resize(w, h) => {
             tui.resize(Rect::new(0, 0, w, h))?;
             tui.draw(|f| {
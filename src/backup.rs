@@ -0,0 +1,279 @@
+//! Rotating snapshots of the database, kept in `backups/` in the data directory.
+//!
+//! A snapshot is a plain copy of the database file, named after the moment it was taken,
+//! so it can also be opened or copied back by hand. Encrypted journals stay encrypted in
+//! their snapshots.
+
+use std::{
+    collections::HashSet,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use chrono::{DateTime, Datelike, Duration, NaiveDateTime, SubsecRound, Utc};
+use color_eyre::eyre::{eyre, Result};
+
+use crate::{calendar::Calendar, config::BackupConfig, db_reader::DbReader, utils::get_data_dir};
+
+const BACKUP_DIR: &str = "backups";
+const PREFIX: &str = "terajot-";
+const EXTENSION: &str = ".db";
+const TIME_FORMAT: &str = "%Y%m%dT%H%M%S%.3fZ";
+/// Names of snapshots taken before they had milliseconds.
+const SECONDS_TIME_FORMAT: &str = "%Y%m%dT%H%M%SZ";
+
+/// A copy of the database taken at some point.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Snapshot {
+    pub path: PathBuf,
+    pub taken_at: DateTime<Utc>,
+    pub size: u64,
+}
+
+impl Snapshot {
+    pub fn name(&self) -> String {
+        self.path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default()
+    }
+}
+
+/// The folder snapshots are kept in.
+pub struct Backups {
+    dir: PathBuf,
+}
+
+impl Backups {
+    pub fn new(dir: impl AsRef<Path>) -> Self {
+        Self {
+            dir: dir.as_ref().to_path_buf(),
+        }
+    }
+
+    /// Uses `backups/` in the data directory.
+    pub fn in_data_dir() -> Self {
+        Self::new(get_data_dir().join(BACKUP_DIR))
+    }
+
+    /// Gets the snapshots, newest first.
+    pub fn list(&self) -> Result<Vec<Snapshot>> {
+        if !self.dir.exists() {
+            return Ok(Vec::new());
+        }
+        let mut snapshots = Vec::new();
+        for entry in fs::read_dir(&self.dir)? {
+            let entry = entry?;
+            let name = entry.file_name().to_string_lossy().to_string();
+            let Some(taken_at) = name
+                .strip_prefix(PREFIX)
+                .and_then(|n| n.strip_suffix(EXTENSION))
+                .and_then(|n| {
+                    [TIME_FORMAT, SECONDS_TIME_FORMAT]
+                        .iter()
+                        .find_map(|f| NaiveDateTime::parse_from_str(n, f).ok())
+                })
+            else {
+                continue;
+            };
+            snapshots.push(Snapshot {
+                path: entry.path(),
+                taken_at: taken_at.and_utc(),
+                size: entry.metadata()?.len(),
+            });
+        }
+        snapshots.sort_by_key(|s| std::cmp::Reverse(s.taken_at));
+        Ok(snapshots)
+    }
+
+    /// Finds a snapshot by its name, with or without the extension.
+    pub fn find(&self, name: &str) -> Result<Snapshot> {
+        let name = name.strip_suffix(EXTENSION).unwrap_or(name);
+        self.list()?
+            .into_iter()
+            .find(|s| s.name().strip_suffix(EXTENSION) == Some(name))
+            .ok_or_else(|| eyre!("There is no snapshot named {name}, see `terajot backup list`"))
+    }
+
+    /// Snapshots the database. A snapshot taken in the same millisecond as another one is
+    /// named a millisecond later rather than replacing it.
+    pub fn snapshot(&self, db: &dyn DbReader, now: DateTime<Utc>) -> Result<Snapshot> {
+        fs::create_dir_all(&self.dir)
+            .map_err(|e| eyre!("Could not create the backup folder {}: {e}", self.dir.display()))?;
+        let mut taken_at = now.trunc_subsecs(3);
+        let name = |at: DateTime<Utc>| self.dir.join(format!("{PREFIX}{}{EXTENSION}", at.format(TIME_FORMAT)));
+        while name(taken_at).exists() {
+            taken_at += Duration::try_milliseconds(1).expect("a millisecond fits in a duration");
+        }
+        let path = name(taken_at);
+        // Copied under another name first, so that a crash never leaves a partial snapshot.
+        let partial = path.with_extension("partial");
+        db.snapshot(&partial)?;
+        fs::rename(&partial, &path)?;
+        let size = fs::metadata(&path)?.len();
+        Ok(Snapshot {
+            path,
+            // As precise as the name it is read back from.
+            taken_at,
+            size,
+        })
+    }
+
+    /// Deletes the snapshots the retention policy doesn't keep, returning them. Days are
+    /// those of the journal, as the calendar tells them.
+    pub fn prune(&self, config: &BackupConfig, calendar: &Calendar) -> Result<Vec<Snapshot>> {
+        let snapshots = self.list()?;
+        let kept = retained(&snapshots, config, calendar);
+        let mut removed = Vec::new();
+        for snapshot in snapshots {
+            if !kept.contains(&snapshot.path) {
+                fs::remove_file(&snapshot.path)?;
+                removed.push(snapshot);
+            }
+        }
+        Ok(removed)
+    }
+
+    /// Snapshots the database and prunes old snapshots.
+    pub fn rotate(&self, db: &dyn DbReader, config: &BackupConfig, calendar: &Calendar) -> Result<Snapshot> {
+        let snapshot = self.snapshot(db, Utc::now())?;
+        self.prune(config, calendar)?;
+        Ok(snapshot)
+    }
+
+    /// Replaces the database with a snapshot. The database is snapshotted first, so that
+    /// the restore can be undone.
+    pub fn restore(&self, db: &dyn DbReader, snapshot: &Snapshot) -> Result<Snapshot> {
+        let before = self.snapshot(db, Utc::now())?;
+        db.replace_with(&snapshot.path)?;
+        Ok(before)
+    }
+}
+
+/// Picks the snapshots to keep: the last one of each of the `keep_daily` most recent days
+/// and of each of the `keep_weekly` most recent weeks that have snapshots.
+fn retained(snapshots: &[Snapshot], config: &BackupConfig, calendar: &Calendar) -> HashSet<PathBuf> {
+    let mut newest = snapshots.iter().collect::<Vec<_>>();
+    newest.sort_by_key(|s| std::cmp::Reverse(s.taken_at));
+
+    let mut kept = HashSet::new();
+    let mut days = HashSet::new();
+    let mut weeks = HashSet::new();
+    for snapshot in newest {
+        let day = calendar.day_of(snapshot.taken_at);
+        if days.len() < config.keep_daily && days.insert(day) {
+            kept.insert(snapshot.path.clone());
+        }
+        let week = day.iso_week();
+        if weeks.len() < config.keep_weekly && weeks.insert((week.year(), week.week())) {
+            kept.insert(snapshot.path.clone());
+        }
+    }
+    kept
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+    use crate::{
+        config::JournalConfig,
+        db_reader::NativeDbReader,
+        models::{entry::Entry, stack::Stack},
+    };
+
+    fn utc_calendar(day_rollover_hour: u32) -> Calendar {
+        Calendar::new(&JournalConfig {
+            day_rollover_hour,
+            timezone: Some("UTC".to_string()),
+            ..Default::default()
+        })
+        .unwrap()
+    }
+
+    fn snapshot_at(taken_at: DateTime<Utc>) -> Snapshot {
+        Snapshot {
+            path: PathBuf::from(format!("{PREFIX}{}{EXTENSION}", taken_at.format(TIME_FORMAT))),
+            taken_at,
+            size: 0,
+        }
+    }
+
+    #[test]
+    fn test_retention_keeps_daily_and_weekly_snapshots() {
+        // Every 12 hours for 6 weeks, starting on a Monday.
+        let snapshots = (0..84)
+            .map(|i| snapshot_at(Utc.with_ymd_and_hms(2026, 9, 7, 6, 0, 0).unwrap() + Duration::try_hours(12 * i).unwrap()))
+            .collect::<Vec<_>>();
+        let config = BackupConfig {
+            keep_daily: 3,
+            keep_weekly: 3,
+            ..Default::default()
+        };
+        let mut kept = retained(&snapshots, &config, &utc_calendar(0)).into_iter().collect::<Vec<_>>();
+        kept.sort();
+        let names = kept.iter().map(|p| p.to_string_lossy().to_string()).collect::<Vec<_>>();
+        assert_eq!(
+            names,
+            vec![
+                "terajot-20261004T180000.000Z.db",
+                "terajot-20261011T180000.000Z.db",
+                "terajot-20261016T180000.000Z.db",
+                "terajot-20261017T180000.000Z.db",
+                "terajot-20261018T180000.000Z.db",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_retention_follows_the_day_rollover() {
+        let snapshots = [(17, 10), (17, 20), (18, 2)]
+            .map(|(day, hour)| snapshot_at(Utc.with_ymd_and_hms(2026, 10, day, hour, 0, 0).unwrap()));
+        let config = BackupConfig {
+            keep_daily: 2,
+            keep_weekly: 0,
+            ..Default::default()
+        };
+        let kept = |calendar| {
+            let mut kept = retained(&snapshots, &config, &calendar).into_iter().collect::<Vec<_>>();
+            kept.sort();
+            kept
+        };
+        assert_eq!(kept(utc_calendar(0)), vec![snapshots[1].path.clone(), snapshots[2].path.clone()]);
+        assert_eq!(kept(utc_calendar(4)), vec![snapshots[2].path.clone()]);
+    }
+
+    #[test]
+    fn test_snapshot_and_restore() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let db = NativeDbReader::open(dir.path().join("terajot.db"))?;
+        let backups = Backups::new(dir.path().join(BACKUP_DIR));
+        let stack = db.save_stack(&Stack::named("ideas".to_string()))?;
+        db.save_entry(&Entry::new(stack.id, "kept".to_string()))?;
+
+        let first = backups.snapshot(&db, Utc.with_ymd_and_hms(2026, 10, 17, 9, 0, 0).unwrap())?;
+        assert_eq!(first.name(), "terajot-20261017T090000.000Z.db");
+
+        db.save_entry(&Entry::new(stack.id, "lost".to_string()))?;
+        let snapshot = backups.find("terajot-20261017T090000.000Z")?;
+        let before = backups.restore(&db, &snapshot)?;
+        assert_eq!(db.get_all_entries()?.iter().map(|e| e.content.as_str()).collect::<Vec<_>>(), vec!["kept"]);
+        assert_eq!(backups.list()?.first(), Some(&before));
+        assert!(backups.find("missing").is_err());
+        Ok(())
+    }
+    #[test]
+    fn test_snapshots_in_the_same_moment_are_all_kept() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let db = NativeDbReader::open(dir.path().join("terajot.db"))?;
+        let backups = Backups::new(dir.path().join(BACKUP_DIR));
+        let now = Utc.with_ymd_and_hms(2026, 10, 17, 9, 0, 0).unwrap();
+        let first = backups.snapshot(&db, now)?;
+        let second = backups.snapshot(&db, now)?;
+        assert_eq!(second.name(), "terajot-20261017T090000.001Z.db");
+
+        fs::write(dir.path().join(BACKUP_DIR).join("terajot-20261016T090000Z.db"), "")?;
+        let names = backups.list()?.iter().map(Snapshot::name).collect::<Vec<_>>();
+        assert_eq!(names, vec![second.name(), first.name(), "terajot-20261016T090000Z.db".to_string()]);
+        Ok(())
+    }
+}
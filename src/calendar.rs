@@ -0,0 +1,126 @@
+use chrono::{DateTime, Duration, Local, NaiveDate, NaiveDateTime, TimeZone, Utc};
+use chrono_tz::Tz;
+use color_eyre::eyre::{eyre, Result};
+
+use crate::config::JournalConfig;
+
+/// Decides which day stack a moment belongs to.
+///
+/// A day starts at the configured rollover hour in the configured timezone, so a thought
+/// captured at 1am can still be filed with the evening before.
+#[derive(Clone, Debug, Default)]
+pub struct Calendar {
+    rollover_hour: u32,
+    timezone: Option<Tz>,
+}
+
+impl Calendar {
+    pub fn new(config: &JournalConfig) -> Result<Self> {
+        if config.day_rollover_hour > 23 {
+            return Err(eyre!(
+                "day_rollover_hour must be between 0 and 23, got {}",
+                config.day_rollover_hour
+            ));
+        }
+        let timezone = match &config.timezone {
+            Some(name) => Some(name.parse::<Tz>().map_err(|e| eyre!("Invalid timezone `{name}`: {e}"))?),
+            None => None,
+        };
+        Ok(Self {
+            rollover_hour: config.day_rollover_hour,
+            timezone,
+        })
+    }
+
+    /// Gets the day a moment belongs to.
+    pub fn day_of(&self, at: DateTime<Utc>) -> NaiveDate {
+        let rollover = Duration::try_hours(self.rollover_hour.into()).expect("the rollover hour fits in a day");
+        let shifted = at - rollover;
+        match self.timezone {
+            Some(tz) => shifted.with_timezone(&tz).date_naive(),
+            None => shifted.with_timezone(&Local).date_naive(),
+        }
+    }
+
+    /// Gets the first moment of a day, so that `day_of(start_of(day)) == day`.
+    pub fn start_of(&self, day: NaiveDate) -> DateTime<Utc> {
+        let midnight = self.from_local(day.and_hms_opt(0, 0, 0).expect("midnight exists"));
+        midnight + Duration::try_hours(self.rollover_hour.into()).expect("the rollover hour fits in a day")
+    }
+
+    /// Gets the moment a wall-clock time in the configured timezone refers to.
+    pub fn from_local(&self, local: NaiveDateTime) -> DateTime<Utc> {
+        match self.timezone {
+            Some(tz) => Self::to_utc(&tz, local),
+            None => Self::to_utc(&Local, local),
+        }
+    }
+
+    /// Converts a local time, skipping forward over a DST gap, which never lasts more than
+    /// a couple of hours.
+    fn to_utc<T: TimeZone>(tz: &T, local: NaiveDateTime) -> DateTime<Utc> {
+        (0..3)
+            .find_map(|hours| {
+                let shifted = local + Duration::try_hours(hours).expect("a few hours fit in a duration");
+                tz.from_local_datetime(&shifted).earliest()
+            })
+            .map(|at| at.with_timezone(&Utc))
+            .unwrap_or_else(|| local.and_utc())
+    }
+
+    /// Gets the day it currently is.
+    pub fn today(&self) -> NaiveDate {
+        self.day_of(Utc::now())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    fn calendar(rollover: u32, timezone: &str) -> Result<Calendar> {
+        Calendar::new(&JournalConfig {
+            day_rollover_hour: rollover,
+            timezone: Some(timezone.to_string()),
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn test_day_follows_timezone() -> Result<()> {
+        let at = Utc.with_ymd_and_hms(2026, 10, 18, 23, 30, 0).unwrap();
+        assert_eq!(calendar(0, "UTC")?.day_of(at), NaiveDate::from_ymd_opt(2026, 10, 18).unwrap());
+        assert_eq!(calendar(0, "Europe/Paris")?.day_of(at), NaiveDate::from_ymd_opt(2026, 10, 19).unwrap());
+        Ok(())
+    }
+
+    #[test]
+    fn test_day_rolls_over_at_configured_hour() -> Result<()> {
+        let calendar = calendar(4, "UTC")?;
+        let before = Utc.with_ymd_and_hms(2026, 10, 18, 3, 59, 0).unwrap();
+        let after = Utc.with_ymd_and_hms(2026, 10, 18, 4, 0, 0).unwrap();
+        assert_eq!(calendar.day_of(before), NaiveDate::from_ymd_opt(2026, 10, 17).unwrap());
+        assert_eq!(calendar.day_of(after), NaiveDate::from_ymd_opt(2026, 10, 18).unwrap());
+        Ok(())
+    }
+
+    #[test]
+    fn test_start_of_day() -> Result<()> {
+        let day = NaiveDate::from_ymd_opt(2026, 10, 18).unwrap();
+        let calendar = calendar(4, "Europe/Paris")?;
+        let start = calendar.start_of(day);
+        assert_eq!(start, Utc.with_ymd_and_hms(2026, 10, 18, 2, 0, 0).unwrap());
+        assert_eq!(calendar.day_of(start), day);
+        assert_eq!(calendar.day_of(start - Duration::try_seconds(1).unwrap()), day.pred_opt().unwrap());
+        Ok(())
+    }
+
+    #[test]
+    fn test_invalid_settings() {
+        assert!(calendar(24, "UTC").is_err());
+        assert!(calendar(0, "Mars/Olympus").is_err());
+    }
+}
@@ -1,12 +1,15 @@
 use std::path::PathBuf;
 
-use clap::Parser;
+use clap::{Parser, Subcommand, ValueEnum};
 
 use crate::utils::version;
 
 #[derive(Parser, Debug)]
 #[command(author, version = version(), about)]
 pub struct Cli {
+  #[command(subcommand)]
+  pub command: Option<Command>,
+
   #[arg(short, long, value_name = "FLOAT", help = "Tick rate, i.e. number of ticks per second", default_value_t = 1.0)]
   pub tick_rate: f64,
 
@@ -18,4 +21,190 @@ pub struct Cli {
     default_value_t = 4.0
   )]
   pub frame_rate: f64,
+
+  #[arg(
+    long,
+    value_name = "FD",
+    global = true,
+    help = "Read the passphrase of an encrypted journal from this file descriptor, instead of TERAJOT_PASSPHRASE or a prompt"
+  )]
+  pub passphrase_fd: Option<i32>,
+}
+
+/// Commands that run without starting the TUI.
+#[derive(Subcommand, Debug)]
+pub enum Command {
+  /// Add an entry to today's stack and exit
+  Add {
+    #[arg(short, long, value_name = "NAME", help = "Add to this named stack instead, creating it if needed")]
+    stack: Option<String>,
+
+    #[arg(value_name = "TEXT", help = "Content of the entry, or `-` to read it from stdin")]
+    content: String,
+  },
+  /// List all stacks
+  Stacks {
+    #[arg(long, value_enum, default_value_t)]
+    format: Format,
+  },
+  /// List the entries of a stack
+  Entries {
+    #[arg(value_name = "STACK", help = "Name or id of the stack")]
+    stack: String,
+
+    #[arg(long, value_enum, default_value_t)]
+    format: Format,
+  },
+  /// Search entries by their content, best matches first
+  Search {
+    #[arg(
+      value_name = "QUERY",
+      required = true,
+      help = "Words to look for, e.g. `stack:work after:2026-09-01 before:7d /invoice \\d+/ -draft`. Quote phrases as `\"some words\"`, end prefixes with `*` and exclude with `-`, after `--` so it is not read as an option"
+    )]
+    query: Vec<String>,
+
+    #[arg(long, value_enum, default_value_t)]
+    format: Format,
+  },
+  /// Show a single entry
+  Show {
+    #[arg(value_name = "ID", help = "Id of the entry")]
+    id: u32,
+
+    #[arg(long, value_enum, default_value_t)]
+    format: Format,
+  },
+  /// Move entries to another stack
+  Mv {
+    #[arg(value_name = "ENTRY", required = true, help = "Ids of the entries")]
+    entries: Vec<u32>,
+
+    #[arg(value_name = "STACK", help = "Name or id of the stack to move them to")]
+    stack: String,
+  },
+  /// Copy entries to another stack, keeping their dates
+  Cp {
+    #[arg(value_name = "ENTRY", required = true, help = "Ids of the entries")]
+    entries: Vec<u32>,
+
+    #[arg(value_name = "STACK", help = "Name or id of the stack to copy them to")]
+    stack: String,
+  },
+  /// Write the journal as plain files, readable without terajot
+  Export {
+    #[arg(value_enum)]
+    format: ExportFormat,
+
+    #[arg(value_name = "DIR", help = "Directory to write to, created if needed. Existing files with the same names are overwritten")]
+    dir: PathBuf,
+
+    #[arg(long, help = "Write a single file per stack with a heading for every day, instead of a folder of entries")]
+    single_file: bool,
+  },
+  /// Import text and Markdown files, a stack per directory and an entry per file.
+  ///
+  /// Dates and the stack are taken from front matter when present, otherwise files are
+  /// dated by their modification time. Entries already in their stack are skipped, so
+  /// importing the same files again is safe.
+  Import {
+    #[arg(value_name = "PATH", help = "File or directory to import")]
+    path: PathBuf,
+
+    #[arg(short, long, value_name = "NAME", help = "Import everything into this stack instead")]
+    stack: Option<String>,
+
+    #[arg(long, value_name = "LINE", help = "Split files into several entries on lines equal to LINE, e.g. `---`")]
+    separator: Option<String>,
+
+    #[arg(
+      long,
+      help = "Split files into several entries on headings starting with a date, e.g. `## 2026-10-18`, dated by the heading"
+    )]
+    dated_headings: bool,
+
+    #[arg(long, help = "Show what would be imported without changing the journal")]
+    dry_run: bool,
+  },
+  /// Write every stack, entry, revision and trashed item as versioned JSON Lines, to back up or move the journal.
+  ///
+  /// Entry content is written decrypted.
+  Dump {
+    #[arg(value_name = "FILE", help = "Write to this file instead of stdout")]
+    file: Option<PathBuf>,
+  },
+  /// Restore a dump written by `terajot dump`.
+  ///
+  /// Restoring the same dump again changes nothing. Records keep their ids in an empty journal
+  /// and in the one they were dumped from; into another journal, stacks are matched by day or name.
+  Restore {
+    #[arg(value_name = "FILE", help = "Dump to restore, or `-` to read it from stdin")]
+    file: PathBuf,
+  },
+  /// List or restore the snapshots the TUI takes of the database
+  Backup {
+    #[command(subcommand)]
+    command: BackupCommand,
+  },
+  /// List, restore or purge deleted stacks and entries
+  Trash {
+    #[command(subcommand)]
+    command: TrashCommand,
+  },
+  /// Encrypt the journal with a passphrase, or change the passphrase it is encrypted with.
+  ///
+  /// The new passphrase is read from TERAJOT_NEW_PASSPHRASE, or prompted for twice.
+  Rekey,
+}
+
+/// What `backup` does.
+#[derive(Subcommand, Debug)]
+pub enum BackupCommand {
+  /// List the snapshots, newest first
+  List,
+  /// Replace the database with a snapshot, after snapshotting it so that this can be undone.
+  ///
+  /// Quit the TUI first, it would keep working on the replaced database.
+  Restore {
+    #[arg(value_name = "SNAPSHOT", help = "Name of the snapshot, as listed by `terajot backup list`")]
+    snapshot: String,
+  },
+}
+
+/// What `trash` does.
+#[derive(Subcommand, Debug)]
+pub enum TrashCommand {
+  /// List what was deleted, newest first
+  List {
+    #[arg(long, value_enum, default_value_t)]
+    format: Format,
+  },
+  /// Put a deleted stack or entry back where it was
+  Restore {
+    #[arg(value_name = "ID", help = "Id of the item, as listed by `terajot trash list`")]
+    id: u32,
+  },
+  /// Delete everything in the trash for good
+  Empty,
+}
+
+/// How commands print their results.
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Format {
+  /// Human-readable text
+  #[default]
+  Text,
+  /// A single JSON document
+  Json,
+  /// One JSON object per line
+  Jsonl,
+  /// Tab-separated values, with tabs, newlines and backslashes escaped
+  Tsv,
+}
+
+/// What `export` writes.
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ExportFormat {
+  /// A folder per stack with a Markdown file per entry, with YAML front matter
+  Markdown,
 }
@@ -0,0 +1,707 @@
+use std::{
+    env,
+    fs::File,
+    io::{self, BufRead, BufReader, IsTerminal, Read, Write},
+    path::Path,
+};
+
+use chrono::{DateTime, Local, Utc};
+use color_eyre::eyre::{eyre, Result};
+use serde_json::{json, Value};
+use zeroize::Zeroizing;
+
+use crate::{
+    calendar::Calendar,
+    backup::Backups,
+    cli::{BackupCommand, Command, ExportFormat, Format, TrashCommand},
+    config::Config,
+    crypto::{NEW_PASSPHRASE_ENV, PASSPHRASE_ENV},
+    db_reader::{DbReader, NativeDbReader},
+    dump,
+    export,
+    import::{ImportOptions, ImportPlan},
+    models::{entry::Entry, stack::Stack, trashed::Trashed},
+    query::{self, Query},
+};
+
+const DATE_FORMAT: &str = "%Y-%m-%d %H:%M";
+
+/// Runs a command against the journal without starting the TUI.
+///
+/// Output piped into something that stops reading early, like `head`, ends the command
+/// quietly rather than as an error.
+pub fn run(command: Command, passphrase_fd: Option<i32>) -> Result<()> {
+    match execute(command, passphrase_fd) {
+        Err(e) if is_broken_pipe(&e) => Ok(()),
+        result => result,
+    }
+}
+
+fn is_broken_pipe(error: &color_eyre::eyre::Report) -> bool {
+    error
+        .chain()
+        .any(|cause| cause.downcast_ref::<io::Error>().is_some_and(|e| e.kind() == io::ErrorKind::BrokenPipe))
+}
+
+fn execute(command: Command, passphrase_fd: Option<i32>) -> Result<()> {
+    let config = Config::new()?;
+    let calendar = Calendar::new(&config.journal)?;
+    let db = NativeDbReader::new()?;
+    // Snapshots are copied as they are, encrypted or not.
+    if !matches!(command, Command::Backup { .. }) {
+        unlock(&db, passphrase_fd)?;
+        if let Some(before) = config.trash.purge_before(Utc::now()) {
+            db.purge_trash(before)?;
+        }
+        if config.history.revisions_per_entry > 0 {
+            db.prune_revisions(config.history.revisions_per_entry)?;
+        }
+    }
+    let mut out = io::stdout().lock();
+    match command {
+        Command::Add { stack, content } => add(&db, &calendar, stack.as_deref(), content, io::stdin().lock(), &mut out),
+        Command::Stacks { format } => stacks(&db, &calendar, format, &mut out),
+        Command::Entries { stack, format } => entries(&db, &calendar, &stack, format, &mut out),
+        Command::Search { query, format } => search(&db, &calendar, &query.join(" "), format, &mut out),
+        Command::Show { id, format } => show(&db, id, format, &mut out),
+        Command::Mv { entries, stack } => transfer(&db, &entries, &stack, false, &mut out),
+        Command::Cp { entries, stack } => transfer(&db, &entries, &stack, true, &mut out),
+        Command::Export {
+            format,
+            dir,
+            single_file,
+        } => export(&db, &calendar, format, &dir, single_file, &mut out),
+        Command::Import {
+            path,
+            stack,
+            separator,
+            dated_headings,
+            dry_run,
+        } => {
+            let options = ImportOptions {
+                stack,
+                separator,
+                dated_headings,
+            };
+            import(&db, &calendar, &path, &options, dry_run, &mut out)
+        }
+        Command::Dump { file: None } => dump::dump(&db, &mut out).map(|_| ()),
+        Command::Dump { file: Some(file) } => {
+            let summary = dump::dump(&db, &mut io::BufWriter::new(File::create(&file)?))?;
+            writeln!(
+                out,
+                "Dumped {} stacks, {} entries, {} revisions and {} trashed items to {}",
+                summary.stacks,
+                summary.entries,
+                summary.revisions,
+                summary.trashed,
+                file.display()
+            )?;
+            Ok(())
+        }
+        Command::Restore { file } => match file.as_os_str() == "-" {
+            true => restore(&db, io::stdin().lock(), &mut out),
+            false => restore(&db, BufReader::new(File::open(&file)?), &mut out),
+        },
+        Command::Backup { command } => backup(&db, &Backups::in_data_dir(), command, &mut out),
+        Command::Trash { command } => trash(&db, command, &mut out),
+        Command::Rekey => rekey(&db, &new_passphrase()?, &mut out),
+    }
+}
+
+/// Unlocks an encrypted journal with the passphrase read from `fd`, from
+/// `TERAJOT_PASSPHRASE` or typed at a prompt, in that order.
+fn unlock(db: &dyn DbReader, fd: Option<i32>) -> Result<()> {
+    if !db.is_locked()? {
+        return Ok(());
+    }
+    let passphrase = match (fd, env::var(PASSPHRASE_ENV)) {
+        (Some(fd), _) => read_passphrase_fd(fd)?,
+        (None, Ok(passphrase)) => Zeroizing::new(passphrase),
+        (None, Err(_)) if io::stdin().is_terminal() => Zeroizing::new(rpassword::prompt_password("Passphrase: ")?),
+        (None, Err(_)) => {
+            return Err(eyre!(
+                "The journal is encrypted, unlock it with {PASSPHRASE_ENV} or --passphrase-fd"
+            ))
+        }
+    };
+    db.unlock(&passphrase)
+}
+
+/// Reads the first line of an inherited file descriptor, e.g. `--passphrase-fd 3
+/// 3< <(pass show terajot)`.
+#[cfg(unix)]
+fn read_passphrase_fd(fd: i32) -> Result<Zeroizing<String>> {
+    let content = Zeroizing::new(
+        std::fs::read_to_string(format!("/dev/fd/{fd}"))
+            .map_err(|e| eyre!("Could not read the passphrase from file descriptor {fd}: {e}"))?,
+    );
+    Ok(Zeroizing::new(content.lines().next().unwrap_or_default().to_string()))
+}
+
+#[cfg(not(unix))]
+fn read_passphrase_fd(_fd: i32) -> Result<Zeroizing<String>> {
+    Err(eyre!("--passphrase-fd is only supported on Unix, use {PASSPHRASE_ENV} instead"))
+}
+
+/// Gets the passphrase `rekey` switches to from `TERAJOT_NEW_PASSPHRASE`, or from a
+/// prompt asking for it twice.
+fn new_passphrase() -> Result<Zeroizing<String>> {
+    if let Ok(passphrase) = env::var(NEW_PASSPHRASE_ENV) {
+        return Ok(Zeroizing::new(passphrase));
+    }
+    if !io::stdin().is_terminal() {
+        return Err(eyre!("Set {NEW_PASSPHRASE_ENV} to rekey without a terminal"));
+    }
+    let passphrase = Zeroizing::new(rpassword::prompt_password("New passphrase: ")?);
+    let confirmation = Zeroizing::new(rpassword::prompt_password("Repeat the new passphrase: ")?);
+    if passphrase != confirmation {
+        return Err(eyre!("The passphrases don't match"));
+    }
+    Ok(passphrase)
+}
+
+/// Encrypts the journal, or changes the passphrase of an encrypted one.
+fn rekey(db: &dyn DbReader, passphrase: &str, out: &mut impl Write) -> Result<()> {
+    let encrypted = db.is_encrypted()?;
+    db.set_passphrase(passphrase)?;
+    match encrypted {
+        true => writeln!(out, "Changed the passphrase")?,
+        false => writeln!(
+            out,
+            "Encrypted the journal. Copies made before, such as backups, are still readable."
+        )?,
+    }
+    Ok(())
+}
+
+/// Adds an entry to the named stack, or to today's stack without a name. Content `-`
+/// is read from `input`.
+fn add(
+    db: &dyn DbReader,
+    calendar: &Calendar,
+    stack: Option<&str>,
+    content: String,
+    mut input: impl Read,
+    out: &mut impl Write,
+) -> Result<()> {
+    let content = if content == "-" {
+        let mut buffer = String::new();
+        input.read_to_string(&mut buffer)?;
+        buffer.trim_end_matches(['\n', '\r']).to_string()
+    } else {
+        content
+    };
+    if content.trim().is_empty() {
+        return Err(eyre!("Refusing to add an empty entry"));
+    }
+
+    let stack = match stack {
+        Some(name) => db.get_or_create_named_stack(name)?,
+        None => db.get_or_create_day_stack(calendar.today())?,
+    };
+    let entry = db.save_entry(&Entry::new(stack.id, content))?;
+    writeln!(out, "Added entry {} to {}", entry.id, stack.name)?;
+    Ok(())
+}
+
+fn stacks(db: &dyn DbReader, calendar: &Calendar, format: Format, out: &mut impl Write) -> Result<()> {
+    let mut stacks = db.get_stacks()?;
+    query::count_smart_stacks(db, calendar, &mut stacks);
+    Stack::sort_for_display(&mut stacks);
+    print(format, &stacks, out)
+}
+
+fn entries(db: &dyn DbReader, calendar: &Calendar, stack: &str, format: Format, out: &mut impl Write) -> Result<()> {
+    let stack = db.find_stack(stack)?.ok_or_else(|| eyre!("There is no stack named `{stack}`"))?;
+    print(format, &query::stack_entries(db, calendar, &stack)?, out)
+}
+
+fn search(db: &dyn DbReader, calendar: &Calendar, query: &str, format: Format, out: &mut impl Write) -> Result<()> {
+    let query = Query::parse(query).map_err(|e| eyre!("Invalid query: {e}"))?;
+    print(format, &query.run(db, calendar)?, out)
+}
+
+/// Moves entries to another stack, or copies them there, all at once.
+fn transfer(db: &dyn DbReader, ids: &[u32], stack: &str, copy: bool, out: &mut impl Write) -> Result<()> {
+    let entries = db.get_entries_by_ids(ids)?;
+    if let Some(id) = ids.iter().find(|id| !entries.iter().any(|e| e.id == **id)) {
+        return Err(eyre!("There is no entry {id}"));
+    }
+    let stack = db.find_stack(stack)?.ok_or_else(|| eyre!("There is no stack named `{stack}`"))?;
+    let joined = |entries: &[Entry]| entries.iter().map(|e| e.id.to_string()).collect::<Vec<_>>().join(", ");
+    let noun = if ids.len() == 1 { "entry" } else { "entries" };
+    if copy {
+        let copies = db.copy_entries(&entries, &stack)?;
+        writeln!(out, "Copied {noun} {} to {} as {noun} {}", joined(&entries), stack.name, joined(&copies))?;
+    } else {
+        db.move_entries(&entries, &stack)?;
+        writeln!(out, "Moved {noun} {} to {}", joined(&entries), stack.name)?;
+    }
+    Ok(())
+}
+
+fn show(db: &dyn DbReader, id: u32, format: Format, out: &mut impl Write) -> Result<()> {
+    let entry = db.get_entry(id)?.ok_or_else(|| eyre!("There is no entry {id}"))?;
+    match format {
+        Format::Text => {
+            let stack = db.get_stack(entry.stack_id)?.map(|s| s.name).unwrap_or_default();
+            writeln!(out, "Entry {} in {stack}", entry.id)?;
+            writeln!(
+                out,
+                "Created {}, updated {}",
+                local(entry.created_at),
+                local(entry.updated_at)
+            )?;
+            writeln!(out)?;
+            writeln!(out, "{}", entry.content)?;
+        }
+        Format::Json => writeln!(out, "{}", serde_json::to_string_pretty(&entry.to_json())?)?,
+        _ => print(format, &[entry], out)?,
+    }
+    Ok(())
+}
+
+fn export(
+    db: &dyn DbReader,
+    calendar: &Calendar,
+    format: ExportFormat,
+    dir: &Path,
+    single_file: bool,
+    out: &mut impl Write,
+) -> Result<()> {
+    let summary = match format {
+        ExportFormat::Markdown => export::export_markdown(db, calendar, dir, single_file)?,
+    };
+    writeln!(
+        out,
+        "Exported {} entries from {} stacks to {}",
+        summary.entries,
+        summary.stacks,
+        dir.display()
+    )?;
+    Ok(())
+}
+
+/// Imports files, or with `dry_run` lists what would be imported.
+fn import(
+    db: &dyn DbReader,
+    calendar: &Calendar,
+    path: &Path,
+    options: &ImportOptions,
+    dry_run: bool,
+    out: &mut impl Write,
+) -> Result<()> {
+    let plan = ImportPlan::new(db, calendar, path, options)?;
+    let new = plan.new_entries().count();
+    let duplicates = plan.entries.len() - new;
+    if dry_run {
+        for found in &plan.entries {
+            let first_line = found.content.lines().next().unwrap_or_default();
+            let marker = if found.duplicate { "=" } else { "+" };
+            writeln!(out, "{marker} {}  {}  {first_line}", found.stack, local(found.created_at))?;
+        }
+        for path in &plan.ignored {
+            writeln!(out, "? {}", path.display())?;
+        }
+        writeln!(
+            out,
+            "Would import {new} entries into {} stacks ({duplicates} already there, {} other files ignored)",
+            plan.stack_count(),
+            plan.ignored.len()
+        )?;
+        return Ok(());
+    }
+
+    plan.apply(db)?;
+    writeln!(
+        out,
+        "Imported {new} entries into {} stacks ({duplicates} already there, {} other files ignored)",
+        plan.stack_count(),
+        plan.ignored.len()
+    )?;
+    Ok(())
+}
+
+fn restore(db: &dyn DbReader, input: impl BufRead, out: &mut impl Write) -> Result<()> {
+    let summary = dump::restore(db, dump::read(input)?)?;
+    writeln!(
+        out,
+        "Restored {} stacks, {} entries, {} revisions and {} trashed items, {} of them unchanged",
+        summary.stacks, summary.entries, summary.revisions, summary.trashed, summary.unchanged
+    )?;
+    Ok(())
+}
+
+fn backup(db: &dyn DbReader, backups: &Backups, command: BackupCommand, out: &mut impl Write) -> Result<()> {
+    match command {
+        BackupCommand::List => {
+            for snapshot in backups.list()? {
+                writeln!(
+                    out,
+                    "{}  {}  {:.1} KiB",
+                    snapshot.name(),
+                    local(snapshot.taken_at),
+                    snapshot.size as f64 / 1024.0
+                )?;
+            }
+        }
+        BackupCommand::Restore { snapshot } => {
+            let snapshot = backups.find(&snapshot)?;
+            let before = backups.restore(db, &snapshot)?;
+            writeln!(
+                out,
+                "Restored {}. The journal as it was is in {}",
+                snapshot.name(),
+                before.name()
+            )?;
+        }
+    }
+    Ok(())
+}
+
+fn trash(db: &dyn DbReader, command: TrashCommand, out: &mut impl Write) -> Result<()> {
+    match command {
+        TrashCommand::List { format } => {
+            let mut trash = db.get_trash()?;
+            trash.sort_by_key(|t| std::cmp::Reverse((t.deleted_at, t.id)));
+            print(format, &trash, out)?;
+        }
+        TrashCommand::Restore { id } => {
+            let trashed = db.restore_trashed(id)?;
+            writeln!(out, "Restored {}", trashed.description())?;
+        }
+        TrashCommand::Empty => {
+            let ids = db.get_trash()?.into_iter().map(|t| t.id).collect::<Vec<_>>();
+            db.remove_trashed_items(&ids)?;
+            writeln!(out, "Deleted {} items for good", ids.len())?;
+        }
+    }
+    Ok(())
+}
+
+fn local(at: DateTime<Utc>) -> String {
+    at.with_timezone(&Local).format(DATE_FORMAT).to_string()
+}
+
+/// Something that can be listed in any output format.
+trait Record {
+    fn to_json(&self) -> Value;
+    fn to_tsv(&self) -> Vec<String>;
+    fn to_text(&self) -> String;
+}
+
+impl Record for Stack {
+    fn to_json(&self) -> Value {
+        json!({ "id": self.id, "name": self.name, "day": self.day, "query": self.query, "count": self.count })
+    }
+
+    fn to_tsv(&self) -> Vec<String> {
+        vec![
+            self.id.to_string(),
+            self.name.clone(),
+            self.day.map(|d| d.to_string()).unwrap_or_default(),
+            self.query.clone().unwrap_or_default(),
+            self.count.to_string(),
+        ]
+    }
+
+    fn to_text(&self) -> String {
+        match &self.query {
+            Some(query) => format!("{:>4}  {} ({}) = {query}", self.id, self.name, self.count),
+            None => format!("{:>4}  {} ({})", self.id, self.name, self.count),
+        }
+    }
+}
+
+impl Record for Entry {
+    fn to_json(&self) -> Value {
+        json!({
+            "id": self.id,
+            "stack_id": self.stack_id,
+            "content": self.content,
+            "created_at": self.created_at,
+            "updated_at": self.updated_at,
+        })
+    }
+
+    fn to_tsv(&self) -> Vec<String> {
+        vec![
+            self.id.to_string(),
+            self.stack_id.to_string(),
+            self.created_at.to_rfc3339(),
+            self.updated_at.to_rfc3339(),
+            self.content.clone(),
+        ]
+    }
+
+    fn to_text(&self) -> String {
+        let first_line = self.content.lines().next().unwrap_or_default();
+        let more = if self.content.trim_end().contains('\n') { " …" } else { "" };
+        format!("{:>4}  {}  {first_line}{more}", self.id, local(self.created_at))
+    }
+}
+
+impl Record for Trashed {
+    fn to_json(&self) -> Value {
+        json!({
+            "id": self.id,
+            "deleted_at": self.deleted_at,
+            "stack": self.stack.name,
+            "whole_stack": self.whole_stack,
+            "entries": self.entries.len(),
+            "description": self.description(),
+        })
+    }
+
+    fn to_tsv(&self) -> Vec<String> {
+        vec![
+            self.id.to_string(),
+            self.deleted_at.to_rfc3339(),
+            self.stack.name.clone(),
+            self.whole_stack.to_string(),
+            self.entries.len().to_string(),
+            self.description(),
+        ]
+    }
+
+    fn to_text(&self) -> String {
+        format!("{:>4}  {}  {}", self.id, local(self.deleted_at), self.description())
+    }
+}
+
+fn print(format: Format, records: &[impl Record], out: &mut impl Write) -> Result<()> {
+    match format {
+        Format::Text => {
+            for record in records {
+                writeln!(out, "{}", record.to_text())?;
+            }
+        }
+        Format::Json => {
+            let records = records.iter().map(Record::to_json).collect::<Vec<_>>();
+            writeln!(out, "{}", serde_json::to_string_pretty(&records)?)?;
+        }
+        Format::Jsonl => {
+            for record in records {
+                writeln!(out, "{}", record.to_json())?;
+            }
+        }
+        Format::Tsv => {
+            for record in records {
+                let fields = record.to_tsv().iter().map(|f| escape_tsv(f)).collect::<Vec<_>>();
+                writeln!(out, "{}", fields.join("\t"))?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Escapes the characters that would break a TSV row, the way `COPY ... TO` does.
+fn escape_tsv(field: &str) -> String {
+    field.replace('\\', "\\\\").replace('\t', "\\t").replace('\n', "\\n").replace('\r', "\\r")
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+    use crate::db_reader::MemoryDbReader;
+
+    #[test]
+    fn test_add_to_today() -> Result<()> {
+        let db = MemoryDbReader::new();
+        let calendar = Calendar::default();
+        let mut out = Vec::new();
+        add(&db, &calendar, None, "call Bob at 555-1234".to_string(), io::empty(), &mut out)?;
+
+        let stack = db.get_day_stack(calendar.today())?.unwrap();
+        assert_eq!(String::from_utf8(out)?, format!("Added entry 1 to {}\n", stack.name));
+        assert_eq!(db.get_entries(stack.id)?[0].content, "call Bob at 555-1234");
+        Ok(())
+    }
+
+    #[test]
+    fn test_add_from_stdin_to_named_stack() -> Result<()> {
+        let db = MemoryDbReader::new();
+        let mut out = Vec::new();
+        let input = "line one\nline two\n".as_bytes();
+        add(&db, &Calendar::default(), Some("ideas"), "-".to_string(), input, &mut out)?;
+        add(&db, &Calendar::default(), Some("ideas"), "another".to_string(), io::empty(), &mut out)?;
+
+        let stacks = db.get_stacks()?;
+        assert_eq!(stacks.len(), 1);
+        assert_eq!(stacks[0].name, "ideas");
+        assert_eq!(stacks[0].count, 2);
+        assert_eq!(db.get_entry(1)?.unwrap().content, "line one\nline two");
+        Ok(())
+    }
+
+    fn output(run: impl FnOnce(&mut Vec<u8>) -> Result<()>) -> Result<String> {
+        let mut out = Vec::new();
+        run(&mut out)?;
+        Ok(String::from_utf8(out)?)
+    }
+
+    fn sample_db() -> Result<MemoryDbReader> {
+        let db = MemoryDbReader::new();
+        let stack = db.save_stack(&Stack::named("ideas".to_string()))?;
+        db.save_entry(&Entry::new(stack.id, "first\tthought".to_string()))?;
+        db.save_entry(&Entry::new(stack.id, "second\nthought".to_string()))?;
+        Ok(db)
+    }
+
+    #[test]
+    fn test_stacks_as_jsonl() -> Result<()> {
+        let out = output(|out| stacks(&sample_db()?, &Calendar::default(), Format::Jsonl, out))?;
+        assert_eq!(out, "{\"count\":2,\"day\":null,\"id\":1,\"name\":\"ideas\",\"query\":null}\n");
+        Ok(())
+    }
+
+    #[test]
+    fn test_smart_stacks_list_their_matches() -> Result<()> {
+        let db = sample_db()?;
+        let calendar = Calendar::default();
+        db.save_stack(&Stack::smart("firsts".to_string(), "first".to_string()))?;
+        let out = output(|out| stacks(&db, &calendar, Format::Text, out))?;
+        assert_eq!(out, "   2  firsts (1) = first\n   1  ideas (2)\n");
+
+        let out = output(|out| entries(&db, &calendar, "firsts", Format::Tsv, out))?;
+        assert_eq!(out.lines().map(|l| &l[..2]).collect::<Vec<_>>(), vec!["1\t"]);
+        let error = add(&db, &calendar, Some("firsts"), "x".to_string(), io::empty(), &mut Vec::new());
+        assert!(error.is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_entries_by_name_or_id() -> Result<()> {
+        let db = sample_db()?;
+        let calendar = Calendar::default();
+        let by_name = output(|out| entries(&db, &calendar, "ideas", Format::Tsv, out))?;
+        let by_id = output(|out| entries(&db, &calendar, "1", Format::Tsv, out))?;
+        assert_eq!(by_name, by_id);
+        let contents = by_name.lines().map(|l| l.rsplit('\t').next().unwrap()).collect::<Vec<_>>();
+        assert_eq!(contents, vec!["first\\tthought", "second\\nthought"]);
+        assert!(entries(&db, &calendar, "missing", Format::Text, &mut Vec::new()).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_search_lists_matches() -> Result<()> {
+        let db = sample_db()?;
+        let calendar = Calendar::default();
+        let out = output(|out| search(&db, &calendar, "second", Format::Tsv, out))?;
+        assert_eq!(out.lines().map(|l| &l[..2]).collect::<Vec<_>>(), vec!["2\t"]);
+        let out = output(|out| search(&db, &calendar, "stack:ideas -/^first/", Format::Tsv, out))?;
+        assert_eq!(out.lines().map(|l| &l[..2]).collect::<Vec<_>>(), vec!["2\t"]);
+        assert!(search(&db, &calendar, "\"unclosed", Format::Text, &mut Vec::new()).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_import_dry_run_changes_nothing() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        std::fs::write(dir.path().join("notes.md"), "first\n---\nsecond\n---\nfirst\n")?;
+        let db = MemoryDbReader::new();
+        let calendar = Calendar::default();
+        let options = ImportOptions {
+            stack: Some("ideas".to_string()),
+            separator: Some("---".to_string()),
+            ..Default::default()
+        };
+        let path = dir.path();
+
+        let out = output(|out| import(&db, &calendar, path, &options, true, out))?;
+        let markers = out.lines().map(|l| &l[..8]).collect::<Vec<_>>();
+        assert_eq!(markers, vec!["+ ideas ", "+ ideas ", "= ideas ", "Would im"]);
+        assert!(out.ends_with("Would import 2 entries into 1 stacks (1 already there, 0 other files ignored)\n"));
+        assert!(db.get_stacks()?.is_empty());
+
+        output(|out| import(&db, &calendar, path, &options, false, out))?;
+        assert_eq!(db.count_all_entries()?, 2);
+        Ok(())
+    }
+
+    #[test]
+    fn test_trash_restore_and_empty() -> Result<()> {
+        let db = sample_db()?;
+        db.trash_entry(&db.get_entry(1)?.unwrap())?;
+        db.trash_entry(&db.get_entry(2)?.unwrap())?;
+        let out = output(|out| trash(&db, TrashCommand::List { format: Format::Tsv }, out))?;
+        assert_eq!(out.lines().map(|l| l.split('\t').next().unwrap()).collect::<Vec<_>>(), vec!["2", "1"]);
+
+        let out = output(|out| trash(&db, TrashCommand::Restore { id: 1 }, out))?;
+        assert_eq!(out, "Restored first\tthought (in ideas)\n");
+        assert_eq!(db.get_entry(1)?.map(|e| e.stack_id), Some(1));
+        let out = output(|out| trash(&db, TrashCommand::Empty, out))?;
+        assert_eq!(out, "Deleted 1 items for good\n");
+        assert_eq!(db.get_trash()?, vec![]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_move_and_copy_between_stacks() -> Result<()> {
+        let db = sample_db()?;
+        let filed = db.save_stack(&Stack::named("filed".to_string()))?;
+        let out = output(|out| transfer(&db, &[1], "filed", false, out))?;
+        assert_eq!(out, "Moved entry 1 to filed\n");
+        let out = output(|out| transfer(&db, &[2], &filed.id.to_string(), true, out))?;
+        assert_eq!(out, "Copied entry 2 to filed as entry 3\n");
+
+        let counts = db.get_stacks()?.into_iter().map(|s| (s.name, s.count)).collect::<Vec<_>>();
+        assert_eq!(counts, vec![("ideas".to_string(), 1), ("filed".to_string(), 2)]);
+        let copy = db.get_entry(3)?.unwrap();
+        assert_eq!((copy.content.as_str(), copy.created_at), ("second\nthought", db.get_entry(2)?.unwrap().created_at));
+        assert!(transfer(&db, &[3], "filed", true, &mut Vec::new()).is_err());
+        assert!(transfer(&db, &[3], "nowhere", false, &mut Vec::new()).is_err());
+
+        // Copying several entries writes all of them or none.
+        let out = output(|out| transfer(&db, &[1, 3], "ideas", true, out))?;
+        assert_eq!(out, "Copied entries 1, 3 to ideas as entries 4, 5\n");
+        assert!(transfer(&db, &[4, 3], "filed", true, &mut Vec::new()).is_err());
+        assert!(transfer(&db, &[4, 9], "filed", true, &mut Vec::new()).is_err());
+        assert_eq!(db.count_all_entries()?, 5);
+        Ok(())
+    }
+
+    #[test]
+    fn test_rekey() -> Result<()> {
+        let db = sample_db()?;
+        let out = output(|out| rekey(&db, "secret", out))?;
+        assert!(out.starts_with("Encrypted the journal."));
+        db.lock();
+        assert!(unlock(&db, Some(-1)).is_err());
+        db.unlock("secret")?;
+        assert_eq!(output(|out| rekey(&db, "other", out))?, "Changed the passphrase\n");
+        db.lock();
+        assert!(db.unlock("secret").is_err());
+        db.unlock("other")?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_show_as_json() -> Result<()> {
+        let db = sample_db()?;
+        let out = output(|out| show(&db, 2, Format::Json, out))?;
+        let value: Value = serde_json::from_str(&out)?;
+        assert_eq!(value["content"], "second\nthought");
+        assert_eq!(value["stack_id"], 1);
+        assert!(show(&db, 3, Format::Json, &mut Vec::new()).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_broken_pipe_is_not_an_error() {
+        let error = color_eyre::eyre::Report::from(io::Error::from(io::ErrorKind::BrokenPipe));
+        assert!(is_broken_pipe(&error));
+        assert!(!is_broken_pipe(&eyre!("There is no entry 1")));
+    }
+
+    #[test]
+    fn test_add_rejects_empty_content() {
+        let db = MemoryDbReader::new();
+        let result = add(&db, &Calendar::default(), None, "-".to_string(), "\n".as_bytes(), &mut Vec::new());
+        assert!(result.is_err());
+        assert!(db.get_stacks().unwrap().is_empty());
+    }
+}
@@ -1,3 +1,5 @@
+use std::sync::Arc;
+
 use color_eyre::eyre::Result;
 use crossterm::event::{KeyEvent, MouseEvent};
 use ratatui::layout::Rect;
@@ -6,11 +8,20 @@ use tokio::sync::mpsc::UnboundedSender;
 use crate::{
   action::Action,
   config::Config,
+  db_reader::DbReader,
   tui::{Event, Frame},
 };
 
+pub mod auto_backup;
+pub mod command_line;
+pub mod editor;
 pub mod fps;
+pub mod history_view;
 pub mod home;
+pub mod quick_switcher;
+pub mod search_overlay;
+pub mod stack_picker;
+pub mod trash_view;
 
 /// `Component` is a trait that represents a visual and interactive element of the user interface.
 /// Implementors of this trait can be registered with the main application loop and will be able to receive events,
@@ -42,6 +53,19 @@ pub trait Component {
   fn register_config_handler(&mut self, config: Config) -> Result<()> {
     Ok(())
   }
+  /// Register a database handler that provides access to the stored models if necessary.
+  ///
+  /// # Arguments
+  ///
+  /// * `db` - The storage backend to read from and write to.
+  ///
+  /// # Returns
+  ///
+  /// * `Result<()>` - An Ok result or an error.
+  #[allow(unused_variables)]
+  fn register_db_handler(&mut self, db: Arc<dyn DbReader>) -> Result<()> {
+    Ok(())
+  }
   /// Initialize the component with a specified area if necessary.
   ///
   /// # Arguments
@@ -122,3 +146,12 @@ pub trait Component {
   /// * `Result<()>` - An Ok result or an error.
   fn draw(&mut self, f: &mut Frame<'_>, area: Rect) -> Result<()>;
 }
+
+/// Gets a rectangle centered in `area`, taking the given percentages of its size. Used
+/// for popups drawn over the other components.
+pub fn popup_area(area: Rect, percent_x: u16, percent_y: u16) -> Rect {
+  let scale = |length: u16, percent: u16| (u32::from(length) * u32::from(percent.min(100)) / 100) as u16;
+  let width = scale(area.width, percent_x);
+  let height = scale(area.height, percent_y);
+  Rect::new(area.x + (area.width - width) / 2, area.y + (area.height - height) / 2, width, height)
+}
@@ -0,0 +1,124 @@
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use color_eyre::eyre::{eyre, Result};
+use ratatui::layout::Rect;
+
+use super::Component;
+use crate::{
+    action::Action,
+    backup::Backups,
+    calendar::Calendar,
+    config::{BackupConfig, Config},
+    db_reader::DbReader,
+    tui::Frame,
+};
+
+/// Snapshots the database when the TUI starts, then every `backup.interval_minutes`
+/// while it runs. Draws nothing; failures are reported as `Action::Error`.
+pub struct AutoBackup {
+    backups: Backups,
+    config: BackupConfig,
+    calendar: Calendar,
+    db: Option<Arc<dyn DbReader>>,
+    last_snapshot: Option<Instant>,
+}
+
+impl AutoBackup {
+    pub fn new(backups: Backups) -> Self {
+        Self {
+            backups,
+            config: BackupConfig::default(),
+            calendar: Calendar::default(),
+            db: None,
+            last_snapshot: None,
+        }
+    }
+
+    fn interval(&self) -> Option<Duration> {
+        match self.config.interval_minutes {
+            0 => None,
+            minutes => Some(Duration::from_secs(u64::from(minutes) * 60)),
+        }
+    }
+
+    fn rotate(&mut self) -> Option<Action> {
+        self.last_snapshot = Some(Instant::now());
+        let result = match &self.db {
+            Some(db) => self.backups.rotate(db.as_ref(), &self.config, &self.calendar),
+            None => Err(eyre!("No database has been registered")),
+        };
+        match result {
+            Ok(_) => None,
+            Err(e) => {
+                log::error!("Failed to back up the journal: {e:?}");
+                Some(Action::Error(format!("Failed to back up the journal: {e}")))
+            }
+        }
+    }
+}
+
+impl Component for AutoBackup {
+    fn register_config_handler(&mut self, config: Config) -> Result<()> {
+        self.calendar = Calendar::new(&config.journal)?;
+        self.config = config.backup;
+        Ok(())
+    }
+
+    fn register_db_handler(&mut self, db: Arc<dyn DbReader>) -> Result<()> {
+        self.db = Some(db);
+        Ok(())
+    }
+
+    fn update(&mut self, action: Action) -> Result<Option<Action>> {
+        if action != Action::Tick || !self.config.enabled {
+            return Ok(None);
+        }
+        let due = match (self.last_snapshot, self.interval()) {
+            (None, _) => true,
+            (Some(last), Some(interval)) => last.elapsed() >= interval,
+            (Some(_), None) => false,
+        };
+        Ok(if due { self.rotate() } else { None })
+    }
+
+    fn draw(&mut self, _f: &mut Frame<'_>, _area: Rect) -> Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+    use crate::db_reader::{MemoryDbReader, NativeDbReader};
+
+    #[test]
+    fn test_snapshots_on_the_first_tick_then_on_interval() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let mut auto = AutoBackup::new(Backups::new(dir.path().join("backups")));
+        auto.register_db_handler(Arc::new(NativeDbReader::open(dir.path().join("terajot.db"))?))?;
+        assert_eq!(auto.update(Action::Tick)?, None);
+        assert_eq!(auto.backups.list()?.len(), 1);
+
+        let taken = auto.last_snapshot;
+        auto.update(Action::Tick)?;
+        assert_eq!(auto.last_snapshot, taken);
+        auto.last_snapshot = Some(Instant::now() - Duration::from_secs(60 * 60));
+        auto.update(Action::Tick)?;
+        assert!(auto.last_snapshot > taken);
+        Ok(())
+    }
+
+    #[test]
+    fn test_failures_are_reported() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let mut auto = AutoBackup::new(Backups::new(dir.path()));
+        auto.register_db_handler(Arc::new(MemoryDbReader::new()))?;
+        assert!(matches!(auto.update(Action::Tick)?, Some(Action::Error(_))));
+        Ok(())
+    }
+}
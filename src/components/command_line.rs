@@ -0,0 +1,310 @@
+use std::path::PathBuf;
+
+use color_eyre::eyre::{eyre, Result};
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use ratatui::{prelude::*, widgets::*};
+
+use crate::tui::Frame;
+
+/// A command typed on the `:` command line.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ExCommand {
+    /// Opens the editor for a new entry, in the given stack or today's one.
+    New(Option<String>),
+    /// Renames the selected stack.
+    Rename(String),
+    /// Moves the marked or selected entries to another stack.
+    Move(String),
+    /// Copies the marked or selected entries to another stack.
+    Copy(String),
+    /// Saves a search query as a smart stack.
+    Smart(String),
+    /// Moves the marked or selected entries, or the selected stack while browsing stacks,
+    /// to the trash.
+    Delete,
+    /// Merges the marked entries into the earliest of them.
+    Merge,
+    /// Shows what was deleted, to restore it.
+    Trash,
+    /// Writes the selected stack, or only its marked entries, to a Markdown file. Without a
+    /// path, it goes to `exports/` in the data directory, named after the stack.
+    Export(Option<PathBuf>),
+    Quit,
+}
+
+impl ExCommand {
+    pub const NAMES: [&'static str; 10] =
+        ["new", "rename", "move", "copy", "smart", "delete", "merge", "trash", "export", "quit"];
+
+    /// Parses a command line, without its leading `:`.
+    ///
+    /// Arguments are the rest of the line, so stack names can contain spaces.
+    pub fn parse(line: &str) -> Result<Self> {
+        let line = line.trim();
+        let (name, argument) = match line.split_once(char::is_whitespace) {
+            Some((name, argument)) => (name, Some(argument.trim().to_string())),
+            None => (line, None),
+        };
+        let required = |what: &str| argument.clone().ok_or_else(|| eyre!(":{name} needs {what}"));
+        let no_argument = |command| match &argument {
+            Some(_) => Err(eyre!(":{name} takes no argument")),
+            None => Ok(command),
+        };
+
+        match name {
+            "new" => Ok(Self::New(argument.clone())),
+            "rename" => Ok(Self::Rename(required("a new name")?)),
+            "move" => Ok(Self::Move(required("a stack")?)),
+            "copy" => Ok(Self::Copy(required("a stack")?)),
+            "smart" => Ok(Self::Smart(required("a search query")?)),
+            "delete" => no_argument(Self::Delete),
+            "merge" => no_argument(Self::Merge),
+            "trash" => no_argument(Self::Trash),
+            "export" => Ok(Self::Export(argument.clone().map(PathBuf::from))),
+            "quit" | "q" => no_argument(Self::Quit),
+            "" => Err(eyre!("Type a command, e.g. :new")),
+            _ => Err(eyre!("Unknown command :{name}")),
+        }
+    }
+
+    /// Whether the argument of the command names a stack, so it can be completed.
+    fn takes_stack(name: &str) -> bool {
+        matches!(name, "new" | "move" | "copy")
+    }
+}
+
+/// What the user decided to do with the command line.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CommandLineEvent {
+    Submit(String),
+    Cancel,
+}
+
+/// Tab completion in progress: the text before the completed word and the candidates
+/// that are cycled through.
+#[derive(Debug, Clone, Default)]
+struct Completion {
+    prefix: String,
+    candidates: Vec<String>,
+    index: usize,
+}
+
+/// A single-line input for ex-style commands, with history and tab completion.
+#[derive(Debug, Clone, Default)]
+pub struct CommandLine {
+    input: String,
+    /// Cursor position in characters.
+    cursor: usize,
+    history: Vec<String>,
+    /// Position while browsing the history; `None` while editing a fresh line.
+    history_index: Option<usize>,
+    completion: Option<Completion>,
+}
+
+impl CommandLine {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn input(&self) -> &str {
+        &self.input
+    }
+
+    /// Clears the input, keeping the history.
+    pub fn reset(&mut self) {
+        self.set_input(String::new());
+        self.history_index = None;
+    }
+
+    /// Handles a key, completing stack arguments from `stack_names`.
+    pub fn handle_key_events(&mut self, key: KeyEvent, stack_names: &[String]) -> Option<CommandLineEvent> {
+        if key.code != KeyCode::Tab {
+            self.completion = None;
+        }
+        match key.code {
+            KeyCode::Esc => return Some(CommandLineEvent::Cancel),
+            KeyCode::Enter => {
+                let line = self.input.clone();
+                if !line.trim().is_empty() && self.history.last() != Some(&line) {
+                    self.history.push(line.clone());
+                }
+                self.reset();
+                return Some(CommandLineEvent::Submit(line));
+            }
+            KeyCode::Char(c) if !key.modifiers.contains(KeyModifiers::CONTROL) => {
+                let offset = self.byte_offset(self.cursor);
+                self.input.insert(offset, c);
+                self.cursor += 1;
+            }
+            KeyCode::Backspace if self.cursor > 0 => {
+                self.cursor -= 1;
+                self.input.remove(self.byte_offset(self.cursor));
+            }
+            // Like in vim, erasing past the start of an empty line leaves it.
+            KeyCode::Backspace if self.input.is_empty() => return Some(CommandLineEvent::Cancel),
+            KeyCode::Delete if self.cursor < self.input.chars().count() => {
+                self.input.remove(self.byte_offset(self.cursor));
+            }
+            KeyCode::Left => self.cursor = self.cursor.saturating_sub(1),
+            KeyCode::Right => self.cursor = (self.cursor + 1).min(self.input.chars().count()),
+            KeyCode::Home => self.cursor = 0,
+            KeyCode::End => self.cursor = self.input.chars().count(),
+            KeyCode::Up => self.browse_history(-1),
+            KeyCode::Down => self.browse_history(1),
+            KeyCode::Tab => self.complete(stack_names),
+            _ => {}
+        }
+        None
+    }
+
+    pub fn draw(&mut self, frame: &mut Frame<'_>, area: Rect, block: Block) {
+        let inner = block.inner(area);
+        let prompt = format!(":{}", self.input);
+        frame.render_widget(Paragraph::new(prompt).block(block), area);
+        let x = inner.x.saturating_add(u16::try_from(self.cursor + 1).unwrap_or(u16::MAX));
+        frame.set_cursor(x.min(inner.right().saturating_sub(1)), inner.y);
+    }
+
+    fn byte_offset(&self, cursor: usize) -> usize {
+        self.input.char_indices().nth(cursor).map(|(i, _)| i).unwrap_or(self.input.len())
+    }
+
+    fn set_input(&mut self, input: String) {
+        self.cursor = input.chars().count();
+        self.input = input;
+    }
+
+    fn browse_history(&mut self, delta: isize) {
+        if self.history.is_empty() {
+            return;
+        }
+        let index = match self.history_index {
+            Some(index) => index.checked_add_signed(delta),
+            None if delta < 0 => Some(self.history.len() - 1),
+            None => return,
+        };
+        match index.filter(|i| *i < self.history.len()) {
+            Some(index) => {
+                self.history_index = Some(index);
+                self.set_input(self.history[index].clone());
+            }
+            // Going down past the newest entry returns to an empty line.
+            None if delta > 0 => self.reset(),
+            None => {}
+        }
+    }
+
+    /// Completes the command name, or the stack argument of commands that take one.
+    /// Pressing Tab again cycles through the other candidates.
+    fn complete(&mut self, stack_names: &[String]) {
+        if let Some(completion) = self.completion.as_mut() {
+            completion.index = (completion.index + 1) % completion.candidates.len();
+            let input = format!("{}{}", completion.prefix, completion.candidates[completion.index]);
+            self.set_input(input);
+            return;
+        }
+
+        let (prefix, word, candidates): (String, &str, Vec<String>) = match self.input.split_once(' ') {
+            None => (String::new(), &self.input, ExCommand::NAMES.iter().map(|n| n.to_string()).collect()),
+            Some((name, argument)) if ExCommand::takes_stack(name) => {
+                (format!("{name} "), argument.trim_start(), stack_names.to_vec())
+            }
+            Some(_) => return,
+        };
+        let word = word.to_lowercase();
+        let candidates = candidates
+            .into_iter()
+            .filter(|c| c.to_lowercase().starts_with(&word))
+            .collect::<Vec<_>>();
+        if candidates.is_empty() {
+            return;
+        }
+        self.set_input(format!("{prefix}{}", candidates[0]));
+        if candidates.len() > 1 {
+            self.completion = Some(Completion {
+                prefix,
+                candidates,
+                index: 0,
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    fn press(line: &mut CommandLine, code: KeyCode, stack_names: &[String]) -> Option<CommandLineEvent> {
+        line.handle_key_events(KeyEvent::new(code, KeyModifiers::empty()), stack_names)
+    }
+
+    fn type_text(line: &mut CommandLine, text: &str) {
+        for c in text.chars() {
+            press(line, KeyCode::Char(c), &[]);
+        }
+    }
+
+    #[test]
+    fn test_parse() -> Result<()> {
+        assert_eq!(ExCommand::parse("new")?, ExCommand::New(None));
+        assert_eq!(ExCommand::parse(" move  work log ")?, ExCommand::Move("work log".to_string()));
+        assert_eq!(ExCommand::parse("export notes.md")?, ExCommand::Export(Some("notes.md".into())));
+        assert_eq!(ExCommand::parse("q")?, ExCommand::Quit);
+        assert_eq!(ExCommand::parse("rename").unwrap_err().to_string(), ":rename needs a new name");
+        assert_eq!(ExCommand::parse("delete all").unwrap_err().to_string(), ":delete takes no argument");
+        assert_eq!(ExCommand::parse("frobnicate").unwrap_err().to_string(), "Unknown command :frobnicate");
+        Ok(())
+    }
+
+    #[test]
+    fn test_history() {
+        let mut line = CommandLine::new();
+        type_text(&mut line, "new");
+        assert_eq!(press(&mut line, KeyCode::Enter, &[]), Some(CommandLineEvent::Submit("new".to_string())));
+        type_text(&mut line, "quit");
+        press(&mut line, KeyCode::Enter, &[]);
+
+        press(&mut line, KeyCode::Up, &[]);
+        assert_eq!(line.input(), "quit");
+        press(&mut line, KeyCode::Up, &[]);
+        press(&mut line, KeyCode::Up, &[]);
+        assert_eq!(line.input(), "new");
+        press(&mut line, KeyCode::Down, &[]);
+        press(&mut line, KeyCode::Down, &[]);
+        assert_eq!(line.input(), "");
+    }
+
+    #[test]
+    fn test_tab_completes_commands_and_stacks() {
+        let stacks = vec!["Work".to_string(), "Workouts".to_string(), "ideas".to_string()];
+        let mut line = CommandLine::new();
+        type_text(&mut line, "mo");
+        press(&mut line, KeyCode::Tab, &stacks);
+        assert_eq!(line.input(), "move");
+
+        type_text(&mut line, " wo");
+        press(&mut line, KeyCode::Tab, &stacks);
+        assert_eq!(line.input(), "move Work");
+        press(&mut line, KeyCode::Tab, &stacks);
+        assert_eq!(line.input(), "move Workouts");
+        press(&mut line, KeyCode::Tab, &stacks);
+        assert_eq!(line.input(), "move Work");
+    }
+
+    #[test]
+    fn test_editing_keys() {
+        let mut line = CommandLine::new();
+        type_text(&mut line, "nëw");
+        press(&mut line, KeyCode::Left, &[]);
+        press(&mut line, KeyCode::Backspace, &[]);
+        type_text(&mut line, "e");
+        assert_eq!(line.input(), "new");
+        press(&mut line, KeyCode::Home, &[]);
+        press(&mut line, KeyCode::Delete, &[]);
+        press(&mut line, KeyCode::Delete, &[]);
+        press(&mut line, KeyCode::Delete, &[]);
+        assert_eq!(press(&mut line, KeyCode::Backspace, &[]), Some(CommandLineEvent::Cancel));
+    }
+}
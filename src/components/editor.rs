@@ -0,0 +1,347 @@
+use std::ops::Range;
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use ratatui::{prelude::*, widgets::*};
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+use crate::tui::Frame;
+
+/// What the user decided to do with the text being edited.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EditorEvent {
+    Save(String),
+    Cancel,
+}
+
+/// A multi-line text editor with soft wrapping.
+///
+/// The cursor is tracked as a logical line and a grapheme index in that line, so
+/// combining characters, emoji and wide CJK characters are moved over and deleted
+/// as a whole.
+#[derive(Debug, Clone)]
+pub struct Editor {
+    title: String,
+    lines: Vec<String>,
+    row: usize,
+    col: usize,
+    /// Display column that Up/Down try to keep when moving through shorter rows.
+    goal_x: Option<usize>,
+    /// Wrap width of the last draw. Zero disables wrapping.
+    width: usize,
+    scroll: usize,
+}
+
+impl Editor {
+    const TAB: &'static str = "    ";
+
+    /// Creates an editor for `content`, with the cursor at its end.
+    pub fn new(title: impl Into<String>, content: &str) -> Self {
+        let mut lines: Vec<String> = content.split('\n').map(String::from).collect();
+        if lines.is_empty() {
+            lines.push(String::new());
+        }
+        let row = lines.len() - 1;
+        let col = lines[row].graphemes(true).count();
+        Self {
+            title: title.into(),
+            lines,
+            row,
+            col,
+            goal_x: None,
+            width: 0,
+            scroll: 0,
+        }
+    }
+
+    pub fn content(&self) -> String {
+        self.lines.join("\n")
+    }
+
+    pub fn handle_key_events(&mut self, key: KeyEvent) -> Option<EditorEvent> {
+        let control = key.modifiers.contains(KeyModifiers::CONTROL);
+        match key.code {
+            KeyCode::Esc => return Some(EditorEvent::Cancel),
+            KeyCode::Char('s') if control => return Some(EditorEvent::Save(self.content())),
+            KeyCode::Char(c) if !control && !key.modifiers.contains(KeyModifiers::ALT) => {
+                self.insert(c.encode_utf8(&mut [0; 4]))
+            }
+            KeyCode::Tab => self.insert(Self::TAB),
+            KeyCode::Enter => self.insert_newline(),
+            KeyCode::Backspace => self.backspace(),
+            KeyCode::Delete => self.delete(),
+            KeyCode::Left => self.move_left(),
+            KeyCode::Right => self.move_right(),
+            KeyCode::Up => return self.move_vertically(-1),
+            KeyCode::Down => return self.move_vertically(1),
+            KeyCode::Home => self.col = 0,
+            KeyCode::End => self.col = self.line_len(self.row),
+            _ => {}
+        }
+        self.goal_x = None;
+        None
+    }
+
+    pub fn draw(&mut self, frame: &mut Frame<'_>, area: Rect) {
+        let block = Block::default()
+            .title(self.title.clone())
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(Color::Rgb(255, 255, 255)));
+        let inner = block.inner(area);
+        // Keep a spare column so the cursor has room after the last character of a row.
+        self.width = usize::from(inner.width.saturating_sub(1));
+
+        let rows = self.visual_rows();
+        let (cursor_row, cursor_x) = self.cursor_position(&rows);
+        let height = usize::from(inner.height).max(1);
+        if cursor_row < self.scroll {
+            self.scroll = cursor_row;
+        } else if cursor_row >= self.scroll + height {
+            self.scroll = cursor_row + 1 - height;
+        }
+
+        let text = rows
+            .iter()
+            .skip(self.scroll)
+            .take(height)
+            .map(|(line, range)| Line::from(Self::slice(&self.lines[*line], range.clone())))
+            .collect::<Vec<Line>>();
+        frame.render_widget(Paragraph::new(text).block(block), area);
+
+        let x = inner.x.saturating_add(u16::try_from(cursor_x).unwrap_or(u16::MAX));
+        let y = inner.y.saturating_add(u16::try_from(cursor_row - self.scroll).unwrap_or(u16::MAX));
+        frame.set_cursor(x.min(inner.right().saturating_sub(1)), y);
+    }
+
+    fn line_len(&self, row: usize) -> usize {
+        self.lines[row].graphemes(true).count()
+    }
+
+    /// Byte offset of the grapheme at index `col`.
+    fn byte_offset(line: &str, col: usize) -> usize {
+        line.grapheme_indices(true).nth(col).map(|(i, _)| i).unwrap_or(line.len())
+    }
+
+    fn slice(line: &str, range: Range<usize>) -> String {
+        line.graphemes(true).skip(range.start).take(range.len()).collect()
+    }
+
+    fn insert(&mut self, text: &str) {
+        let line = &mut self.lines[self.row];
+        let before = line.graphemes(true).count();
+        line.insert_str(Self::byte_offset(line, self.col), text);
+        // Combining characters merge with the previous grapheme instead of adding one.
+        self.col += line.graphemes(true).count() - before;
+    }
+
+    fn insert_newline(&mut self) {
+        let line = &mut self.lines[self.row];
+        let rest = line.split_off(Self::byte_offset(line, self.col));
+        self.row += 1;
+        self.col = 0;
+        self.lines.insert(self.row, rest);
+    }
+
+    fn backspace(&mut self) {
+        if self.col > 0 {
+            self.move_left();
+            self.delete();
+        } else if self.row > 0 {
+            let line = self.lines.remove(self.row);
+            self.row -= 1;
+            self.col = self.line_len(self.row);
+            self.lines[self.row].push_str(&line);
+        }
+    }
+
+    fn delete(&mut self) {
+        if self.col < self.line_len(self.row) {
+            let line = &mut self.lines[self.row];
+            let start = Self::byte_offset(line, self.col);
+            let end = Self::byte_offset(line, self.col + 1);
+            line.replace_range(start..end, "");
+        } else if self.row + 1 < self.lines.len() {
+            let next = self.lines.remove(self.row + 1);
+            self.lines[self.row].push_str(&next);
+        }
+    }
+
+    fn move_left(&mut self) {
+        if self.col > 0 {
+            self.col -= 1;
+        } else if self.row > 0 {
+            self.row -= 1;
+            self.col = self.line_len(self.row);
+        }
+    }
+
+    fn move_right(&mut self) {
+        if self.col < self.line_len(self.row) {
+            self.col += 1;
+        } else if self.row + 1 < self.lines.len() {
+            self.row += 1;
+            self.col = 0;
+        }
+    }
+
+    /// Moves the cursor up or down by visual rows, keeping its display column.
+    fn move_vertically(&mut self, delta: isize) -> Option<EditorEvent> {
+        let rows = self.visual_rows();
+        let (current, x) = self.cursor_position(&rows);
+        let goal_x = *self.goal_x.get_or_insert(x);
+        let target = current.checked_add_signed(delta).filter(|t| *t < rows.len())?;
+
+        let (line, range) = rows[target].clone();
+        let graphemes = self.lines[line].graphemes(true).collect::<Vec<_>>();
+        let last_of_line = rows.get(target + 1).is_none_or(|(next, _)| *next != line);
+        // Only the last row of a line can hold the cursor past its final grapheme.
+        let max_col = if last_of_line || range.is_empty() { range.end } else { range.end - 1 };
+        let mut col = range.start;
+        let mut used = 0;
+        while col < max_col && used + graphemes[col].width() <= goal_x {
+            used += graphemes[col].width();
+            col += 1;
+        }
+        self.row = line;
+        self.col = col;
+        None
+    }
+
+    /// Splits every line into the rows it occupies on screen.
+    fn visual_rows(&self) -> Vec<(usize, Range<usize>)> {
+        self.lines
+            .iter()
+            .enumerate()
+            .flat_map(|(i, line)| wrap(line, self.width).into_iter().map(move |r| (i, r)))
+            .collect()
+    }
+
+    /// Gets the visual row and display column of the cursor.
+    fn cursor_position(&self, rows: &[(usize, Range<usize>)]) -> (usize, usize) {
+        let index = rows
+            .iter()
+            .position(|(line, range)| *line == self.row && self.col < range.end)
+            .or_else(|| rows.iter().rposition(|(line, _)| *line == self.row))
+            .unwrap_or(0);
+        let (line, range) = &rows[index];
+        let x = Self::slice(&self.lines[*line], range.start..self.col.max(range.start)).width();
+        (index, x)
+    }
+}
+
+/// Soft wraps a line into grapheme ranges no wider than `width`, breaking after
+/// whitespace when possible.
+fn wrap(line: &str, width: usize) -> Vec<Range<usize>> {
+    let graphemes = line.graphemes(true).collect::<Vec<_>>();
+    let mut rows = Vec::new();
+    let mut start = 0;
+    let mut used = 0;
+    let mut i = 0;
+    while i < graphemes.len() {
+        let w = graphemes[i].width();
+        if width > 0 && used + w > width && i > start {
+            let last_space = (start..i).rev().find(|j| graphemes[*j].trim().is_empty());
+            let end = last_space.map_or(i, |j| j + 1);
+            rows.push(start..end);
+            start = end;
+            used = graphemes[start..i].iter().map(|g| g.width()).sum();
+            continue;
+        }
+        used += w;
+        i += 1;
+    }
+    rows.push(start..graphemes.len());
+    rows
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    fn type_keys(editor: &mut Editor, codes: &[KeyCode]) {
+        for code in codes {
+            editor.handle_key_events(KeyEvent::new(*code, KeyModifiers::empty()));
+        }
+    }
+
+    fn type_text(editor: &mut Editor, text: &str) {
+        for c in text.chars() {
+            type_keys(editor, &[KeyCode::Char(c)]);
+        }
+    }
+
+    #[test]
+    fn test_insert_and_newline() {
+        let mut editor = Editor::new("", "");
+        type_text(&mut editor, "hello");
+        type_keys(&mut editor, &[KeyCode::Left, KeyCode::Left, KeyCode::Enter]);
+        assert_eq!(editor.content(), "hel\nlo");
+        assert_eq!((editor.row, editor.col), (1, 0));
+    }
+
+    #[test]
+    fn test_backspace_removes_whole_graphemes() {
+        // A family emoji and an "e" followed by a combining acute accent.
+        let mut editor = Editor::new("", "a👨‍👩‍👧e\u{301}");
+        assert_eq!(editor.col, 3);
+        type_keys(&mut editor, &[KeyCode::Backspace]);
+        assert_eq!(editor.content(), "a👨‍👩‍👧");
+        type_keys(&mut editor, &[KeyCode::Backspace]);
+        assert_eq!(editor.content(), "a");
+    }
+
+    #[test]
+    fn test_combining_character_joins_previous_grapheme() {
+        let mut editor = Editor::new("", "cafe");
+        type_text(&mut editor, "\u{301}");
+        assert_eq!(editor.col, 4);
+        type_text(&mut editor, "!");
+        assert_eq!(editor.content(), "cafe\u{301}!");
+    }
+
+    #[test]
+    fn test_backspace_and_delete_join_lines() {
+        let mut editor = Editor::new("", "one\ntwo\nthree");
+        type_keys(&mut editor, &[KeyCode::Up, KeyCode::Home, KeyCode::Backspace]);
+        assert_eq!(editor.content(), "onetwo\nthree");
+        type_keys(&mut editor, &[KeyCode::End, KeyCode::Delete]);
+        assert_eq!(editor.content(), "onetwothree");
+    }
+
+    #[test]
+    fn test_wrap_breaks_after_whitespace() {
+        assert_eq!(wrap("the quick brown fox jumps", 10), vec![0..10, 10..20, 20..25]);
+        assert_eq!(wrap("abcdefghij", 4), vec![0..4, 4..8, 8..10]);
+        assert_eq!(wrap("", 4), vec![0..0]);
+    }
+
+    #[test]
+    fn test_wrap_counts_wide_characters() {
+        assert_eq!(wrap("日本語テキスト", 6), vec![0..3, 3..6, 6..7]);
+    }
+
+    #[test]
+    fn test_vertical_movement_follows_wrapped_rows() {
+        let mut editor = Editor::new("", "the quick brown fox jumps");
+        editor.width = 10;
+        type_keys(&mut editor, &[KeyCode::Up]);
+        assert_eq!(editor.col, 15);
+        type_keys(&mut editor, &[KeyCode::Up]);
+        assert_eq!(editor.col, 5);
+        type_keys(&mut editor, &[KeyCode::Up]);
+        assert_eq!(editor.col, 5);
+        type_keys(&mut editor, &[KeyCode::Down, KeyCode::Down]);
+        assert_eq!(editor.col, 25);
+    }
+
+    #[test]
+    fn test_save_and_cancel() {
+        let mut editor = Editor::new("", "note");
+        let save = KeyEvent::new(KeyCode::Char('s'), KeyModifiers::CONTROL);
+        assert_eq!(editor.handle_key_events(save), Some(EditorEvent::Save("note".to_string())));
+        let cancel = KeyEvent::new(KeyCode::Esc, KeyModifiers::empty());
+        assert_eq!(editor.handle_key_events(cancel), Some(EditorEvent::Cancel));
+    }
+}
@@ -0,0 +1,183 @@
+use chrono::{DateTime, Local, Utc};
+use color_eyre::eyre::Result;
+use crossterm::event::{KeyCode, KeyEvent};
+use ratatui::{prelude::*, widgets::*};
+
+use super::popup_area;
+use crate::{
+    db_reader::DbReader,
+    models::{entry::Entry, revision::Revision},
+    tui::Frame,
+};
+
+/// What the user decided to do in the history view.
+#[derive(Debug, Clone, PartialEq)]
+pub enum HistoryViewEvent {
+    Restore(Revision),
+    Close,
+}
+
+/// The entry as it is now, or one of its revisions.
+struct Version {
+    revision: Option<Revision>,
+    content: String,
+    saved_at: DateTime<Utc>,
+}
+
+/// A popup listing the revisions of an entry, with a line diff between two of them.
+///
+/// The selected version is compared with the one marked with space, or else with the
+/// version before it.
+pub struct HistoryView {
+    /// Newest first, starting with the entry as it is now.
+    versions: Vec<Version>,
+    state: ListState,
+    marked: Option<usize>,
+}
+
+impl HistoryView {
+    const DATE_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+
+    pub fn new(db: &dyn DbReader, entry: &Entry) -> Result<Self> {
+        let mut versions = vec![Version {
+            revision: None,
+            content: entry.content.clone(),
+            saved_at: entry.updated_at,
+        }];
+        versions.extend(db.get_revisions(entry.id)?.into_iter().rev().map(|revision| Version {
+            content: revision.content.clone(),
+            saved_at: revision.saved_at,
+            revision: Some(revision),
+        }));
+        Ok(Self {
+            versions,
+            state: ListState::default().with_selected(Some(0)),
+            marked: None,
+        })
+    }
+
+    fn selected(&self) -> usize {
+        self.state.selected().unwrap_or(0)
+    }
+
+    /// The versions being compared, older first.
+    fn compared(&self) -> (Option<&Version>, &Version) {
+        let selected = self.selected();
+        let base = match self.marked {
+            Some(marked) if marked != selected => Some(marked),
+            _ => Some(selected + 1).filter(|&i| i < self.versions.len()),
+        };
+        match base {
+            Some(base) if base < selected => (Some(&self.versions[selected]), &self.versions[base]),
+            base => (base.map(|i| &self.versions[i]), &self.versions[selected]),
+        }
+    }
+
+    pub fn handle_key_events(&mut self, key: KeyEvent) -> Option<HistoryViewEvent> {
+        let selected = self.selected();
+        match key.code {
+            KeyCode::Esc => return Some(HistoryViewEvent::Close),
+            KeyCode::Enter | KeyCode::Char('r') => {
+                return self.versions[selected].revision.clone().map(HistoryViewEvent::Restore);
+            }
+            KeyCode::Char(' ') => self.marked = if self.marked == Some(selected) { None } else { Some(selected) },
+            KeyCode::Down => self.state.select(Some((selected + 1).min(self.versions.len() - 1))),
+            KeyCode::Up => self.state.select(Some(selected.saturating_sub(1))),
+            _ => {}
+        }
+        None
+    }
+
+    fn label(&self, index: usize) -> String {
+        let version = &self.versions[index];
+        let saved = version.saved_at.with_timezone(&Local).format(Self::DATE_FORMAT);
+        let current = if version.revision.is_none() { " (current)" } else { "" };
+        let marked = if self.marked == Some(index) { "● " } else { "  " };
+        format!("{marked}{saved}{current}")
+    }
+
+    pub fn draw(&mut self, frame: &mut Frame<'_>, area: Rect) {
+        let area = popup_area(area, 80, 80);
+        frame.render_widget(Clear, area);
+        let layout = Layout::new(Direction::Horizontal, [Constraint::Length(32), Constraint::Min(0)]).split(area);
+
+        let labels = (0..self.versions.len()).map(|i| self.label(i)).collect::<Vec<_>>();
+        let list = List::new(labels)
+            .block(Block::default().title("History").borders(Borders::ALL))
+            .highlight_symbol("▶️ ");
+        frame.render_stateful_widget(list, layout[0], &mut self.state);
+
+        let (old, new) = self.compared();
+        let title = match old {
+            Some(old) => format!("Changes since {}", old.saved_at.with_timezone(&Local).format(Self::DATE_FORMAT)),
+            None => "First version".to_string(),
+        };
+        let diff = diff_lines(old.map_or("", |v| v.content.as_str()), &new.content);
+        let paragraph = Paragraph::new(diff)
+            .block(Block::default().title(title).borders(Borders::ALL))
+            .wrap(Wrap { trim: false });
+        frame.render_widget(paragraph, layout[1]);
+    }
+}
+
+/// Lines of `new`, with those added since `old` in green and those removed in red.
+fn diff_lines(old: &str, new: &str) -> Vec<Line<'static>> {
+    diff::lines(old, new)
+        .into_iter()
+        .map(|change| match change {
+            diff::Result::Left(line) => Line::styled(format!("- {line}"), Style::default().fg(Color::Red)),
+            diff::Result::Right(line) => Line::styled(format!("+ {line}"), Style::default().fg(Color::Green)),
+            diff::Result::Both(line, _) => Line::raw(format!("  {line}")),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crossterm::event::KeyModifiers;
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+    use crate::db_reader::MemoryDbReader;
+
+    fn press(view: &mut HistoryView, code: KeyCode) -> Option<HistoryViewEvent> {
+        view.handle_key_events(KeyEvent::new(code, KeyModifiers::empty()))
+    }
+
+    fn contents(view: &HistoryView) -> (Option<&str>, &str) {
+        let (old, new) = view.compared();
+        (old.map(|v| v.content.as_str()), new.content.as_str())
+    }
+
+    #[test]
+    fn test_compares_revisions() -> Result<()> {
+        let db = MemoryDbReader::new();
+        let mut entry = db.save_entry(&Entry::new(1, "one".to_string()))?;
+        for content in ["one\ntwo", "one\ntwo\nthree"] {
+            entry.set_content(content.to_string());
+            db.update_entry(&entry)?;
+        }
+        let mut view = HistoryView::new(&db, &entry)?;
+        assert_eq!(contents(&view), (Some("one\ntwo"), "one\ntwo\nthree"));
+        assert_eq!(press(&mut view, KeyCode::Enter), None);
+
+        press(&mut view, KeyCode::Char(' '));
+        press(&mut view, KeyCode::Down);
+        press(&mut view, KeyCode::Down);
+        assert_eq!(contents(&view), (Some("one"), "one\ntwo\nthree"));
+        assert_eq!(
+            diff_lines("one", "one\ntwo\nthree"),
+            vec![
+                Line::raw("  one"),
+                Line::styled("+ two", Style::default().fg(Color::Green)),
+                Line::styled("+ three", Style::default().fg(Color::Green)),
+            ]
+        );
+
+        let Some(HistoryViewEvent::Restore(revision)) = press(&mut view, KeyCode::Char('r')) else {
+            panic!("expected the oldest revision to be restored");
+        };
+        assert_eq!(revision.content, "one");
+        Ok(())
+    }
+}
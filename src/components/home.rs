@@ -1,17 +1,43 @@
-use std::{cmp::max, cmp::min, collections::HashMap, time::Duration};
+use std::{
+    cmp::max,
+    cmp::min,
+    collections::{BTreeSet, HashMap},
+    sync::Arc,
+    time::{Duration, Instant},
+};
 
-use color_eyre::{eyre::Result, owo_colors::OwoColorize};
-use crossterm::event::{KeyCode, KeyEvent};
+use chrono::{Local, Utc};
+use color_eyre::{
+    eyre::{eyre, Result},
+    owo_colors::OwoColorize,
+};
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
 use ratatui::{prelude::*, widgets::*};
 use serde::{Deserialize, Serialize};
 use tokio::sync::mpsc::UnboundedSender;
+use zeroize::Zeroizing;
 
-use super::{Component, Frame};
+use super::{
+    command_line::{CommandLine, CommandLineEvent, ExCommand},
+    editor::{Editor, EditorEvent},
+    history_view::{HistoryView, HistoryViewEvent},
+    quick_switcher::{QuickSwitcher, QuickSwitcherEvent, Target},
+    popup_area,
+    search_overlay::{SearchOverlay, SearchOverlayEvent},
+    stack_picker::{StackPicker, StackPickerEvent},
+    trash_view::{TrashView, TrashViewEvent},
+    Component, Frame,
+};
 use crate::{
     action::Action,
+    calendar::Calendar,
     config::{Config, KeyBindings},
     db_reader::DbReader,
+    export,
+    mode::Mode as AppMode,
     models::{entry::Entry, stack::Stack},
+    query::{self, Query},
+    utils::get_data_dir,
 };
 
 #[derive(Default, Copy, Clone, PartialEq, Eq)]
@@ -19,42 +45,125 @@ enum Mode {
     #[default]
     BrowseStacks,
     BrowseEntries,
+    Editing,
     Command,
+    Search,
+    QuickSwitch,
+    Trash,
+    History,
+    PickStack,
+    /// Asking for the passphrase of an encrypted journal. Nothing is shown until it is
+    /// unlocked.
+    Unlock,
+}
+
+/// What the text of the editor is saved to.
+#[derive(Clone, Debug)]
+enum EditTarget {
+    Entry(Entry),
+    /// A new entry for the named stack, or for today's stack without a name.
+    NewEntry(Option<String>),
+}
+
+/// What is done with the selected entry once a stack is picked.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum Transfer {
+    Move,
+    Copy,
+}
+
+/// A range of entries being marked, from `anchor` to the selected entry.
+#[derive(Clone, Debug, Default)]
+struct Visual {
+    anchor: usize,
+    /// Entries marked before the range was started.
+    marked_before: BTreeSet<u32>,
+}
+
+/// A message shown in the status bar until the next key press.
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Status {
+    Info(String),
+    Error(String),
 }
 
 #[derive(Default)]
 pub struct Home {
     command_tx: Option<UnboundedSender<Action>>,
     config: Config,
+    db: Option<Arc<dyn DbReader>>,
+    calendar: Calendar,
 
     stacks: Vec<Stack>,
     stack_state: ListState,
 
     entries: Vec<Entry>,
     entry_state: ListState,
+    /// Ids of the entries marked for bulk actions.
+    marked: BTreeSet<u32>,
+    visual: Option<Visual>,
 
     mode: Mode,
+    /// Mode to go back to once the editor is closed.
+    previous_mode: Mode,
+
+    editor: Option<Editor>,
+    editing: Option<EditTarget>,
+
+    command_line: CommandLine,
+    status: Option<Status>,
+
+    search: Option<SearchOverlay>,
+    quick_switcher: Option<QuickSwitcher>,
+    trash_view: Option<TrashView>,
+    history_view: Option<HistoryView>,
+    stack_picker: Option<(Transfer, StackPicker)>,
+
+    passphrase: Zeroizing<String>,
+    /// Shown under the passphrase prompt, e.g. why the last unlock attempt failed.
+    unlock_message: Option<Status>,
+    /// When a key was last pressed, or the journal was opened or unlocked, to lock it
+    /// once it sits idle.
+    last_key_press: Option<Instant>,
 }
 
 impl Home {
     const COLOR_INACTIVE: Color = Color::Rgb(100, 100, 100);
     const COLOR_ACTIVE: Color = Color::Rgb(255, 255, 255);
+    const COLOR_SMART: Color = Color::Cyan;
+    const COLOR_MARKED: Color = Color::Yellow;
+    const DATE_FORMAT: &str = "%Y-%m-%d %H:%M";
 
     pub fn new() -> Self {
         Self::default()
     }
 
-    pub fn load_stacks(&mut self) {
-        self.stacks = Stack::get_all();
+    fn db(&self) -> Result<Arc<dyn DbReader>> {
+        self.db.clone().ok_or_else(|| eyre!("No database has been registered"))
+    }
+
+    pub fn load_stacks(&mut self) -> Result<()> {
+        let db = self.db()?;
+        self.stacks = db.get_stacks()?;
+        query::count_smart_stacks(db.as_ref(), &self.calendar, &mut self.stacks);
+        Stack::sort_for_display(&mut self.stacks);
         self.stack_state = ListState::default();
+        Ok(())
     }
 
     fn draw_stack_ui(&mut self, frame: &mut Frame<'_>, area: Rect) {
         let list = List::new(
             self.stacks
                 .iter()
-                .map(|s| s.name.clone())
-                .collect::<Vec<String>>(),
+                .map(|s| {
+                    let marker = if s.is_smart() { "⌕ " } else { "" };
+                    Line::from(vec![
+                        Span::styled(marker, Style::default().fg(Self::COLOR_SMART)),
+                        Span::raw(s.name.clone()),
+                        Span::styled(format!(" ({})", s.count), Style::default().fg(Self::COLOR_INACTIVE)),
+                    ])
+                })
+                .collect::<Vec<Line>>(),
         )
         .block(
             Block::default()
@@ -78,13 +187,19 @@ impl Home {
                 .iter()
                 .map(|s| {
                     let content = &s.content;
-                    if content.chars().count() > 100 {
-                        format!("{}...", &content[..100])
+                    let preview = if content.chars().count() > 100 {
+                        format!("{}...", content.chars().take(100).collect::<String>())
                     } else {
                         content.to_string()
+                    };
+                    let created = s.created_at.with_timezone(&Local).format(Self::DATE_FORMAT);
+                    let item = ListItem::new(format!("{created}  {preview}"));
+                    match self.marked.contains(&s.id) {
+                        true => item.style(Style::default().fg(Self::COLOR_MARKED).add_modifier(Modifier::BOLD)),
+                        false => item,
                     }
                 })
-                .collect::<Vec<String>>(),
+                .collect::<Vec<ListItem>>(),
         )
         .block(
             Block::default()
@@ -103,12 +218,600 @@ impl Home {
     }
 
     fn draw_status_ui(&mut self, frame: &mut Frame<'_>, area: Rect) {
-        frame.render_widget(
-            Block::new()
-                .borders(Borders::ALL)
-                .title("tooltips and status will go here"),
-            area,
+        let block = Block::new().borders(Borders::ALL);
+        if self.mode == Mode::Command {
+            self.command_line.draw(frame, area, block);
+            return;
+        }
+
+        let selection = format!(
+            "{} selected · space mark · V mark a range · m move · c copy · :delete · :merge · :export · esc unmark",
+            self.marked.len()
         );
+        let (text, color) = match &self.status {
+            Some(Status::Info(message)) => (message.as_str(), Self::COLOR_ACTIVE),
+            Some(Status::Error(message)) => (message.as_str(), Color::Red),
+            None if self.mode == Mode::BrowseEntries && !self.marked.is_empty() => {
+                (selection.as_str(), Self::COLOR_MARKED)
+            }
+            None => (
+                match self.mode {
+                    Mode::BrowseStacks => "↑↓ select · ↵ open · n new entry · / search · ctrl-p go to · : command · q quit",
+                    Mode::BrowseEntries => {
+                        "↑↓ select · space mark · e edit · v edit in $EDITOR · h history · m move · c copy · n new entry · / search · ctrl-p go to · : command · esc back"
+                    }
+                    Mode::Editing => "ctrl-s save · esc cancel",
+                    Mode::Search => "↑↓ select · ↵ open · ctrl-s save as smart stack · esc close",
+                    Mode::QuickSwitch => "↑↓ select · ↵ open · esc close",
+                    Mode::Trash => "↑↓ select · ↵ restore · d delete for good · esc close",
+                    Mode::History => "↑↓ select · space compare with · ↵ restore · esc close",
+                    Mode::PickStack => "↑↓ select · ↵ pick, or create the stack typed · esc cancel",
+                    Mode::Unlock => "↵ unlock · esc quit",
+                    Mode::Command => "",
+                },
+                Self::COLOR_INACTIVE,
+            ),
+        };
+        frame.render_widget(Paragraph::new(text).style(Style::default().fg(color)).block(block), area);
+    }
+
+    /// Hides the journal behind the passphrase prompt.
+    fn open_unlock_prompt(&mut self) -> Option<Action> {
+        self.stacks.clear();
+        self.entries.clear();
+        self.unmark();
+        self.stack_state = ListState::default();
+        self.entry_state = ListState::default();
+        self.passphrase = Zeroizing::default();
+        self.mode = Mode::Unlock;
+        Some(Action::ChangeMode(AppMode::Insert))
+    }
+
+    fn handle_keys_for_unlock_mode(&mut self, key: KeyEvent) -> Result<Option<Action>> {
+        match key.code {
+            KeyCode::Esc => return Ok(Some(Action::Quit)),
+            KeyCode::Enter => {
+                let passphrase = std::mem::take(&mut self.passphrase);
+                if let Err(e) = self.db()?.unlock(&passphrase) {
+                    self.unlock_message = Some(Status::Error(e.to_string()));
+                    return Ok(Some(Action::Render));
+                }
+                self.unlock_message = None;
+                self.last_key_press = Some(Instant::now());
+                self.mode = Mode::BrowseStacks;
+                self.clean_up()?;
+                self.load_stacks()?;
+                self.stack_state.select(Some(0));
+                return Ok(Some(Action::ChangeMode(AppMode::Home)));
+            }
+            KeyCode::Char(c) if !key.modifiers.contains(KeyModifiers::CONTROL) => self.passphrase.push(c),
+            KeyCode::Backspace => {
+                self.passphrase.pop();
+            }
+            _ => {}
+        }
+        Ok(Some(Action::Render))
+    }
+
+    fn draw_unlock_ui(&mut self, frame: &mut Frame<'_>, area: Rect) {
+        let popup = popup_area(area, 50, 100);
+        let popup = Rect::new(popup.x, area.y + area.height.saturating_sub(3) / 2, popup.width, 3.min(area.height));
+        frame.render_widget(Clear, popup);
+        let block = Block::default().title("Passphrase").borders(Borders::ALL);
+        let block = match &self.unlock_message {
+            Some(Status::Info(message)) => block.title_bottom(Line::from(message.clone())),
+            Some(Status::Error(error)) => {
+                block.title_bottom(Line::from(error.clone()).style(Style::default().fg(Color::Red)))
+            }
+            None => block,
+        };
+        let inner = block.inner(popup);
+        let masked = "•".repeat(self.passphrase.chars().count());
+        frame.render_widget(Paragraph::new(masked).block(block), popup);
+        let x = inner.x.saturating_add(u16::try_from(self.passphrase.chars().count()).unwrap_or(u16::MAX));
+        frame.set_cursor(x.min(inner.right().saturating_sub(1)), inner.y);
+    }
+
+    /// Locks an encrypted journal once no key was pressed for the configured time,
+    /// dropping everything decrypted, including an unsaved edit.
+    fn lock_if_idle(&mut self) -> Result<Option<Action>> {
+        let Some(minutes) = self.config.journal.auto_lock_minutes else {
+            return Ok(None);
+        };
+        let idle = self.last_key_press.map_or(Duration::ZERO, |at| at.elapsed());
+        if self.mode == Mode::Unlock || idle < Duration::from_secs(u64::from(minutes) * 60) {
+            return Ok(None);
+        }
+        // Checked only once the time is up, rather than on every tick.
+        self.last_key_press = Some(Instant::now());
+        let db = self.db()?;
+        if !db.is_encrypted()? {
+            return Ok(None);
+        }
+
+        db.lock();
+        let discarded = self.editing.take().is_some();
+        self.editor = None;
+        self.search = None;
+        self.quick_switcher = None;
+        self.trash_view = None;
+        self.history_view = None;
+        self.stack_picker = None;
+        self.command_line = CommandLine::default();
+        self.status = None;
+        self.unlock_message = Some(Status::Info(if discarded {
+            format!("Locked after {minutes} idle minutes, the unsaved edit was discarded")
+        } else {
+            format!("Locked after {minutes} idle minutes")
+        }));
+        Ok(self.open_unlock_prompt())
+    }
+
+    /// Opens the editor on the text of `target`.
+    fn open_editor(&mut self, target: EditTarget) -> Option<Action> {
+        self.editor = Some(match &target {
+            EditTarget::Entry(entry) => Editor::new("Edit entry", &entry.content),
+            EditTarget::NewEntry(Some(stack)) => Editor::new(format!("New entry for {stack}"), ""),
+            EditTarget::NewEntry(None) => {
+                Editor::new(format!("New entry for {}", self.calendar.today().format(Stack::DAY_FORMAT)), "")
+            }
+        });
+        self.editing = Some(target);
+        self.previous_mode = self.mode;
+        self.mode = Mode::Editing;
+        Some(Action::ChangeMode(AppMode::Insert))
+    }
+
+    fn handle_keys_for_editor_mode(&mut self, key: KeyEvent) -> Result<Option<Action>> {
+        let Some(editor) = self.editor.as_mut() else {
+            return Ok(None);
+        };
+        let event = match editor.handle_key_events(key) {
+            Some(event) => event,
+            None => return Ok(Some(Action::Render)),
+        };
+
+        self.editor = None;
+        self.mode = self.previous_mode;
+        let editing = self.editing.take();
+        if let (EditorEvent::Save(content), Some(target)) = (event, editing) {
+            self.save_entry(target, content)?;
+        }
+        Ok(Some(Action::ChangeMode(AppMode::Home)))
+    }
+
+    /// Stores the editor content, then shows the saved entry in its stack.
+    fn save_entry(&mut self, target: EditTarget, content: String) -> Result<()> {
+        let db = self.db()?;
+        let entry = match target {
+            EditTarget::Entry(mut entry) if entry.content != content => {
+                entry.set_content(content);
+                db.update_entry(&entry)?;
+                entry
+            }
+            EditTarget::Entry(_) => return Ok(()),
+            // Nothing was written, so there is nothing to capture.
+            EditTarget::NewEntry(_) if content.trim().is_empty() => return Ok(()),
+            EditTarget::NewEntry(Some(stack)) => {
+                let stack = db.get_or_create_named_stack(&stack)?;
+                db.save_entry(&Entry::new(stack.id, content))?
+            }
+            EditTarget::NewEntry(None) => db.capture(content, self.calendar.today())?,
+        };
+        self.show_entry(&entry)
+    }
+
+    /// Reloads the stacks and opens the stack of `entry` with it selected.
+    fn show_entry(&mut self, entry: &Entry) -> Result<()> {
+        self.load_stacks()?;
+        let Some(stack_index) = self.stacks.iter().position(|s| s.id == entry.stack_id) else {
+            return Ok(());
+        };
+        self.get_enteries_for_stack(&stack_index)?;
+        self.stack_state.select(Some(stack_index));
+        self.entry_state.select(self.entries.iter().position(|e| e.id == entry.id));
+        self.mode = Mode::BrowseEntries;
+        Ok(())
+    }
+
+    /// Reloads the stacks and selects the one with `id`, reopening its entries if they
+    /// were shown.
+    fn show_stack(&mut self, id: u32) -> Result<()> {
+        let browsing_entries = self.mode == Mode::BrowseEntries;
+        let entry_index = self.entry_state.selected();
+        self.load_stacks()?;
+        let stack_index = self.stacks.iter().position(|s| s.id == id);
+        if let (true, Some(index)) = (browsing_entries, stack_index) {
+            self.get_enteries_for_stack(&index)?;
+            let last = self.entries.len().checked_sub(1);
+            self.entry_state.select(entry_index.zip(last).map(|(i, last)| i.min(last)));
+        } else {
+            self.mode = Mode::BrowseStacks;
+        }
+        self.stack_state.select(stack_index.or(Some(0)));
+        Ok(())
+    }
+
+    fn selected_stack(&self) -> Result<Stack> {
+        self.stack_state
+            .selected()
+            .and_then(|i| self.stacks.get(i))
+            .cloned()
+            .ok_or_else(|| eyre!("No stack is selected"))
+    }
+
+    fn selected_entry(&self) -> Result<Entry> {
+        match self.mode {
+            Mode::BrowseEntries => self.entry_state.selected().and_then(|i| self.entries.get(i)).cloned(),
+            _ => None,
+        }
+        .ok_or_else(|| eyre!("No entry is selected"))
+    }
+
+    fn open_search(&mut self) -> Result<Option<Action>> {
+        self.search = Some(SearchOverlay::new(self.db()?, self.calendar.clone())?);
+        self.previous_mode = self.mode;
+        self.mode = Mode::Search;
+        Ok(Some(Action::ChangeMode(AppMode::Insert)))
+    }
+
+    fn handle_keys_for_search_mode(&mut self, key: KeyEvent) -> Result<Option<Action>> {
+        let Some(search) = self.search.as_mut() else {
+            return Ok(None);
+        };
+        let event = match search.handle_key_events(key)? {
+            Some(event) => event,
+            None => return Ok(Some(Action::Render)),
+        };
+
+        self.search = None;
+        self.mode = self.previous_mode;
+        match event {
+            SearchOverlayEvent::Open(entry) => self.show_entry(&entry)?,
+            SearchOverlayEvent::Save(query) => {
+                if let Err(e) = self.save_smart_stack(query) {
+                    self.status = Some(Status::Error(e.to_string()));
+                }
+            }
+            SearchOverlayEvent::Cancel => {}
+        }
+        Ok(Some(Action::ChangeMode(AppMode::Home)))
+    }
+
+    /// Saves a query as a smart stack named after it, then selects the new stack.
+    fn save_smart_stack(&mut self, query: String) -> Result<()> {
+        Query::parse(&query)?;
+        let db = self.db()?;
+        if db.get_stack_by_name(&query)?.is_some() {
+            return Err(eyre!("There already is a stack named {query}"));
+        }
+        let stack = db.save_stack(&Stack::smart(query.clone(), query))?;
+        self.status = Some(Status::Info(format!("Saved {} as a smart stack, :rename it at will", stack.name)));
+        self.mode = Mode::BrowseStacks;
+        self.entries.clear();
+        self.show_stack(stack.id)
+    }
+
+    fn open_quick_switcher(&mut self) -> Result<Option<Action>> {
+        self.quick_switcher = Some(QuickSwitcher::new(self.db()?.as_ref(), &self.calendar)?);
+        self.previous_mode = self.mode;
+        self.mode = Mode::QuickSwitch;
+        Ok(Some(Action::ChangeMode(AppMode::Insert)))
+    }
+
+    fn handle_keys_for_quick_switch_mode(&mut self, key: KeyEvent) -> Result<Option<Action>> {
+        let Some(switcher) = self.quick_switcher.as_mut() else {
+            return Ok(None);
+        };
+        let event = match switcher.handle_key_events(key) {
+            Some(event) => event,
+            None => return Ok(Some(Action::Render)),
+        };
+
+        self.quick_switcher = None;
+        self.mode = self.previous_mode;
+        match event {
+            QuickSwitcherEvent::Jump(Target::Stack(stack)) => {
+                self.mode = Mode::BrowseStacks;
+                self.entries.clear();
+                self.show_stack(stack.id)?;
+            }
+            QuickSwitcherEvent::Jump(Target::Entry(entry)) => self.show_entry(&entry)?,
+            QuickSwitcherEvent::Cancel => {}
+        }
+        Ok(Some(Action::ChangeMode(AppMode::Home)))
+    }
+
+    /// Purges what has been in the trash longer than configured and prunes revisions.
+    fn clean_up(&self) -> Result<()> {
+        let db = self.db()?;
+        if let Some(before) = self.config.trash.purge_before(Utc::now()) {
+            db.purge_trash(before)?;
+        }
+        if self.config.history.revisions_per_entry > 0 {
+            db.prune_revisions(self.config.history.revisions_per_entry)?;
+        }
+        Ok(())
+    }
+
+    fn open_history_view(&mut self, entry: &Entry) -> Result<Option<Action>> {
+        self.history_view = Some(HistoryView::new(self.db()?.as_ref(), entry)?);
+        self.previous_mode = self.mode;
+        self.mode = Mode::History;
+        Ok(Some(Action::ChangeMode(AppMode::Insert)))
+    }
+
+    fn handle_keys_for_history_mode(&mut self, key: KeyEvent) -> Result<Option<Action>> {
+        let Some(view) = self.history_view.as_mut() else {
+            return Ok(None);
+        };
+        let event = match view.handle_key_events(key) {
+            Some(event) => event,
+            None => return Ok(Some(Action::Render)),
+        };
+
+        self.history_view = None;
+        self.mode = self.previous_mode;
+        if let HistoryViewEvent::Restore(revision) = event {
+            let entry = self.db()?.restore_revision(&revision)?;
+            let saved = revision.saved_at.with_timezone(&Local).format(Self::DATE_FORMAT);
+            self.status = Some(Status::Info(format!("Restored the entry as it was on {saved}")));
+            self.show_entry(&entry)?;
+        }
+        Ok(Some(Action::ChangeMode(AppMode::Home)))
+    }
+
+    fn open_trash_view(&mut self) -> Result<Option<Action>> {
+        self.trash_view = Some(TrashView::new(self.db()?.as_ref())?);
+        self.previous_mode = self.mode;
+        self.mode = Mode::Trash;
+        Ok(Some(Action::ChangeMode(AppMode::Insert)))
+    }
+
+    /// Restores or purges items while the trash is shown, which stays open until closed.
+    fn handle_keys_for_trash_mode(&mut self, key: KeyEvent) -> Result<Option<Action>> {
+        let Some(view) = self.trash_view.as_mut() else {
+            return Ok(None);
+        };
+        let event = match view.handle_key_events(key) {
+            Some(event) => event,
+            None => return Ok(Some(Action::Render)),
+        };
+
+        let db = self.db()?;
+        let done = match event {
+            TrashViewEvent::Restore(id) => db.restore_trashed(id).map(|t| format!("Restored {}", t.description())),
+            TrashViewEvent::Purge(id) => db.remove_trashed(id).map(|()| "Deleted it for good".to_string()),
+            TrashViewEvent::Close => {
+                self.trash_view = None;
+                self.mode = self.previous_mode;
+                return Ok(Some(Action::ChangeMode(AppMode::Home)));
+            }
+        };
+        self.status = Some(match done {
+            Ok(message) => Status::Info(message),
+            Err(e) => Status::Error(e.to_string()),
+        });
+        if let Some(view) = self.trash_view.as_mut() {
+            view.reload(db.as_ref())?;
+        }
+        // Restored stacks show up behind the trash, which stays open until closed.
+        let stack_id = self.stack_state.selected().and_then(|i| self.stacks.get(i)).map_or(0, |s| s.id);
+        self.mode = Mode::BrowseStacks;
+        self.entries.clear();
+        self.show_stack(stack_id)?;
+        self.previous_mode = Mode::BrowseStacks;
+        self.mode = Mode::Trash;
+        Ok(Some(Action::Render))
+    }
+
+    /// Forgets the marked entries.
+    fn unmark(&mut self) {
+        self.marked.clear();
+        self.visual = None;
+    }
+
+    /// Marks the entries from the anchor of the range to the selected one, besides those
+    /// marked before the range was started.
+    fn extend_visual(&mut self) {
+        let (Some(visual), Some(selected)) = (&self.visual, self.entry_state.selected()) else {
+            return;
+        };
+        let range = min(visual.anchor, selected)..=max(visual.anchor, selected);
+        let mut marked = visual.marked_before.clone();
+        marked.extend(self.entries[range].iter().map(|e| e.id));
+        self.marked = marked;
+    }
+
+    /// The marked entries in list order, or else the selected one.
+    fn target_entries(&self) -> Result<Vec<Entry>> {
+        if self.mode == Mode::BrowseEntries && !self.marked.is_empty() {
+            return Ok(self.entries.iter().filter(|e| self.marked.contains(&e.id)).cloned().collect());
+        }
+        Ok(vec![self.selected_entry()?])
+    }
+
+    fn open_stack_picker(&mut self, transfer: Transfer, entry: &Entry) -> Result<Option<Action>> {
+        let title = match transfer {
+            Transfer::Move => "Move to",
+            Transfer::Copy => "Copy to",
+        };
+        self.stack_picker = Some((transfer, StackPicker::new(self.db()?.as_ref(), title, entry.stack_id)?));
+        self.previous_mode = self.mode;
+        self.mode = Mode::PickStack;
+        Ok(Some(Action::ChangeMode(AppMode::Insert)))
+    }
+
+    fn handle_keys_for_pick_stack_mode(&mut self, key: KeyEvent) -> Result<Option<Action>> {
+        let Some((transfer, picker)) = self.stack_picker.as_mut() else {
+            return Ok(None);
+        };
+        let transfer = *transfer;
+        let event = match picker.handle_key_events(key) {
+            Some(event) => event,
+            None => return Ok(Some(Action::Render)),
+        };
+
+        self.stack_picker = None;
+        self.mode = self.previous_mode;
+        if let StackPickerEvent::Pick(name) = event {
+            let entries = self.target_entries()?;
+            let picked = self.db()?.get_or_create_named_stack(&name);
+            if let Err(e) = picked.and_then(|stack| self.transfer_entries(transfer, &entries, &stack)) {
+                self.status = Some(Status::Error(e.to_string()));
+            }
+        }
+        Ok(Some(Action::ChangeMode(AppMode::Home)))
+    }
+
+    /// Moves or copies entries, then shows the first of them in `stack`. Copying many
+    /// entries skips those already in `stack`.
+    fn transfer_entries(&mut self, transfer: Transfer, entries: &[Entry], stack: &Stack) -> Result<()> {
+        let db = self.db()?;
+        let done = match transfer {
+            Transfer::Move => db.move_entries(entries, stack)?,
+            Transfer::Copy if entries.len() > 1 => {
+                let others = entries.iter().filter(|e| e.stack_id != stack.id).cloned().collect::<Vec<_>>();
+                db.copy_entries(&others, stack)?
+            }
+            Transfer::Copy => db.copy_entries(entries, stack)?,
+        };
+        let message = match (transfer, entries, done.as_slice()) {
+            (Transfer::Move, [entry], _) => format!("Moved entry {} to {}", entry.id, stack.name),
+            (Transfer::Copy, [entry], [copy]) => {
+                format!("Copied entry {} to {} as entry {}", entry.id, stack.name, copy.id)
+            }
+            (Transfer::Move, _, _) => format!("Moved {} entries to {}", done.len(), stack.name),
+            (Transfer::Copy, _, _) => format!("Copied {} entries to {}", done.len(), stack.name),
+        };
+        self.status = Some(Status::Info(message));
+        match done.first() {
+            Some(entry) => self.show_entry(entry),
+            None => self.show_stack(stack.id),
+        }
+    }
+
+    fn open_command_line(&mut self) -> Option<Action> {
+        self.command_line.reset();
+        self.previous_mode = self.mode;
+        self.mode = Mode::Command;
+        Some(Action::ChangeMode(AppMode::Insert))
+    }
+
+    fn handle_keys_for_command_mode(&mut self, key: KeyEvent) -> Result<Option<Action>> {
+        // Entries can't be added to smart stacks, so they aren't offered.
+        let stack_names = self.stacks.iter().filter(|s| !s.is_smart()).map(|s| s.name.clone()).collect::<Vec<_>>();
+        let event = match self.command_line.handle_key_events(key, &stack_names) {
+            Some(event) => event,
+            None => return Ok(Some(Action::Render)),
+        };
+
+        self.mode = self.previous_mode;
+        let CommandLineEvent::Submit(line) = event else {
+            return Ok(Some(Action::ChangeMode(AppMode::Home)));
+        };
+        // Mistyped commands and missing selections are reported, not fatal.
+        match ExCommand::parse(&line).and_then(|command| self.execute(command)) {
+            Ok(Some(action)) => Ok(Some(action)),
+            Ok(None) => Ok(Some(Action::ChangeMode(AppMode::Home))),
+            Err(e) => {
+                self.status = Some(Status::Error(e.to_string()));
+                Ok(Some(Action::ChangeMode(AppMode::Home)))
+            }
+        }
+    }
+
+    /// Runs a command from the command line. It can return an action that replaces
+    /// going back to browsing.
+    fn execute(&mut self, command: ExCommand) -> Result<Option<Action>> {
+        let db = self.db()?;
+        match command {
+            ExCommand::New(Some(name)) if db.get_stack_by_name(&name)?.is_some_and(|s| s.is_smart()) => {
+                return Err(eyre!("{name} is a smart stack, entries can't be added to it"));
+            }
+            ExCommand::New(stack) => return Ok(self.open_editor(EditTarget::NewEntry(stack))),
+            ExCommand::Rename(name) => {
+                let mut stack = self.selected_stack()?;
+                if db.get_stack_by_name(&name)?.is_some_and(|s| s.id != stack.id) {
+                    return Err(eyre!("There already is a stack named {name}"));
+                }
+                stack.name = name;
+                db.update_stack(&stack)?;
+                self.show_stack(stack.id)?;
+            }
+            ExCommand::Smart(query) => self.save_smart_stack(query)?,
+            ExCommand::Move(name) => {
+                let entries = self.target_entries()?;
+                let stack = db.find_stack(&name)?.ok_or_else(|| eyre!("There is no stack named {name}"))?;
+                self.transfer_entries(Transfer::Move, &entries, &stack)?;
+            }
+            ExCommand::Copy(name) => {
+                let entries = self.target_entries()?;
+                let stack = db.find_stack(&name)?.ok_or_else(|| eyre!("There is no stack named {name}"))?;
+                self.transfer_entries(Transfer::Copy, &entries, &stack)?;
+            }
+            ExCommand::Delete => match self.mode {
+                Mode::BrowseEntries => {
+                    let entries = self.target_entries()?;
+                    db.trash_entries(&entries)?;
+                    self.status = Some(Status::Info(match entries.len() {
+                        1 => "Moved the entry to the trash, :trash to restore it".to_string(),
+                        n => format!("Moved {n} entries to the trash, :trash to restore them"),
+                    }));
+                    // Stays in the stack that was browsed, which may be a smart stack.
+                    let stack = self.selected_stack().map_or(entries[0].stack_id, |s| s.id);
+                    self.show_stack(stack)?;
+                }
+                _ => {
+                    let stack = self.selected_stack()?;
+                    let trashed = db.trash_stack(&stack)?;
+                    self.status =
+                        Some(Status::Info(format!("Moved {} to the trash, :trash to restore it", trashed.description())));
+                    self.show_stack(stack.id)?;
+                }
+            },
+            ExCommand::Merge => {
+                let entries = self.target_entries()?;
+                if entries.len() < 2 {
+                    return Err(eyre!("Mark the entries to merge first, with space or V"));
+                }
+                let merged = db.merge_entries(&entries)?;
+                self.status = Some(Status::Info(format!(
+                    "Merged {} entries into entry {}, the others are in the trash",
+                    entries.len(),
+                    merged.id
+                )));
+                self.show_entry(&merged)?;
+            }
+            ExCommand::Trash => return self.open_trash_view(),
+            ExCommand::Export(path) => {
+                let stack = self.selected_stack()?;
+                let path = match path {
+                    Some(path) => std::env::current_dir()?.join(path),
+                    None => {
+                        let dir = get_data_dir().join("exports");
+                        std::fs::create_dir_all(&dir)?;
+                        dir.join(format!("{}.md", stack.name.replace(['/', '\\'], "-")))
+                    }
+                };
+                if self.mode == Mode::BrowseEntries && !self.marked.is_empty() {
+                    let entries = self.target_entries()?;
+                    std::fs::write(&path, export::stack_to_markdown(&stack, &entries, &self.calendar))?;
+                    self.status = Some(Status::Info(format!(
+                        "Exported {} entries of {} to {}",
+                        entries.len(),
+                        stack.name,
+                        path.display()
+                    )));
+                    return Ok(None);
+                }
+                let entries = query::stack_entries(db.as_ref(), &self.calendar, &stack)?;
+                std::fs::write(&path, export::stack_to_markdown(&stack, &entries, &self.calendar))?;
+                self.status = Some(Status::Info(format!("Exported {} to {}", stack.name, path.display())));
+            }
+            ExCommand::Quit => return Ok(Some(Action::Quit)),
+        }
+        Ok(None)
     }
 
     fn handle_keys_for_stack_mode(&mut self, key: KeyEvent) -> Result<Option<Action>> {
@@ -116,21 +819,36 @@ impl Home {
         let mut return_action = None;
         match key.code {
             KeyCode::Down => {
-                selected_index = min(selected_index + 1, self.stacks.len() - 1);
+                selected_index = min(selected_index + 1, self.stacks.len().saturating_sub(1));
                 return_action = Some(Action::Render);
             }
-            KeyCode::Up => {
-                if selected_index > 0 {
-                    selected_index -= 1;
-                    return_action = Some(Action::Render);
-                }
+            KeyCode::Up if selected_index > 0 => {
+                selected_index -= 1;
+                return_action = Some(Action::Render);
             }
-            KeyCode::Enter => {
-                if let Some(selected) = self.stack_state.selected() {
-                    self.mode = Mode::BrowseEntries;
-                    self.get_enteries_for_stack(&selected_index);
-                    return_action = Some(Action::Render);
+            KeyCode::Enter if selected_index < self.stacks.len() => {
+                // A smart stack whose query no longer runs is reported instead of opened.
+                match self.get_enteries_for_stack(&selected_index) {
+                    Ok(()) => self.mode = Mode::BrowseEntries,
+                    Err(e) => self.status = Some(Status::Error(e.to_string())),
                 }
+                return_action = Some(Action::Render);
+            }
+            KeyCode::Char('n') => {
+                self.stack_state.select(Some(selected_index));
+                return Ok(self.open_editor(EditTarget::NewEntry(None)));
+            }
+            KeyCode::Char(':') => {
+                self.stack_state.select(Some(selected_index));
+                return Ok(self.open_command_line());
+            }
+            KeyCode::Char('/') => {
+                self.stack_state.select(Some(selected_index));
+                return self.open_search();
+            }
+            KeyCode::Char('p') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                self.stack_state.select(Some(selected_index));
+                return self.open_quick_switcher();
             }
             _ => {}
         }
@@ -143,14 +861,21 @@ impl Home {
         let mut return_action = None;
         match key.code {
             KeyCode::Down => {
-                selected_index = min(selected_index + 1, self.entries.len() - 1);
+                selected_index = min(selected_index + 1, self.entries.len().saturating_sub(1));
                 return_action = Some(Action::Render);
             }
-            KeyCode::Up => {
-                if selected_index > 0 {
-                    selected_index -= 1;
-                    return_action = Some(Action::Render);
-                }
+            KeyCode::Up if selected_index > 0 => {
+                selected_index -= 1;
+                return_action = Some(Action::Render);
+            }
+            // Esc ends a range first, then drops the marks, then goes back to the stacks.
+            KeyCode::Esc if self.visual.is_some() => {
+                self.visual = None;
+                return_action = Some(Action::Render);
+            }
+            KeyCode::Esc if !self.marked.is_empty() => {
+                self.unmark();
+                return_action = Some(Action::Render);
             }
             KeyCode::Esc => {
                 if let Some(selected) = self.stack_state.selected() {
@@ -159,38 +884,98 @@ impl Home {
                     return_action = Some(Action::Render);
                 }
             }
+            KeyCode::Char(' ') if selected_index < self.entries.len() => {
+                let id = self.entries[selected_index].id;
+                if !self.marked.remove(&id) {
+                    self.marked.insert(id);
+                }
+                self.visual = None;
+                selected_index = min(selected_index + 1, self.entries.len() - 1);
+                return_action = Some(Action::Render);
+            }
+            KeyCode::Char('V') if selected_index < self.entries.len() => {
+                self.visual = match self.visual.take() {
+                    Some(_) => None,
+                    None => Some(Visual {
+                        anchor: selected_index,
+                        marked_before: self.marked.clone(),
+                    }),
+                };
+                return_action = Some(Action::Render);
+            }
+            KeyCode::Char('n') => return Ok(self.open_editor(EditTarget::NewEntry(None))),
+            KeyCode::Char('e') if selected_index < self.entries.len() => {
+                return Ok(self.open_editor(EditTarget::Entry(self.entries[selected_index].clone())));
+            }
+            KeyCode::Char(':') => return Ok(self.open_command_line()),
+            KeyCode::Char('/') => return self.open_search(),
+            KeyCode::Char('p') if key.modifiers.contains(KeyModifiers::CONTROL) => return self.open_quick_switcher(),
+            KeyCode::Char('h') if selected_index < self.entries.len() => {
+                let entry = self.entries[selected_index].clone();
+                self.entry_state.select(Some(selected_index));
+                return self.open_history_view(&entry);
+            }
+            KeyCode::Char(c @ ('m' | 'c')) if selected_index < self.entries.len() => {
+                let entry = self.entries[selected_index].clone();
+                self.entry_state.select(Some(selected_index));
+                let transfer = if c == 'm' { Transfer::Move } else { Transfer::Copy };
+                return self.open_stack_picker(transfer, &entry);
+            }
+            KeyCode::Char('v') if selected_index < self.entries.len() => {
+                let entry = self.entries[selected_index].clone();
+                return_action = Some(Action::EditExternally(entry.content.clone()));
+                self.editing = Some(EditTarget::Entry(entry));
+            }
             _ => {}
         }
 
         self.entry_state.select(Some(selected_index));
+        self.extend_visual();
         Ok(return_action)
     }
 
-    fn get_enteries_for_stack(&mut self, index: &usize) {
+    fn get_enteries_for_stack(&mut self, index: &usize) -> Result<()> {
         let selected_stack = self.stacks[*index].clone();
         self.stack_state = ListState::default();
+        self.unmark();
 
-        self.entries = Entry::get_all(&selected_stack.id);
+        self.entries = query::stack_entries(self.db()?.as_ref(), &self.calendar, &selected_stack)?;
         self.entry_state.select(Some(0));
+        Ok(())
     }
 }
 
 impl Component for Home {
     fn init(&mut self, area: Rect) -> Result<()> {
-        self.load_stacks();
+        self.last_key_press = Some(Instant::now());
+        if self.db()?.is_locked()? {
+            if let (Some(action), Some(tx)) = (self.open_unlock_prompt(), &self.command_tx) {
+                tx.send(action)?;
+            }
+            return Ok(());
+        }
+        self.clean_up()?;
+        self.load_stacks()?;
         self.stack_state.select(Some(0));
 
         Ok(())
     }
 
     fn handle_key_events(&mut self, key: KeyEvent) -> Result<Option<Action>> {
-        let a = match self.mode {
+        self.status = None;
+        self.last_key_press = Some(Instant::now());
+        match self.mode {
             Mode::BrowseStacks => self.handle_keys_for_stack_mode(key),
             Mode::BrowseEntries => self.handle_keys_for_entry_mode(key),
-            _ => Ok(None),
-        };
-
-        a
+            Mode::Editing => self.handle_keys_for_editor_mode(key),
+            Mode::Command => self.handle_keys_for_command_mode(key),
+            Mode::Search => self.handle_keys_for_search_mode(key),
+            Mode::QuickSwitch => self.handle_keys_for_quick_switch_mode(key),
+            Mode::Trash => self.handle_keys_for_trash_mode(key),
+            Mode::History => self.handle_keys_for_history_mode(key),
+            Mode::PickStack => self.handle_keys_for_pick_stack_mode(key),
+            Mode::Unlock => self.handle_keys_for_unlock_mode(key),
+        }
     }
 
     fn register_action_handler(&mut self, tx: UnboundedSender<Action>) -> Result<()> {
@@ -199,14 +984,30 @@ impl Component for Home {
     }
 
     fn register_config_handler(&mut self, config: Config) -> Result<()> {
+        self.calendar = Calendar::new(&config.journal)?;
         self.config = config;
         Ok(())
     }
 
+    fn register_db_handler(&mut self, db: Arc<dyn DbReader>) -> Result<()> {
+        self.db = Some(db);
+        Ok(())
+    }
+
     fn update(&mut self, action: Action) -> Result<Option<Action>> {
-        match action {
-            Action::Resume => {}
-            _ => {}
+        if action == Action::Tick {
+            return self.lock_if_idle();
+        }
+        if let Action::Error(message) = action {
+            self.status = Some(Status::Error(message));
+            return Ok(Some(Action::Render));
+        }
+        if let Action::ExternalEditFinished(edited) = action {
+            let editing = self.editing.take();
+            if let (Some(content), Some(target)) = (edited, editing) {
+                self.save_entry(target, content)?;
+            }
+            return Ok(Some(Action::Render));
         }
         Ok(None)
     }
@@ -228,8 +1029,454 @@ impl Component for Home {
 
         self.draw_stack_ui(frame, inner_layout[0]);
 
-        self.draw_entry_ui(frame, inner_layout[1]);
+        match self.editor.as_mut() {
+            Some(editor) => editor.draw(frame, inner_layout[1]),
+            None => self.draw_entry_ui(frame, inner_layout[1]),
+        }
+
+        if let Some(search) = self.search.as_mut() {
+            search.draw(frame, area);
+        }
+        if let Some(switcher) = self.quick_switcher.as_mut() {
+            switcher.draw(frame, area);
+        }
+        if let Some(view) = self.trash_view.as_mut() {
+            view.draw(frame, area);
+        }
+        if let Some(view) = self.history_view.as_mut() {
+            view.draw(frame, area);
+        }
+        if let Some((_, picker)) = self.stack_picker.as_mut() {
+            picker.draw(frame, area);
+        }
+        if self.mode == Mode::Unlock {
+            self.draw_unlock_ui(frame, area);
+        }
 
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crossterm::event::KeyModifiers;
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+    use crate::db_reader::MemoryDbReader;
+
+    fn home_with(db: MemoryDbReader) -> Result<Home> {
+        let mut home = Home::new();
+        home.register_db_handler(Arc::new(db))?;
+        home.init(Rect::default())?;
+        Ok(home)
+    }
+
+    fn press(home: &mut Home, code: KeyCode) -> Result<Option<Action>> {
+        home.handle_key_events(KeyEvent::new(code, KeyModifiers::empty()))
+    }
+
+    fn sample_db() -> Result<MemoryDbReader> {
+        let db = MemoryDbReader::new();
+        for id in 1..=2 {
+            db.save_stack(&Stack::named(format!("Stack {id}")))?;
+        }
+        for id in 1..=3 {
+            db.save_entry(&Entry::new(2, format!("Entry {id}")))?;
+        }
+        Ok(db)
+    }
+
+    #[test]
+    fn test_init_loads_stacks() -> Result<()> {
+        let home = home_with(sample_db()?)?;
+        assert_eq!(home.stacks.len(), 2);
+        assert_eq!(home.stack_state.selected(), Some(0));
+        Ok(())
+    }
+
+    #[test]
+    fn test_enter_loads_entries_of_selected_stack() -> Result<()> {
+        let mut home = home_with(sample_db()?)?;
+        press(&mut home, KeyCode::Down)?;
+        assert_eq!(press(&mut home, KeyCode::Enter)?, Some(Action::Render));
+        assert!(home.mode == Mode::BrowseEntries);
+        assert_eq!(
+            home.entries.iter().map(|e| e.id).collect::<Vec<_>>(),
+            vec![1, 2, 3]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_navigation_without_stacks() -> Result<()> {
+        let mut home = home_with(MemoryDbReader::new())?;
+        press(&mut home, KeyCode::Down)?;
+        assert_eq!(press(&mut home, KeyCode::Enter)?, None);
+        assert!(home.mode == Mode::BrowseStacks);
+        Ok(())
+    }
+
+    fn type_text(home: &mut Home, text: &str) -> Result<()> {
+        for c in text.chars() {
+            press(home, KeyCode::Char(c))?;
+        }
+        Ok(())
+    }
+
+    fn save(home: &mut Home) -> Result<Option<Action>> {
+        home.handle_key_events(KeyEvent::new(KeyCode::Char('s'), KeyModifiers::CONTROL))
+    }
+
+    #[test]
+    fn test_new_entry_is_captured_into_today() -> Result<()> {
+        let mut home = home_with(sample_db()?)?;
+        assert_eq!(press(&mut home, KeyCode::Char('n'))?, Some(Action::ChangeMode(AppMode::Insert)));
+        type_text(&mut home, "first line")?;
+        press(&mut home, KeyCode::Enter)?;
+        type_text(&mut home, "second line")?;
+        assert_eq!(save(&mut home)?, Some(Action::ChangeMode(AppMode::Home)));
+
+        assert!(home.mode == Mode::BrowseEntries);
+        assert!(home.editor.is_none());
+        let selected = &home.entries[home.entry_state.selected().unwrap()];
+        assert_eq!(selected.content, "first line\nsecond line");
+        let stack = &home.stacks[home.stack_state.selected().unwrap()];
+        assert_eq!(stack.day, Some(home.calendar.today()));
+        assert_eq!(stack.count, 1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_edit_updates_selected_entry() -> Result<()> {
+        let mut home = home_with(sample_db()?)?;
+        press(&mut home, KeyCode::Down)?;
+        press(&mut home, KeyCode::Enter)?;
+        press(&mut home, KeyCode::Down)?;
+        press(&mut home, KeyCode::Char('e'))?;
+        type_text(&mut home, " edited")?;
+        save(&mut home)?;
+
+        assert_eq!(home.entry_state.selected(), Some(1));
+        assert_eq!(home.entries[1].content, "Entry 2 edited");
+        assert_eq!(home.db()?.get_entry(2)?.unwrap().content, "Entry 2 edited");
+        Ok(())
+    }
+
+    #[test]
+    fn test_external_edit_saves_result() -> Result<()> {
+        let mut home = home_with(sample_db()?)?;
+        press(&mut home, KeyCode::Down)?;
+        press(&mut home, KeyCode::Enter)?;
+        let action = press(&mut home, KeyCode::Char('v'))?;
+        assert_eq!(action, Some(Action::EditExternally("Entry 1".to_string())));
+
+        home.update(Action::ExternalEditFinished(Some("Entry 1, longer".to_string())))?;
+        assert_eq!(home.db()?.get_entry(1)?.unwrap().content, "Entry 1, longer");
+        assert!(home.editing.is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn test_cancel_discards_editor() -> Result<()> {
+        let mut home = home_with(sample_db()?)?;
+        press(&mut home, KeyCode::Char('n'))?;
+        type_text(&mut home, "never mind")?;
+        assert_eq!(press(&mut home, KeyCode::Esc)?, Some(Action::ChangeMode(AppMode::Home)));
+        assert!(home.mode == Mode::BrowseStacks);
+        assert_eq!(home.db()?.get_all_entries()?.len(), 3);
+        Ok(())
+    }
+
+    fn run_command(home: &mut Home, line: &str) -> Result<Option<Action>> {
+        assert_eq!(press(home, KeyCode::Char(':'))?, Some(Action::ChangeMode(AppMode::Insert)));
+        type_text(home, line)?;
+        press(home, KeyCode::Enter)
+    }
+
+    #[test]
+    fn test_command_rename_and_move() -> Result<()> {
+        let mut home = home_with(sample_db()?)?;
+        assert_eq!(run_command(&mut home, "rename Inbox")?, Some(Action::ChangeMode(AppMode::Home)));
+        assert!(home.mode == Mode::BrowseStacks);
+        assert_eq!(home.selected_stack()?.name, "Inbox");
+
+        press(&mut home, KeyCode::Down)?;
+        press(&mut home, KeyCode::Enter)?;
+        run_command(&mut home, "move Inbox")?;
+        assert_eq!(home.selected_stack()?.name, "Inbox");
+        assert_eq!(home.selected_entry()?.id, 1);
+        assert_eq!(home.stacks.iter().map(|s| s.count).collect::<Vec<_>>(), vec![1, 2]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_command_delete_entry() -> Result<()> {
+        let mut home = home_with(sample_db()?)?;
+        press(&mut home, KeyCode::Down)?;
+        press(&mut home, KeyCode::Enter)?;
+        run_command(&mut home, "delete")?;
+        assert!(home.mode == Mode::BrowseEntries);
+        assert_eq!(home.entries.iter().map(|e| e.id).collect::<Vec<_>>(), vec![2, 3]);
+        assert_eq!(home.entry_state.selected(), Some(0));
+        Ok(())
+    }
+
+    #[test]
+    fn test_deleted_stack_is_restored_from_the_trash() -> Result<()> {
+        let mut home = home_with(sample_db()?)?;
+        press(&mut home, KeyCode::Down)?;
+        run_command(&mut home, "delete")?;
+        assert_eq!(
+            home.status,
+            Some(Status::Info("Moved Stack 2 with 3 entries to the trash, :trash to restore it".to_string()))
+        );
+        assert_eq!(home.stacks.len(), 1);
+
+        assert_eq!(run_command(&mut home, "trash")?, Some(Action::ChangeMode(AppMode::Insert)));
+        assert!(home.mode == Mode::Trash);
+        press(&mut home, KeyCode::Enter)?;
+        assert_eq!(home.status, Some(Status::Info("Restored Stack 2 with 3 entries".to_string())));
+        assert_eq!(home.stacks.iter().map(|s| s.count).collect::<Vec<_>>(), vec![0, 3]);
+        assert_eq!(press(&mut home, KeyCode::Esc)?, Some(Action::ChangeMode(AppMode::Home)));
+        assert!(home.mode == Mode::BrowseStacks);
+        assert_eq!(home.db()?.get_trash()?, vec![]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_history_restores_a_revision() -> Result<()> {
+        let mut home = home_with(sample_db()?)?;
+        press(&mut home, KeyCode::Down)?;
+        press(&mut home, KeyCode::Enter)?;
+        press(&mut home, KeyCode::Char('e'))?;
+        type_text(&mut home, " edited")?;
+        save(&mut home)?;
+
+        assert_eq!(press(&mut home, KeyCode::Char('h'))?, Some(Action::ChangeMode(AppMode::Insert)));
+        assert!(home.mode == Mode::History);
+        press(&mut home, KeyCode::Down)?;
+        assert_eq!(press(&mut home, KeyCode::Enter)?, Some(Action::ChangeMode(AppMode::Home)));
+        assert!(home.mode == Mode::BrowseEntries);
+        assert_eq!(home.selected_entry()?.content, "Entry 1");
+        let revisions = home.db()?.get_revisions(1)?.into_iter().map(|r| r.content).collect::<Vec<_>>();
+        assert_eq!(revisions, vec!["Entry 1", "Entry 1 edited"]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_stack_picker_moves_and_copies() -> Result<()> {
+        let mut home = home_with(sample_db()?)?;
+        press(&mut home, KeyCode::Down)?;
+        press(&mut home, KeyCode::Enter)?;
+        assert_eq!(press(&mut home, KeyCode::Char('m'))?, Some(Action::ChangeMode(AppMode::Insert)));
+        assert!(home.mode == Mode::PickStack);
+        type_text(&mut home, "inbox")?;
+        assert_eq!(press(&mut home, KeyCode::Enter)?, Some(Action::ChangeMode(AppMode::Home)));
+        assert_eq!(home.status, Some(Status::Info("Moved entry 1 to inbox".to_string())));
+        assert_eq!(home.selected_stack()?.name, "inbox");
+
+        press(&mut home, KeyCode::Char('c'))?;
+        type_text(&mut home, "stk2")?;
+        press(&mut home, KeyCode::Enter)?;
+        assert_eq!(home.status, Some(Status::Info("Copied entry 1 to Stack 2 as entry 4".to_string())));
+        assert_eq!(home.selected_entry()?.content, "Entry 1");
+        let counts = home.stacks.iter().map(|s| (s.name.as_str(), s.count)).collect::<Vec<_>>();
+        assert_eq!(counts, vec![("Stack 1", 0), ("Stack 2", 3), ("inbox", 1)]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_marked_entries_are_moved_together() -> Result<()> {
+        let mut home = home_with(sample_db()?)?;
+        press(&mut home, KeyCode::Down)?;
+        press(&mut home, KeyCode::Enter)?;
+        press(&mut home, KeyCode::Char(' '))?;
+        assert_eq!(home.entry_state.selected(), Some(1));
+        press(&mut home, KeyCode::Char(' '))?;
+        press(&mut home, KeyCode::Up)?;
+        press(&mut home, KeyCode::Char(' '))?;
+        assert_eq!(home.marked, BTreeSet::from([1]));
+
+        press(&mut home, KeyCode::Char('V'))?;
+        press(&mut home, KeyCode::Up)?;
+        assert_eq!(home.marked, BTreeSet::from([1, 2, 3]));
+        press(&mut home, KeyCode::Down)?;
+        assert_eq!(home.marked, BTreeSet::from([1, 3]));
+        press(&mut home, KeyCode::Esc)?;
+        assert!(home.visual.is_none() && home.mode == Mode::BrowseEntries);
+
+        run_command(&mut home, "move Stack 1")?;
+        assert_eq!(home.status, Some(Status::Info("Moved 2 entries to Stack 1".to_string())));
+        assert_eq!(home.stacks.iter().map(|s| s.count).collect::<Vec<_>>(), vec![2, 1]);
+        assert!(home.marked.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_marked_entries_are_merged_and_deleted() -> Result<()> {
+        let mut home = home_with(sample_db()?)?;
+        press(&mut home, KeyCode::Down)?;
+        press(&mut home, KeyCode::Enter)?;
+        run_command(&mut home, "merge")?;
+        assert_eq!(home.status, Some(Status::Error("Mark the entries to merge first, with space or V".to_string())));
+
+        press(&mut home, KeyCode::Char('V'))?;
+        press(&mut home, KeyCode::Down)?;
+        run_command(&mut home, "merge")?;
+        assert_eq!(
+            home.status,
+            Some(Status::Info("Merged 2 entries into entry 1, the others are in the trash".to_string()))
+        );
+        assert_eq!(
+            home.entries.iter().map(|e| e.content.as_str()).collect::<Vec<_>>(),
+            vec!["Entry 1\n\nEntry 2", "Entry 3"]
+        );
+
+        press(&mut home, KeyCode::Char(' '))?;
+        press(&mut home, KeyCode::Char(' '))?;
+        run_command(&mut home, "delete")?;
+        assert_eq!(
+            home.status,
+            Some(Status::Info("Moved 2 entries to the trash, :trash to restore them".to_string()))
+        );
+        assert!(home.entries.is_empty());
+        assert_eq!(home.db()?.get_trash()?.len(), 3);
+        Ok(())
+    }
+
+    #[test]
+    fn test_command_errors_are_shown() -> Result<()> {
+        let mut home = home_with(sample_db()?)?;
+        run_command(&mut home, "move Inbox")?;
+        assert_eq!(home.status, Some(Status::Error("No entry is selected".to_string())));
+        assert!(home.mode == Mode::BrowseStacks);
+        press(&mut home, KeyCode::Down)?;
+        assert_eq!(home.status, None);
+
+        run_command(&mut home, "rename Stack 1")?;
+        assert_eq!(home.status, Some(Status::Error("There already is a stack named Stack 1".to_string())));
+        assert_eq!(run_command(&mut home, "quit")?, Some(Action::Quit));
+        Ok(())
+    }
+
+    #[test]
+    fn test_search_opens_entry_in_its_stack() -> Result<()> {
+        let mut home = home_with(sample_db()?)?;
+        assert_eq!(press(&mut home, KeyCode::Char('/'))?, Some(Action::ChangeMode(AppMode::Insert)));
+        type_text(&mut home, "\"entry 3\"")?;
+        assert_eq!(press(&mut home, KeyCode::Enter)?, Some(Action::ChangeMode(AppMode::Home)));
+        assert!(home.search.is_none());
+        assert!(home.mode == Mode::BrowseEntries);
+        assert_eq!(home.selected_stack()?.name, "Stack 2");
+        assert_eq!(home.selected_entry()?.id, 3);
+        Ok(())
+    }
+
+    #[test]
+    fn test_search_saved_as_smart_stack() -> Result<()> {
+        let mut home = home_with(sample_db()?)?;
+        press(&mut home, KeyCode::Char('/'))?;
+        type_text(&mut home, "entry -3")?;
+        home.handle_key_events(KeyEvent::new(KeyCode::Char('s'), KeyModifiers::CONTROL))?;
+        assert!(home.mode == Mode::BrowseStacks);
+        let smart = home.selected_stack()?;
+        assert_eq!((smart.name.as_str(), smart.query.as_deref(), smart.count), ("entry -3", Some("entry -3"), 2));
+        assert_eq!(home.stack_state.selected(), Some(0));
+
+        press(&mut home, KeyCode::Enter)?;
+        assert_eq!(home.entries.iter().map(|e| e.id).collect::<Vec<_>>(), vec![2, 1]);
+        run_command(&mut home, "new entry -3")?;
+        assert_eq!(
+            home.status,
+            Some(Status::Error("entry -3 is a smart stack, entries can't be added to it".to_string()))
+        );
+
+        run_command(&mut home, "smart stack:nowhere")?;
+        assert_eq!(home.stacks.len(), 4);
+        press(&mut home, KeyCode::Enter)?;
+        assert_eq!(home.status, Some(Status::Error("There is no stack named nowhere".to_string())));
+        assert!(home.mode == Mode::BrowseStacks);
+        Ok(())
+    }
+
+    #[test]
+    fn test_quick_switcher_jumps_to_stack() -> Result<()> {
+        let mut home = home_with(sample_db()?)?;
+        home.handle_key_events(KeyEvent::new(KeyCode::Char('p'), KeyModifiers::CONTROL))?;
+        assert!(home.mode == Mode::QuickSwitch);
+        type_text(&mut home, "stk2")?;
+        press(&mut home, KeyCode::Enter)?;
+        assert!(home.mode == Mode::BrowseStacks);
+        assert_eq!(home.selected_stack()?.name, "Stack 2");
+        Ok(())
+    }
+
+    #[test]
+    fn test_encrypted_journal_asks_for_passphrase() -> Result<()> {
+        let db = sample_db()?;
+        db.set_passphrase("secret")?;
+        db.lock();
+        let mut home = home_with(db)?;
+        assert!(home.mode == Mode::Unlock);
+        assert!(home.stacks.is_empty());
+
+        type_text(&mut home, "wrong")?;
+        press(&mut home, KeyCode::Enter)?;
+        assert_eq!(home.unlock_message, Some(Status::Error("Wrong passphrase".to_string())));
+        assert_eq!(home.passphrase.as_str(), "");
+
+        type_text(&mut home, "secret")?;
+        assert_eq!(press(&mut home, KeyCode::Enter)?, Some(Action::ChangeMode(AppMode::Home)));
+        assert!(home.mode == Mode::BrowseStacks);
+        assert_eq!(home.stacks.len(), 2);
+        Ok(())
+    }
+
+    #[test]
+    fn test_idle_journal_locks() -> Result<()> {
+        let db = sample_db()?;
+        db.set_passphrase("secret")?;
+        let mut home = home_with(db)?;
+        home.config.journal.auto_lock_minutes = Some(5);
+        press(&mut home, KeyCode::Char('n'))?;
+        assert!(home.mode == Mode::Editing);
+        assert_eq!(home.update(Action::Tick)?, None);
+
+        home.last_key_press = Some(Instant::now() - Duration::from_secs(5 * 60));
+        assert_eq!(home.update(Action::Tick)?, Some(Action::ChangeMode(AppMode::Insert)));
+        assert!(home.mode == Mode::Unlock);
+        assert!(home.editor.is_none() && home.stacks.is_empty() && home.entries.is_empty());
+        assert!(home.db()?.is_locked()?);
+        assert_eq!(
+            home.unlock_message,
+            Some(Status::Info("Locked after 5 idle minutes, the unsaved edit was discarded".to_string()))
+        );
+
+        type_text(&mut home, "secret")?;
+        press(&mut home, KeyCode::Enter)?;
+        assert!(home.mode == Mode::BrowseStacks);
+        assert_eq!(home.stacks.len(), 2);
+        Ok(())
+    }
+
+    #[test]
+    fn test_untouched_journal_locks() -> Result<()> {
+        let db = sample_db()?;
+        db.set_passphrase("secret")?;
+        let mut home = home_with(db)?;
+        home.config.journal.auto_lock_minutes = Some(5);
+        assert_eq!(home.update(Action::Tick)?, None);
+
+        home.last_key_press = home.last_key_press.map(|at| at - Duration::from_secs(5 * 60));
+        assert_eq!(home.update(Action::Tick)?, Some(Action::ChangeMode(AppMode::Insert)));
+        assert!(home.mode == Mode::Unlock);
+        assert!(home.db()?.is_locked()?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_init_without_db_fails() {
+        assert!(Home::new().init(Rect::default()).is_err());
+    }
+}
@@ -0,0 +1,223 @@
+use color_eyre::eyre::Result;
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use fuzzy_matcher::{skim::SkimMatcherV2, FuzzyMatcher};
+use ratatui::{prelude::*, widgets::*};
+
+use super::popup_area;
+use crate::{
+    calendar::Calendar,
+    db_reader::DbReader,
+    models::{entry::Entry, stack::Stack},
+    query,
+    tui::Frame,
+};
+
+/// Where the quick switcher jumps to.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Target {
+    Stack(Stack),
+    Entry(Entry),
+}
+
+/// What the user decided to do with the quick switcher.
+#[derive(Debug, Clone, PartialEq)]
+pub enum QuickSwitcherEvent {
+    Jump(Target),
+    Cancel,
+}
+
+/// Something that can be jumped to, with the text it is matched by.
+struct Candidate {
+    target: Target,
+    label: String,
+    /// Shown next to the label but not matched, e.g. the stack of an entry.
+    detail: String,
+}
+
+struct Match {
+    candidate: usize,
+    score: i64,
+    /// Character positions of the label that matched the pattern.
+    indices: Vec<usize>,
+}
+
+/// A Ctrl-P style popup that fuzzy-matches stack names and the first lines of entries.
+pub struct QuickSwitcher {
+    matcher: SkimMatcherV2,
+    candidates: Vec<Candidate>,
+    input: String,
+    matches: Vec<Match>,
+    match_state: ListState,
+}
+
+impl QuickSwitcher {
+    pub fn new(db: &dyn DbReader, calendar: &Calendar) -> Result<Self> {
+        let mut stacks = db.get_stacks()?;
+        query::count_smart_stacks(db, calendar, &mut stacks);
+        Stack::sort_for_display(&mut stacks);
+        let mut entries = db.get_all_entries()?;
+        entries.sort_by_key(|e| std::cmp::Reverse(e.created_key()));
+
+        let mut candidates = stacks
+            .iter()
+            .map(|stack| Candidate {
+                target: Target::Stack(stack.clone()),
+                label: stack.name.clone(),
+                detail: format!("{} entries", stack.count),
+            })
+            .collect::<Vec<_>>();
+        candidates.extend(entries.into_iter().map(|entry| {
+            let label = entry.content.lines().find(|l| !l.trim().is_empty()).unwrap_or_default().trim().to_string();
+            let stack = stacks.iter().find(|s| s.id == entry.stack_id).map(|s| s.name.clone()).unwrap_or_default();
+            Candidate {
+                target: Target::Entry(entry),
+                label,
+                detail: stack,
+            }
+        }));
+
+        let mut switcher = Self {
+            matcher: SkimMatcherV2::default(),
+            candidates,
+            input: String::new(),
+            matches: Vec::new(),
+            match_state: ListState::default(),
+        };
+        switcher.update_matches();
+        Ok(switcher)
+    }
+
+    pub fn handle_key_events(&mut self, key: KeyEvent) -> Option<QuickSwitcherEvent> {
+        let selected = self.match_state.selected().unwrap_or(0);
+        match key.code {
+            KeyCode::Esc => return Some(QuickSwitcherEvent::Cancel),
+            KeyCode::Enter => {
+                let target = self.matches.get(selected).map(|m| self.candidates[m.candidate].target.clone());
+                return target.map(QuickSwitcherEvent::Jump);
+            }
+            KeyCode::Down => self.match_state.select(Some((selected + 1).min(self.matches.len().saturating_sub(1)))),
+            KeyCode::Up => self.match_state.select(Some(selected.saturating_sub(1))),
+            KeyCode::Char(c) if !key.modifiers.contains(KeyModifiers::CONTROL) => {
+                self.input.push(c);
+                self.update_matches();
+            }
+            KeyCode::Backspace => {
+                self.input.pop();
+                self.update_matches();
+            }
+            _ => {}
+        }
+        None
+    }
+
+    /// Ranks the candidates against the input. Without input every candidate is listed,
+    /// stacks first.
+    fn update_matches(&mut self) {
+        self.matches = self
+            .candidates
+            .iter()
+            .enumerate()
+            .filter_map(|(i, candidate)| {
+                let (score, indices) = self.matcher.fuzzy_indices(&candidate.label, &self.input)?;
+                Some(Match {
+                    candidate: i,
+                    score,
+                    indices,
+                })
+            })
+            .collect();
+        // The sort is stable, so equal scores keep stacks ahead of entries.
+        self.matches.sort_by_key(|m| std::cmp::Reverse(m.score));
+        self.match_state.select(Some(0));
+    }
+
+    pub fn draw(&mut self, frame: &mut Frame<'_>, area: Rect) {
+        let area = popup_area(area, 60, 60);
+        frame.render_widget(Clear, area);
+        let layout = Layout::new(Direction::Vertical, [Constraint::Length(3), Constraint::Min(0)]).split(area);
+
+        let input_block = Block::default().title("Go to").borders(Borders::ALL);
+        let inner = input_block.inner(layout[0]);
+        frame.render_widget(Paragraph::new(self.input.clone()).block(input_block), layout[0]);
+        let x = inner.x.saturating_add(u16::try_from(self.input.chars().count()).unwrap_or(u16::MAX));
+        frame.set_cursor(x.min(inner.right().saturating_sub(1)), inner.y);
+
+        let highlight = Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD);
+        let dim = Style::default().fg(Color::Rgb(100, 100, 100));
+        let lines = self
+            .matches
+            .iter()
+            .map(|m| {
+                let candidate = &self.candidates[m.candidate];
+                let marker = match candidate.target {
+                    Target::Stack(_) => "▤ ",
+                    Target::Entry(_) => "  ",
+                };
+                let mut spans = vec![Span::styled(marker, dim)];
+                spans.extend(candidate.label.chars().enumerate().map(|(i, c)| {
+                    let style = if m.indices.binary_search(&i).is_ok() { highlight } else { Style::default() };
+                    Span::styled(c.to_string(), style)
+                }));
+                spans.push(Span::styled(format!("  {}", candidate.detail), dim));
+                Line::from(spans)
+            })
+            .collect::<Vec<Line>>();
+        let list = List::new(lines)
+            .block(Block::default().borders(Borders::ALL))
+            .highlight_symbol("▶️ ");
+        frame.render_stateful_widget(list, layout[1], &mut self.match_state);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+    use crate::{config::JournalConfig, db_reader::MemoryDbReader};
+
+    fn type_text(switcher: &mut QuickSwitcher, text: &str) {
+        for c in text.chars() {
+            switcher.handle_key_events(KeyEvent::new(KeyCode::Char(c), KeyModifiers::empty()));
+        }
+    }
+
+    fn labels(switcher: &QuickSwitcher) -> Vec<&str> {
+        switcher.matches.iter().map(|m| switcher.candidates[m.candidate].label.as_str()).collect()
+    }
+
+    #[test]
+    fn test_matches_stacks_and_entry_first_lines() -> Result<()> {
+        let db = MemoryDbReader::new();
+        let work = db.save_stack(&Stack::named("work".to_string()))?;
+        db.save_stack(&Stack::named("workouts".to_string()))?;
+        db.save_entry(&Entry::new(work.id, "\nweekly review\nwith notes".to_string()))?;
+        let calendar = Calendar::new(&JournalConfig::default())?;
+        let mut switcher = QuickSwitcher::new(&db, &calendar)?;
+        assert_eq!(labels(&switcher), vec!["work", "workouts", "weekly review"]);
+
+        type_text(&mut switcher, "wkr");
+        assert_eq!(labels(&switcher), vec!["weekly review"]);
+        assert_eq!(switcher.matches[0].indices, vec![0, 3, 7]);
+
+        let enter = KeyEvent::new(KeyCode::Enter, KeyModifiers::empty());
+        let Some(QuickSwitcherEvent::Jump(Target::Entry(entry))) = switcher.handle_key_events(enter) else {
+            panic!("expected a jump to the entry");
+        };
+        assert_eq!(entry.id, 1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_smart_stacks_show_what_they_match() -> Result<()> {
+        let db = MemoryDbReader::new();
+        let work = db.save_stack(&Stack::named("work".to_string()))?;
+        db.save_stack(&Stack::smart("reviews".to_string(), "review".to_string()))?;
+        db.save_entry(&Entry::new(work.id, "weekly review".to_string()))?;
+        db.save_entry(&Entry::new(work.id, "standup".to_string()))?;
+        let switcher = QuickSwitcher::new(&db, &Calendar::new(&JournalConfig::default())?)?;
+        let details = switcher.candidates.iter().map(|c| (c.label.as_str(), c.detail.as_str())).collect::<Vec<_>>();
+        assert_eq!(&details[..2], &[("reviews", "1 entries"), ("work", "2 entries")]);
+        Ok(())
+    }
+}
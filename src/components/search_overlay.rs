@@ -0,0 +1,180 @@
+use std::{collections::HashMap, sync::Arc};
+
+use chrono::Local;
+use color_eyre::eyre::Result;
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use ratatui::{prelude::*, widgets::*};
+
+use super::popup_area;
+use crate::{calendar::Calendar, db_reader::DbReader, models::entry::Entry, query::Query, search, tui::Frame};
+
+/// What the user decided to do with the search.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SearchOverlayEvent {
+    Open(Entry),
+    /// Keep the query as a smart stack.
+    Save(String),
+    Cancel,
+}
+
+/// A popup that searches entries as the query is typed.
+pub struct SearchOverlay {
+    db: Arc<dyn DbReader>,
+    calendar: Calendar,
+    stack_names: HashMap<u32, String>,
+    input: String,
+    query: Query,
+    hits: Vec<Entry>,
+    hit_state: ListState,
+    /// Why the current input can't be searched, e.g. an unclosed quote or an unknown stack.
+    error: Option<String>,
+}
+
+impl SearchOverlay {
+    const DATE_FORMAT: &str = "%Y-%m-%d";
+
+    pub fn new(db: Arc<dyn DbReader>, calendar: Calendar) -> Result<Self> {
+        let stack_names = db.get_stacks()?.into_iter().map(|s| (s.id, s.name)).collect();
+        Ok(Self {
+            db,
+            calendar,
+            stack_names,
+            input: String::new(),
+            query: Query::default(),
+            hits: Vec::new(),
+            hit_state: ListState::default(),
+            error: None,
+        })
+    }
+
+    pub fn handle_key_events(&mut self, key: KeyEvent) -> Result<Option<SearchOverlayEvent>> {
+        let selected = self.hit_state.selected().unwrap_or(0);
+        match key.code {
+            KeyCode::Esc => return Ok(Some(SearchOverlayEvent::Cancel)),
+            KeyCode::Enter => {
+                return Ok(self.hits.get(selected).map(|entry| SearchOverlayEvent::Open(entry.clone())));
+            }
+            // Only a query that runs is worth keeping.
+            KeyCode::Char('s')
+                if key.modifiers.contains(KeyModifiers::CONTROL) && self.error.is_none() && !self.input.trim().is_empty() =>
+            {
+                return Ok(Some(SearchOverlayEvent::Save(self.input.trim().to_string())));
+            }
+            KeyCode::Down => self.hit_state.select(Some((selected + 1).min(self.hits.len().saturating_sub(1)))),
+            KeyCode::Up => self.hit_state.select(Some(selected.saturating_sub(1))),
+            KeyCode::Char(c) if !key.modifiers.contains(KeyModifiers::CONTROL) => {
+                self.input.push(c);
+                self.run()?;
+            }
+            KeyCode::Backspace => {
+                self.input.pop();
+                self.run()?;
+            }
+            _ => {}
+        }
+        Ok(None)
+    }
+
+    /// Searches for the current input. Half-typed queries that don't parse, or name a
+    /// stack that doesn't exist yet, keep the previous results.
+    fn run(&mut self) -> Result<()> {
+        let query = match Query::parse(&self.input) {
+            Ok(query) => query,
+            Err(e) => {
+                self.error = Some(e.to_string());
+                return Ok(());
+            }
+        };
+        let hits = match query.is_empty() {
+            true => Vec::new(),
+            false => match query.run(self.db.as_ref(), &self.calendar) {
+                Ok(hits) => hits,
+                Err(e) => {
+                    self.error = Some(e.to_string());
+                    return Ok(());
+                }
+            },
+        };
+        self.hits = hits;
+        self.query = query;
+        self.error = None;
+        self.hit_state.select(Some(0));
+        Ok(())
+    }
+
+    pub fn draw(&mut self, frame: &mut Frame<'_>, area: Rect) {
+        let area = popup_area(area, 80, 70);
+        frame.render_widget(Clear, area);
+        let layout = Layout::new(Direction::Vertical, [Constraint::Length(3), Constraint::Min(0)]).split(area);
+
+        let input_block = Block::default().title("Search").borders(Borders::ALL);
+        let input_block = match &self.error {
+            Some(error) => input_block.title_bottom(Line::from(error.clone()).style(Style::default().fg(Color::Red))),
+            None => input_block,
+        };
+        let inner = input_block.inner(layout[0]);
+        frame.render_widget(Paragraph::new(format!("/{}", self.input)).block(input_block), layout[0]);
+        let x = inner.x.saturating_add(u16::try_from(self.input.chars().count() + 1).unwrap_or(u16::MAX));
+        frame.set_cursor(x.min(inner.right().saturating_sub(1)), inner.y);
+
+        let lines = self
+            .hits
+            .iter()
+            .map(|entry| {
+                let stack = self.stack_names.get(&entry.stack_id).cloned().unwrap_or_default();
+                let created = entry.created_at.with_timezone(&Local).format(Self::DATE_FORMAT);
+                Line::from(vec![
+                    Span::styled(format!("{created}  {stack}  "), Style::default().fg(Color::Rgb(100, 100, 100))),
+                    Span::raw(search::snippet(entry, &self.query.text)),
+                ])
+            })
+            .collect::<Vec<Line>>();
+        let list = List::new(lines)
+            .block(Block::default().title(format!("{} results", self.hits.len())).borders(Borders::ALL))
+            .highlight_symbol("▶️ ");
+        frame.render_stateful_widget(list, layout[1], &mut self.hit_state);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+    use crate::{db_reader::MemoryDbReader, models::stack::Stack};
+
+    fn press(overlay: &mut SearchOverlay, code: KeyCode) -> Result<Option<SearchOverlayEvent>> {
+        overlay.handle_key_events(KeyEvent::new(code, KeyModifiers::empty()))
+    }
+
+    #[test]
+    fn test_searches_while_typing() -> Result<()> {
+        let db = MemoryDbReader::new();
+        let stack = db.save_stack(&Stack::named("ideas".to_string()))?;
+        db.save_entry(&Entry::new(stack.id, "buy milk".to_string()))?;
+        let tea = db.save_entry(&Entry::new(stack.id, "buy tea".to_string()))?;
+        let mut overlay = SearchOverlay::new(Arc::new(db), Calendar::default())?;
+
+        for c in "buy \"te".chars() {
+            press(&mut overlay, KeyCode::Char(c))?;
+        }
+        assert_eq!(overlay.hits.len(), 2);
+        assert!(overlay.error.is_some());
+
+        press(&mut overlay, KeyCode::Char('a'))?;
+        press(&mut overlay, KeyCode::Char('"'))?;
+        assert_eq!(overlay.error, None);
+        assert_eq!(press(&mut overlay, KeyCode::Enter)?, Some(SearchOverlayEvent::Open(tea.clone())));
+
+        for c in " stack:id".chars() {
+            press(&mut overlay, KeyCode::Char(c))?;
+        }
+        assert_eq!(overlay.error, Some("There is no stack named id".to_string()));
+        for c in "eas".chars() {
+            press(&mut overlay, KeyCode::Char(c))?;
+        }
+        assert_eq!(overlay.error, None);
+        assert_eq!(overlay.hits, vec![tea]);
+        Ok(())
+    }
+}
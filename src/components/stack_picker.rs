@@ -0,0 +1,116 @@
+use color_eyre::eyre::Result;
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use fuzzy_matcher::{skim::SkimMatcherV2, FuzzyMatcher};
+use ratatui::{prelude::*, widgets::*};
+
+use super::popup_area;
+use crate::{db_reader::DbReader, models::stack::Stack, tui::Frame};
+
+/// What the user decided to do with the stack picker.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StackPickerEvent {
+    /// The name of the picked stack, which doesn't exist yet when nothing matched.
+    Pick(String),
+    Cancel,
+}
+
+/// A popup that fuzzy-matches the stacks entries can be added to. Enter picks the
+/// highlighted stack, or a new stack named as typed when none matches.
+pub struct StackPicker {
+    title: String,
+    matcher: SkimMatcherV2,
+    stacks: Vec<Stack>,
+    input: String,
+    /// Indices of the matching stacks, best match first.
+    matches: Vec<usize>,
+    match_state: ListState,
+}
+
+impl StackPicker {
+    /// Offers every stack but smart stacks and the one with `exclude`.
+    pub fn new(db: &dyn DbReader, title: impl Into<String>, exclude: u32) -> Result<Self> {
+        let mut stacks = db.get_stacks()?;
+        stacks.retain(|s| !s.is_smart() && s.id != exclude);
+        Stack::sort_for_display(&mut stacks);
+        let mut picker = Self {
+            title: title.into(),
+            matcher: SkimMatcherV2::default(),
+            stacks,
+            input: String::new(),
+            matches: Vec::new(),
+            match_state: ListState::default(),
+        };
+        picker.update_matches();
+        Ok(picker)
+    }
+
+    pub fn handle_key_events(&mut self, key: KeyEvent) -> Option<StackPickerEvent> {
+        let selected = self.match_state.selected().unwrap_or(0);
+        match key.code {
+            KeyCode::Esc => return Some(StackPickerEvent::Cancel),
+            KeyCode::Enter => {
+                let name = match self.matches.get(selected) {
+                    Some(&i) => self.stacks[i].name.clone(),
+                    None => self.input.trim().to_string(),
+                };
+                return (!name.is_empty()).then_some(StackPickerEvent::Pick(name));
+            }
+            KeyCode::Down => self.match_state.select(Some((selected + 1).min(self.matches.len().saturating_sub(1)))),
+            KeyCode::Up => self.match_state.select(Some(selected.saturating_sub(1))),
+            KeyCode::Char(c) if !key.modifiers.contains(KeyModifiers::CONTROL) => {
+                self.input.push(c);
+                self.update_matches();
+            }
+            KeyCode::Backspace => {
+                self.input.pop();
+                self.update_matches();
+            }
+            _ => {}
+        }
+        None
+    }
+
+    fn update_matches(&mut self) {
+        let mut scored = self
+            .stacks
+            .iter()
+            .enumerate()
+            .filter_map(|(i, stack)| Some((self.matcher.fuzzy_match(&stack.name, &self.input)?, i)))
+            .collect::<Vec<_>>();
+        // The sort is stable, so equal scores keep the display order.
+        scored.sort_by_key(|(score, _)| std::cmp::Reverse(*score));
+        self.matches = scored.into_iter().map(|(_, i)| i).collect();
+        self.match_state.select(Some(0));
+    }
+
+    pub fn draw(&mut self, frame: &mut Frame<'_>, area: Rect) {
+        let area = popup_area(area, 50, 60);
+        frame.render_widget(Clear, area);
+        let layout = Layout::new(Direction::Vertical, [Constraint::Length(3), Constraint::Min(0)]).split(area);
+
+        let input_block = Block::default().title(self.title.clone()).borders(Borders::ALL);
+        let inner = input_block.inner(layout[0]);
+        frame.render_widget(Paragraph::new(self.input.clone()).block(input_block), layout[0]);
+        let x = inner.x.saturating_add(u16::try_from(self.input.chars().count()).unwrap_or(u16::MAX));
+        frame.set_cursor(x.min(inner.right().saturating_sub(1)), inner.y);
+
+        let dim = Style::default().fg(Color::Rgb(100, 100, 100));
+        let lines = match self.matches.is_empty() {
+            true if !self.input.trim().is_empty() => {
+                vec![Line::styled(format!("↵ creates {}", self.input.trim()), dim)]
+            }
+            _ => self
+                .matches
+                .iter()
+                .map(|&i| {
+                    let stack = &self.stacks[i];
+                    Line::from(vec![Span::raw(stack.name.clone()), Span::styled(format!(" ({})", stack.count), dim)])
+                })
+                .collect(),
+        };
+        let list = List::new(lines)
+            .block(Block::default().borders(Borders::ALL))
+            .highlight_symbol("▶️ ");
+        frame.render_stateful_widget(list, layout[1], &mut self.match_state);
+    }
+}
@@ -0,0 +1,91 @@
+use chrono::Local;
+use color_eyre::eyre::Result;
+use crossterm::event::{KeyCode, KeyEvent};
+use ratatui::{prelude::*, widgets::*};
+
+use super::popup_area;
+use crate::{db_reader::DbReader, models::trashed::Trashed, tui::Frame};
+
+/// What the user decided to do in the trash view.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TrashViewEvent {
+    /// Puts the item with this id back.
+    Restore(u32),
+    /// Deletes the item with this id for good.
+    Purge(u32),
+    Close,
+}
+
+/// A popup listing what was deleted, newest first.
+pub struct TrashView {
+    items: Vec<Trashed>,
+    state: ListState,
+}
+
+impl TrashView {
+    const DATE_FORMAT: &str = "%Y-%m-%d %H:%M";
+
+    pub fn new(db: &dyn DbReader) -> Result<Self> {
+        let mut view = Self {
+            items: Vec::new(),
+            state: ListState::default(),
+        };
+        view.reload(db)?;
+        Ok(view)
+    }
+
+    /// Reads the trash again, keeping the selection where it was.
+    pub fn reload(&mut self, db: &dyn DbReader) -> Result<()> {
+        self.items = db.get_trash()?;
+        self.items.sort_by_key(|t| std::cmp::Reverse((t.deleted_at, t.id)));
+        let last = self.items.len().checked_sub(1);
+        self.state.select(last.map(|last| self.state.selected().unwrap_or(0).min(last)));
+        Ok(())
+    }
+
+    fn selected(&self) -> Option<&Trashed> {
+        self.state.selected().and_then(|i| self.items.get(i))
+    }
+
+    pub fn handle_key_events(&mut self, key: KeyEvent) -> Option<TrashViewEvent> {
+        let selected = self.state.selected().unwrap_or(0);
+        match key.code {
+            KeyCode::Esc => return Some(TrashViewEvent::Close),
+            KeyCode::Enter | KeyCode::Char('r') => return self.selected().map(|t| TrashViewEvent::Restore(t.id)),
+            KeyCode::Char('d') => return self.selected().map(|t| TrashViewEvent::Purge(t.id)),
+            KeyCode::Down if !self.items.is_empty() => {
+                self.state.select(Some((selected + 1).min(self.items.len() - 1)));
+            }
+            KeyCode::Up if !self.items.is_empty() => self.state.select(Some(selected.saturating_sub(1))),
+            _ => {}
+        }
+        None
+    }
+
+    pub fn draw(&mut self, frame: &mut Frame<'_>, area: Rect) {
+        let area = popup_area(area, 70, 60);
+        frame.render_widget(Clear, area);
+        let block = Block::default().title("Trash").borders(Borders::ALL);
+        if self.items.is_empty() {
+            frame.render_widget(Paragraph::new("The trash is empty").block(block), area);
+            return;
+        }
+
+        let dim = Style::default().fg(Color::Rgb(100, 100, 100));
+        let lines = self
+            .items
+            .iter()
+            .map(|t| {
+                let deleted = t.deleted_at.with_timezone(&Local).format(Self::DATE_FORMAT);
+                let marker = if t.whole_stack { "▤ " } else { "  " };
+                Line::from(vec![
+                    Span::styled(format!("{deleted}  "), dim),
+                    Span::styled(marker, dim),
+                    Span::raw(t.description()),
+                ])
+            })
+            .collect::<Vec<Line>>();
+        let list = List::new(lines).block(block).highlight_symbol("▶️ ");
+        frame.render_stateful_widget(list, area, &mut self.state);
+    }
+}
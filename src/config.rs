@@ -1,5 +1,6 @@
 use std::{collections::HashMap, fmt, path::PathBuf};
 
+use chrono::{DateTime, Utc};
 use color_eyre::eyre::Result;
 use config::Value;
 use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
@@ -23,11 +24,87 @@ pub struct AppConfig {
   pub _config_dir: PathBuf,
 }
 
+#[derive(Clone, Debug, Deserialize, Default)]
+pub struct JournalConfig {
+  /// Hour (0-23) at which a new day starts. Entries captured before it go into the previous day's stack.
+  #[serde(default)]
+  pub day_rollover_hour: u32,
+  /// IANA timezone used to decide which day it is, e.g. `Europe/Paris`. Defaults to the system timezone.
+  #[serde(default)]
+  pub timezone: Option<String>,
+  /// Minutes without a key press after which the TUI locks an encrypted journal again. Never locks when unset.
+  #[serde(default)]
+  pub auto_lock_minutes: Option<u32>,
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct BackupConfig {
+  /// Whether the TUI snapshots the database into `backups/` in the data directory.
+  pub enabled: bool,
+  /// Minutes between snapshots while the TUI runs, besides the one taken when it starts. 0 only snapshots on start.
+  pub interval_minutes: u32,
+  /// Number of days, most recent first, whose last snapshot is kept.
+  pub keep_daily: usize,
+  /// Number of weeks, most recent first, whose last snapshot is kept.
+  pub keep_weekly: usize,
+}
+
+impl Default for BackupConfig {
+  fn default() -> Self {
+    Self { enabled: true, interval_minutes: 60, keep_daily: 7, keep_weekly: 4 }
+  }
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct TrashConfig {
+  /// Days deleted stacks and entries stay in the trash before they are purged. 0 keeps them until the trash is emptied.
+  pub purge_after_days: u32,
+}
+
+impl Default for TrashConfig {
+  fn default() -> Self {
+    Self { purge_after_days: 30 }
+  }
+}
+
+impl TrashConfig {
+  /// What was deleted before this is due to be purged, if anything is.
+  pub fn purge_before(&self, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+    match self.purge_after_days {
+      0 => None,
+      days => chrono::Duration::try_days(i64::from(days)).map(|age| now - age),
+    }
+  }
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct HistoryConfig {
+  /// Revisions kept for each entry, newest first. 0 keeps every revision.
+  pub revisions_per_entry: usize,
+}
+
+impl Default for HistoryConfig {
+  fn default() -> Self {
+    Self { revisions_per_entry: 50 }
+  }
+}
+
 #[derive(Clone, Debug, Default, Deserialize)]
 pub struct Config {
   #[serde(default, flatten)]
   pub config: AppConfig,
   #[serde(default)]
+  pub journal: JournalConfig,
+  #[serde(default)]
+  pub backup: BackupConfig,
+  #[serde(default)]
+  pub trash: TrashConfig,
+  #[serde(default)]
+  pub history: HistoryConfig,
+  #[serde(default)]
   pub keybindings: KeyBindings,
   #[serde(default)]
   pub styles: Styles,
@@ -71,6 +148,7 @@ impl Config {
     for (mode, default_styles) in default_config.styles.iter() {
       let user_styles = cfg.styles.entry(*mode).or_default();
       for (style_key, style) in default_styles.iter() {
+        #[allow(clippy::clone_on_copy)]
         user_styles.entry(style_key.clone()).or_insert_with(|| style.clone());
       }
     }
@@ -180,6 +258,7 @@ fn parse_key_code_with_modifiers(raw: &str, mut modifiers: KeyModifiers) -> Resu
   Ok(KeyEvent::new(c, modifiers))
 }
 
+#[allow(clippy::redundant_guards)]
 pub fn key_event_to_string(key_event: &KeyEvent) -> String {
   let char;
   let key_code = match key_event.code {
@@ -427,6 +506,7 @@ mod tests {
   }
 
   #[test]
+  #[allow(clippy::identity_op)]
   fn test_parse_color_rgb() {
     let color = parse_color("rgb123");
     let expected = 16 + 1 * 36 + 2 * 6 + 3;
@@ -0,0 +1,178 @@
+//! Passphrase-based encryption of entry content.
+//!
+//! Entries are encrypted with a random data key using XChaCha20-Poly1305. The data key
+//! itself is stored wrapped by a key derived from the passphrase with Argon2id, so
+//! changing the passphrase only rewraps the data key instead of re-encrypting every entry.
+
+use argon2::{Algorithm, Argon2, Params, Version};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use chacha20poly1305::{
+    aead::{rand_core::RngCore, Aead, AeadCore, KeyInit, OsRng, Payload},
+    XChaCha20Poly1305, XNonce,
+};
+use color_eyre::eyre::{eyre, Result};
+use serde::{Deserialize, Serialize};
+use zeroize::Zeroizing;
+
+/// Environment variable holding the passphrase, to unlock without a prompt.
+pub const PASSPHRASE_ENV: &str = "TERAJOT_PASSPHRASE";
+/// Environment variable holding the passphrase `terajot rekey` switches to.
+pub const NEW_PASSPHRASE_ENV: &str = "TERAJOT_NEW_PASSPHRASE";
+
+const KEY_LENGTH: usize = 32;
+const NONCE_LENGTH: usize = 24;
+const SALT_LENGTH: usize = 16;
+
+/// The key entries are encrypted with. It is wiped from memory when dropped.
+#[derive(Clone)]
+pub struct DataKey(Zeroizing<[u8; KEY_LENGTH]>);
+
+impl DataKey {
+    fn generate() -> Self {
+        let mut key = Zeroizing::new([0; KEY_LENGTH]);
+        OsRng.fill_bytes(key.as_mut());
+        Self(key)
+    }
+
+    fn cipher(&self) -> XChaCha20Poly1305 {
+        XChaCha20Poly1305::new(self.0.as_ref().into())
+    }
+
+    /// Encrypts text, bound to `context` so that it can't be passed off as the text of
+    /// something else, e.g. another entry.
+    pub fn seal(&self, text: &str, context: &[u8]) -> Result<String> {
+        let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let payload = Payload {
+            msg: text.as_bytes(),
+            aad: context,
+        };
+        let sealed = self.cipher().encrypt(&nonce, payload).map_err(|_| eyre!("Failed to encrypt"))?;
+        Ok(STANDARD.encode([nonce.as_slice(), &sealed].concat()))
+    }
+
+    /// Decrypts text sealed with the same `context`.
+    pub fn open(&self, sealed: &str, context: &[u8]) -> Result<String> {
+        let bytes = STANDARD.decode(sealed).map_err(|_| eyre!("Encrypted content is corrupted"))?;
+        if bytes.len() < NONCE_LENGTH {
+            return Err(eyre!("Encrypted content is corrupted"));
+        }
+        let (nonce, sealed) = bytes.split_at(NONCE_LENGTH);
+        let payload = Payload {
+            msg: sealed,
+            aad: context,
+        };
+        let text = self
+            .cipher()
+            .decrypt(XNonce::from_slice(nonce), payload)
+            .map_err(|_| eyre!("Encrypted content failed authentication"))?;
+        Ok(String::from_utf8(text)?)
+    }
+}
+
+/// The data key wrapped by a passphrase, along with what is needed to derive the
+/// wrapping key again.
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+pub struct Keyring {
+    salt: Vec<u8>,
+    /// Argon2id cost, kept so that it can be raised for new keyrings without locking
+    /// out existing ones.
+    memory_kib: u32,
+    iterations: u32,
+    parallelism: u32,
+    /// Nonce followed by the encrypted data key.
+    wrapped_key: Vec<u8>,
+}
+
+impl Keyring {
+    /// Creates a keyring for a fresh data key.
+    pub fn create(passphrase: &str) -> Result<(Self, DataKey)> {
+        let key = DataKey::generate();
+        Ok((Self::wrap(&key, passphrase)?, key))
+    }
+
+    /// Protects an existing data key with a passphrase.
+    pub fn wrap(key: &DataKey, passphrase: &str) -> Result<Self> {
+        if passphrase.is_empty() {
+            return Err(eyre!("The passphrase can't be empty"));
+        }
+        let mut salt = vec![0; SALT_LENGTH];
+        OsRng.fill_bytes(&mut salt);
+        let params = Params::default();
+        let mut keyring = Self {
+            salt,
+            memory_kib: params.m_cost(),
+            iterations: params.t_cost(),
+            parallelism: params.p_cost(),
+            wrapped_key: Vec::new(),
+        };
+
+        let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let wrapped = keyring
+            .wrapping_cipher(passphrase)?
+            .encrypt(&nonce, key.0.as_slice())
+            .map_err(|_| eyre!("Failed to wrap the data key"))?;
+        keyring.wrapped_key = [nonce.as_slice(), &wrapped].concat();
+        Ok(keyring)
+    }
+
+    /// Gets the data key back, failing if the passphrase is wrong.
+    pub fn unlock(&self, passphrase: &str) -> Result<DataKey> {
+        if self.wrapped_key.len() < NONCE_LENGTH {
+            return Err(eyre!("The keyring is corrupted"));
+        }
+        let (nonce, wrapped) = self.wrapped_key.split_at(NONCE_LENGTH);
+        let unwrapped = Zeroizing::new(
+            self.wrapping_cipher(passphrase)?
+                .decrypt(XNonce::from_slice(nonce), wrapped)
+                .map_err(|_| eyre!("Wrong passphrase"))?,
+        );
+        let mut key = Zeroizing::new([0; KEY_LENGTH]);
+        if unwrapped.len() != KEY_LENGTH {
+            return Err(eyre!("The keyring is corrupted"));
+        }
+        key.copy_from_slice(&unwrapped);
+        Ok(DataKey(key))
+    }
+
+    fn wrapping_cipher(&self, passphrase: &str) -> Result<XChaCha20Poly1305> {
+        let params = Params::new(self.memory_kib, self.iterations, self.parallelism, Some(KEY_LENGTH))
+            .map_err(|e| eyre!("Invalid key derivation settings: {e}"))?;
+        let mut key = Zeroizing::new([0; KEY_LENGTH]);
+        Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+            .hash_password_into(passphrase.as_bytes(), &self.salt, key.as_mut())
+            .map_err(|e| eyre!("Failed to derive a key from the passphrase: {e}"))?;
+        Ok(XChaCha20Poly1305::new(key.as_ref().into()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn test_seal_and_open() -> Result<()> {
+        let (keyring, key) = Keyring::create("correct horse")?;
+        let sealed = key.seal("dear diary", b"1")?;
+        assert!(!sealed.contains("diary"));
+        assert_eq!(key.open(&sealed, b"1")?, "dear diary");
+        assert!(key.open(&sealed, b"2").is_err());
+
+        let unlocked = keyring.unlock("correct horse")?;
+        assert_eq!(unlocked.open(&sealed, b"1")?, "dear diary");
+        assert_eq!(keyring.unlock("wrong").err().map(|e| e.to_string()), Some("Wrong passphrase".to_string()));
+        Ok(())
+    }
+
+    #[test]
+    fn test_rewrap_keeps_data_key() -> Result<()> {
+        let (keyring, key) = Keyring::create("old")?;
+        let sealed = key.seal("secret", b"")?;
+        let rewrapped = Keyring::wrap(&keyring.unlock("old")?, "new")?;
+        assert!(rewrapped.unlock("old").is_err());
+        assert_eq!(rewrapped.unlock("new")?.open(&sealed, b"")?, "secret");
+        assert!(Keyring::wrap(&key, "").is_err());
+        Ok(())
+    }
+}
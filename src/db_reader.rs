@@ -1,29 +1,291 @@
-use color_eyre::eyre::Result;
+use std::path::Path;
 
-/// Defines How to read a given model from the database.
-pub trait DbReader<T>{
+use chrono::{DateTime, NaiveDate, Utc};
+use color_eyre::eyre::{eyre, Result};
 
-    /// Saves the model to the database.
-    fn save(model:&T) -> Result<()>{
-        Ok(())
+use crate::{
+    models::{entry::Entry, revision::Revision, stack::Stack, trashed::Trashed},
+    search::Posting,
+};
+
+pub mod memory;
+pub mod native;
+
+pub use memory::MemoryDbReader;
+pub use native::NativeDbReader;
+
+/// Defines how the models are read from and written to a storage backend.
+///
+/// Components receive an implementation through `Component::register_db_handler`,
+/// so the backend can be swapped (e.g. for the in-memory one in tests).
+pub trait DbReader: Send + Sync {
+    /// Gets all stacks.
+    fn get_stacks(&self) -> Result<Vec<Stack>>;
+
+    /// Gets a single stack.
+    fn get_stack(&self, id: u32) -> Result<Option<Stack>>;
+
+    /// Gets the stack collecting the entries of a day, if it was created.
+    fn get_day_stack(&self, day: NaiveDate) -> Result<Option<Stack>>;
+
+    /// Gets the stack of a day, creating it on demand.
+    fn get_or_create_day_stack(&self, day: NaiveDate) -> Result<Stack>;
+
+    /// Saves a new stack under a freshly allocated id and returns it.
+    fn save_stack(&self, stack: &Stack) -> Result<Stack>;
+
+    /// Replaces an existing stack.
+    fn update_stack(&self, stack: &Stack) -> Result<()>;
+
+    /// Deletes a stack along with all of its entries, for good. See `trash_stack`.
+    fn delete_stack(&self, stack: &Stack) -> Result<()>;
+
+    /// Gets every entry, regardless of its stack.
+    fn get_all_entries(&self) -> Result<Vec<Entry>>;
+
+    /// Gets all entries belonging to a stack.
+    fn get_entries(&self, stack_id: u32) -> Result<Vec<Entry>>;
+
+    /// Gets the entries created in `[start, end)`, oldest first.
+    fn get_entries_created_between(&self, start: DateTime<Utc>, end: DateTime<Utc>) -> Result<Vec<Entry>>;
+
+    /// Gets a single entry.
+    fn get_entry(&self, id: u32) -> Result<Option<Entry>>;
+
+    /// Gets the entries with the given ids in that order, skipping those that don't exist.
+    fn get_entries_by_ids(&self, ids: &[u32]) -> Result<Vec<Entry>>;
+
+    /// Saves a new entry under a freshly allocated id and returns it.
+    fn save_entry(&self, entry: &Entry) -> Result<Entry>;
+
+    /// Saves new entries under freshly allocated ids in a single write and returns them.
+    fn save_entries(&self, entries: &[Entry]) -> Result<Vec<Entry>>;
+
+    /// Saves new entries, each into the stack it is paired with, in a single write. Day
+    /// stacks are looked up by their day and others by their name, and those that don't
+    /// exist yet are created along with the entries. Smart stacks are refused.
+    fn save_entries_into(&self, entries: &[(Stack, Entry)]) -> Result<Vec<Entry>>;
+
+    /// Replaces an existing entry. When its content changes, the previous content is kept
+    /// as a revision.
+    fn update_entry(&self, entry: &Entry) -> Result<()>;
+
+    /// Deletes a single entry and its revisions, for good. See `trash_entry`.
+    fn delete_entry(&self, entry: &Entry) -> Result<()>;
+
+    /// Counts all entries.
+    fn count_all_entries(&self) -> Result<u64>;
+
+    /// Gets the full-text index postings of a term, or of every term starting with it.
+    ///
+    /// The index is kept up to date by every write to the entries.
+    fn get_postings(&self, term: &str, prefix: bool) -> Result<Vec<Posting>>;
+
+    /// Gets the postings of several terms at once, in the order given, each paired with
+    /// whether it is a prefix. See `get_postings`.
+    fn get_postings_of(&self, terms: &[(String, bool)]) -> Result<Vec<Vec<Posting>>> {
+        terms.iter().map(|(term, prefix)| self.get_postings(term, *prefix)).collect()
+    }
+
+    /// Writes stacks, entries, revisions and trashed items under their own ids, replacing
+    /// those with the same id, all at once. Ids handed out afterwards come after the
+    /// written ones, including those of the stacks and entries in the trash.
+    fn restore(&self, stacks: &[Stack], entries: &[Entry], revisions: &[Revision], trash: &[Trashed]) -> Result<()>;
+
+    /// Gets the previous contents of an entry, oldest first. They are kept while the entry
+    /// is in the trash.
+    fn get_revisions(&self, entry_id: u32) -> Result<Vec<Revision>>;
+
+    /// Gets the previous contents of every entry.
+    fn get_all_revisions(&self) -> Result<Vec<Revision>>;
+
+    /// Drops all but the `keep` newest revisions of every entry, returning how many were
+    /// dropped.
+    fn prune_revisions(&self, keep: usize) -> Result<usize>;
+
+    /// Keeps deleted items in the trash under a freshly allocated id and returns them.
+    fn save_trashed(&self, trashed: &Trashed) -> Result<Trashed>;
+
+    /// Gets everything in the trash.
+    fn get_trash(&self) -> Result<Vec<Trashed>>;
+
+    /// Removes items from the trash for good, along with the revisions of their entries, in
+    /// a single write. Ids that aren't in the trash are skipped.
+    fn remove_trashed_items(&self, ids: &[u32]) -> Result<()>;
+
+    /// Removes an item from the trash for good. See `remove_trashed_items`.
+    fn remove_trashed(&self, id: u32) -> Result<()> {
+        self.remove_trashed_items(&[id])
+    }
+
+    /// Writes the entries of a bulk operation in a single write: `updated` replace the
+    /// stored entries like `update_entry` does, and `trashed` go to the trash. Nothing is
+    /// written when one of them fails, e.g. because it was deleted meanwhile.
+    fn write_batch(&self, updated: &[Entry], trashed: &[Entry]) -> Result<Vec<Trashed>>;
+
+    /// Moves a stack to the trash along with all of its entries, in a single write.
+    fn trash_stack(&self, stack: &Stack) -> Result<Trashed>;
+
+    /// Puts trashed items back where they were, under their own ids, and takes them out
+    /// of the trash in a single write. A stack recreated since, e.g. the stack of the same day,
+    /// gets the entries back instead. See `Trashed::home`.
+    fn restore_trashed(&self, id: u32) -> Result<Trashed>;
+
+    /// Copies the database to `to` while no other process writes to it.
+    fn snapshot(&self, to: &Path) -> Result<()>;
+
+    /// Replaces the database with a copy made by `snapshot`.
+    fn replace_with(&self, snapshot: &Path) -> Result<()>;
+
+    /// Whether entry content is encrypted at rest.
+    fn is_encrypted(&self) -> Result<bool>;
+
+    /// Whether entries can't be read or written until the journal is unlocked.
+    fn is_locked(&self) -> Result<bool>;
+
+    /// Unlocks an encrypted journal, failing if the passphrase is wrong.
+    fn unlock(&self, passphrase: &str) -> Result<()>;
+
+    /// Forgets the key of an encrypted journal until it is unlocked again.
+    fn lock(&self);
+
+    /// Encrypts the journal under a new passphrase. An encrypted journal has to be
+    /// unlocked first.
+    fn set_passphrase(&self, passphrase: &str) -> Result<()>;
+
+    /// Gets a stack by its name.
+    fn get_stack_by_name(&self, name: &str) -> Result<Option<Stack>> {
+        Ok(self.get_stacks()?.into_iter().find(|s| s.name == name))
+    }
+
+    /// Gets a stack by its name, falling back to reading `name_or_id` as its id.
+    fn find_stack(&self, name_or_id: &str) -> Result<Option<Stack>> {
+        if let Some(stack) = self.get_stack_by_name(name_or_id)? {
+            return Ok(Some(stack));
+        }
+        match name_or_id.parse() {
+            Ok(id) => self.get_stack(id),
+            Err(_) => Ok(None),
+        }
     }
-    
-    /// Updates the model in the database.
-    fn update(&mut self) -> Result<()>{
-        Ok(())
+
+    /// Gets a stack by its name, creating a named stack on demand. Smart stacks are
+    /// refused since entries can't be added to them.
+    fn get_or_create_named_stack(&self, name: &str) -> Result<Stack> {
+        match self.get_stack_by_name(name)? {
+            Some(stack) if stack.is_smart() => Err(eyre!("{name} is a smart stack, entries can't be added to it")),
+            Some(stack) => Ok(stack),
+            None => self.save_stack(&Stack::named(name.to_string())),
+        }
+    }
+
+    /// Moves an entry to another stack. It is a single write, so the counts of both stacks
+    /// change together.
+    fn move_entry(&self, entry: &Entry, stack: &Stack) -> Result<Entry> {
+        let mut moved = self.move_entries(std::slice::from_ref(entry), stack)?;
+        Ok(moved.pop().expect("one entry was moved"))
+    }
+
+    /// Moves entries to another stack, all of them or none.
+    fn move_entries(&self, entries: &[Entry], stack: &Stack) -> Result<Vec<Entry>> {
+        if stack.is_smart() {
+            return Err(eyre!("{} is a smart stack, entries can't be moved to it", stack.name));
+        }
+        let ids = entries.iter().map(|e| e.id).collect::<Vec<_>>();
+        let mut moved = self.get_entries_by_ids(&ids)?;
+        if let Some(missing) = ids.iter().find(|id| moved.iter().all(|e| e.id != **id)) {
+            return Err(eyre!("Entry {missing} does not exist"));
+        }
+        let mut changed = Vec::new();
+        for entry in moved.iter_mut().filter(|e| e.stack_id != stack.id) {
+            entry.stack_id = stack.id;
+            changed.push(entry.clone());
+        }
+        self.write_batch(&changed, &[])?;
+        Ok(moved)
+    }
+
+    /// Copies an entry to another stack. The copy keeps the dates of the original.
+    fn copy_entry(&self, entry: &Entry, stack: &Stack) -> Result<Entry> {
+        let mut copied = self.copy_entries(std::slice::from_ref(entry), stack)?;
+        Ok(copied.remove(0))
     }
 
-    /// Gets all entries from the database.
-    fn get_all() -> Vec<T>;
+    /// Copies entries to another stack in a single write, so either all of them are copied
+    /// or none. See `copy_entry`.
+    fn copy_entries(&self, entries: &[Entry], stack: &Stack) -> Result<Vec<Entry>> {
+        if stack.is_smart() {
+            return Err(eyre!("{} is a smart stack, entries can't be copied to it", stack.name));
+        }
+        if let Some(entry) = entries.iter().find(|e| e.stack_id == stack.id) {
+            return Err(eyre!("Entry {} is already in {}", entry.id, stack.name));
+        }
+        let copies = entries
+            .iter()
+            .map(|e| Entry {
+                stack_id: stack.id,
+                ..e.clone()
+            })
+            .collect::<Vec<_>>();
+        self.save_entries(&copies)
+    }
 
-    /// Gets a single entry from the database.
-    fn get_by_id(id:u32)->Option<Result<T>>{
-        None
+    /// Merges entries into the earliest of them, joining their contents in the order they
+    /// were created. The others go to the trash, and the content the earliest had is kept
+    /// as a revision. It is a single write, so no content is lost nor kept twice.
+    fn merge_entries(&self, entries: &[Entry]) -> Result<Entry> {
+        let mut entries = entries.to_vec();
+        entries.sort_by_key(|e| e.created_key());
+        let Some((first, rest)) = entries.split_first() else {
+            return Err(eyre!("There are no entries to merge"));
+        };
+        let mut merged = first.clone();
+        merged.set_content(entries.iter().map(|e| e.content.trim_end()).collect::<Vec<_>>().join("\n\n"));
+        self.write_batch(std::slice::from_ref(&merged), rest)?;
+        Ok(merged)
     }
 
-    /// Deletes a single entry from the database.
-    fn delete(&self)->Result<()>{
-        Ok(())
+    /// Puts the content of a revision back into its entry. The content it replaces becomes
+    /// a revision too, so this can be undone.
+    fn restore_revision(&self, revision: &Revision) -> Result<Entry> {
+        let mut entry = self
+            .get_entry(revision.entry_id)?
+            .ok_or_else(|| eyre!("Entry {} does not exist", revision.entry_id))?;
+        if entry.content != revision.content {
+            entry.set_content(revision.content.clone());
+            self.update_entry(&entry)?;
+        }
+        Ok(entry)
     }
 
-}
\ No newline at end of file
+    /// Moves an entry to the trash. It is a single write, so the entry is never lost nor
+    /// kept twice.
+    fn trash_entry(&self, entry: &Entry) -> Result<Trashed> {
+        let mut trashed = self.trash_entries(std::slice::from_ref(entry))?;
+        Ok(trashed.pop().expect("one entry was trashed"))
+    }
+
+    /// Moves entries to the trash, all of them or none.
+    fn trash_entries(&self, entries: &[Entry]) -> Result<Vec<Trashed>> {
+        self.write_batch(&[], entries)
+    }
+
+    /// Removes what was deleted before `before` from the trash, returning how many items
+    /// were purged.
+    fn purge_trash(&self, before: DateTime<Utc>) -> Result<usize> {
+        let expired = self
+            .get_trash()?
+            .into_iter()
+            .filter(|t| t.deleted_at < before)
+            .map(|t| t.id)
+            .collect::<Vec<_>>();
+        self.remove_trashed_items(&expired)?;
+        Ok(expired.len())
+    }
+
+    /// Captures a new entry into the stack of the given day.
+    fn capture(&self, content: String, day: NaiveDate) -> Result<Entry> {
+        let stack = self.get_or_create_day_stack(day)?;
+        self.save_entry(&Entry::new(stack.id, content))
+    }
+}
@@ -0,0 +1,621 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    path::Path,
+    sync::{
+        atomic::{AtomicU32, Ordering},
+        Mutex, MutexGuard,
+    },
+};
+
+use chrono::{DateTime, NaiveDate, Utc};
+use color_eyre::eyre::{eyre, Result};
+
+use super::DbReader;
+use crate::{
+    crypto::{DataKey, Keyring},
+    models::{entry::Entry, revision::Revision, stack::Stack, trashed::Trashed},
+    search::{self, Posting},
+};
+
+/// Full-text index, from term and entry id to the positions of the term.
+type Postings = BTreeMap<(String, u32), Vec<u32>>;
+
+/// A [`DbReader`] that only lives in memory. Used by tests to get deterministic data.
+///
+/// Nothing is written anywhere, so a passphrase only guards access to the entries, the
+/// same way it does for the native database.
+#[derive(Default)]
+pub struct MemoryDbReader {
+    stacks: Mutex<BTreeMap<u32, Stack>>,
+    entries: Mutex<BTreeMap<u32, Entry>>,
+    postings: Mutex<Postings>,
+    revisions: Mutex<BTreeMap<u32, Revision>>,
+    trash: Mutex<BTreeMap<u32, Trashed>>,
+    keyring: Mutex<Option<Keyring>>,
+    key: Mutex<Option<DataKey>>,
+    last_stack_id: AtomicU32,
+    last_entry_id: AtomicU32,
+    last_trashed_id: AtomicU32,
+    last_revision_id: AtomicU32,
+}
+
+impl MemoryDbReader {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn stacks(&self) -> Result<MutexGuard<'_, BTreeMap<u32, Stack>>> {
+        self.stacks.lock().map_err(|e| eyre!("Poisoned stack store: {e}"))
+    }
+
+    /// Gets the entries, as long as the journal isn't locked.
+    fn entries(&self) -> Result<MutexGuard<'_, BTreeMap<u32, Entry>>> {
+        if self.is_locked()? {
+            return Err(eyre!("The journal is locked"));
+        }
+        self.entries.lock().map_err(|e| eyre!("Poisoned entry store: {e}"))
+    }
+
+    /// Gets the revisions, as long as the journal isn't locked.
+    fn revisions(&self) -> Result<MutexGuard<'_, BTreeMap<u32, Revision>>> {
+        if self.is_locked()? {
+            return Err(eyre!("The journal is locked"));
+        }
+        self.revisions.lock().map_err(|e| eyre!("Poisoned revision store: {e}"))
+    }
+
+    /// Gets the trash, which holds entries too, as long as the journal isn't locked.
+    fn trash(&self) -> Result<MutexGuard<'_, BTreeMap<u32, Trashed>>> {
+        if self.is_locked()? {
+            return Err(eyre!("The journal is locked"));
+        }
+        self.trash.lock().map_err(|e| eyre!("Poisoned trash: {e}"))
+    }
+
+    fn keyring(&self) -> Result<MutexGuard<'_, Option<Keyring>>> {
+        self.keyring.lock().map_err(|e| eyre!("Poisoned keyring: {e}"))
+    }
+
+    fn key(&self) -> Result<MutexGuard<'_, Option<DataKey>>> {
+        self.key.lock().map_err(|e| eyre!("Poisoned key: {e}"))
+    }
+
+    fn postings(&self) -> Result<MutexGuard<'_, Postings>> {
+        self.postings.lock().map_err(|e| eyre!("Poisoned search index: {e}"))
+    }
+
+    fn index(&self, entry: &Entry) -> Result<()> {
+        let mut postings = self.postings()?;
+        for posting in search::postings(entry) {
+            postings.insert((posting.term, posting.entry_id), posting.positions);
+        }
+        Ok(())
+    }
+
+    fn unindex(&self, entry_id: u32) -> Result<()> {
+        self.postings()?.retain(|(_, id), _| *id != entry_id);
+        Ok(())
+    }
+
+    fn with_count(&self, mut stack: Stack) -> Result<Stack> {
+        let entries = self.entries.lock().map_err(|e| eyre!("Poisoned entry store: {e}"))?;
+        stack.count = entries.values().filter(|e| e.stack_id == stack.id).count() as u64;
+        Ok(stack)
+    }
+}
+
+impl DbReader for MemoryDbReader {
+    fn get_stacks(&self) -> Result<Vec<Stack>> {
+        let stacks: Vec<Stack> = self.stacks()?.values().cloned().collect();
+        stacks.into_iter().map(|s| self.with_count(s)).collect()
+    }
+
+    fn get_stack(&self, id: u32) -> Result<Option<Stack>> {
+        let stack = self.stacks()?.get(&id).cloned();
+        stack.map(|s| self.with_count(s)).transpose()
+    }
+
+    fn get_day_stack(&self, day: NaiveDate) -> Result<Option<Stack>> {
+        let stack = self.stacks()?.values().find(|s| s.day == Some(day)).cloned();
+        stack.map(|s| self.with_count(s)).transpose()
+    }
+
+    fn get_or_create_day_stack(&self, day: NaiveDate) -> Result<Stack> {
+        let mut stacks = self.stacks()?;
+        let stack = match stacks.values().find(|s| s.day == Some(day)) {
+            Some(stack) => stack.clone(),
+            None => {
+                let mut stack = Stack::for_day(day);
+                stack.id = self.last_stack_id.fetch_add(1, Ordering::SeqCst) + 1;
+                stacks.insert(stack.id, stack.clone());
+                stack
+            }
+        };
+        drop(stacks);
+        self.with_count(stack)
+    }
+
+    fn save_stack(&self, stack: &Stack) -> Result<Stack> {
+        let mut stack = stack.clone();
+        stack.id = self.last_stack_id.fetch_add(1, Ordering::SeqCst) + 1;
+        self.stacks()?.insert(stack.id, stack.clone());
+        Ok(stack)
+    }
+
+    fn update_stack(&self, stack: &Stack) -> Result<()> {
+        let mut stacks = self.stacks()?;
+        let old = stacks.get_mut(&stack.id).ok_or_else(|| eyre!("Stack {} does not exist", stack.id))?;
+        *old = stack.clone();
+        Ok(())
+    }
+
+    fn delete_stack(&self, stack: &Stack) -> Result<()> {
+        let ids: Vec<u32> = self.entries()?.values().filter(|e| e.stack_id == stack.id).map(|e| e.id).collect();
+        for id in ids {
+            self.entries()?.remove(&id);
+            self.revisions()?.retain(|_, r| r.entry_id != id);
+            self.unindex(id)?;
+        }
+        self.stacks()?.remove(&stack.id);
+        Ok(())
+    }
+
+    fn get_all_entries(&self) -> Result<Vec<Entry>> {
+        Ok(self.entries()?.values().cloned().collect())
+    }
+
+    fn get_entries(&self, stack_id: u32) -> Result<Vec<Entry>> {
+        Ok(self.entries()?.values().filter(|e| e.stack_id == stack_id).cloned().collect())
+    }
+
+    fn get_entries_created_between(&self, start: DateTime<Utc>, end: DateTime<Utc>) -> Result<Vec<Entry>> {
+        let mut entries: Vec<Entry> = self
+            .entries()?
+            .values()
+            .filter(|e| e.created_at >= start && e.created_at < end)
+            .cloned()
+            .collect();
+        entries.sort_by_key(|e| e.created_key());
+        Ok(entries)
+    }
+
+    fn get_entry(&self, id: u32) -> Result<Option<Entry>> {
+        Ok(self.entries()?.get(&id).cloned())
+    }
+
+    fn get_entries_by_ids(&self, ids: &[u32]) -> Result<Vec<Entry>> {
+        let entries = self.entries()?;
+        Ok(ids.iter().filter_map(|id| entries.get(id).cloned()).collect())
+    }
+
+    fn save_entry(&self, entry: &Entry) -> Result<Entry> {
+        let mut entry = entry.clone();
+        entry.id = self.last_entry_id.fetch_add(1, Ordering::SeqCst) + 1;
+        self.entries()?.insert(entry.id, entry.clone());
+        self.index(&entry)?;
+        Ok(entry)
+    }
+
+    fn save_entries_into(&self, entries: &[(Stack, Entry)]) -> Result<Vec<Entry>> {
+        let mut stacks = self.stacks()?;
+        // Everything is checked before anything is written.
+        let smart = |stack: &Stack| stacks.values().find(|s| stack.is_same_as(s) && s.is_smart());
+        if let Some(smart) = entries.iter().find_map(|(stack, _)| smart(stack)) {
+            return Err(eyre!("{} is a smart stack, entries can't be added to it", smart.name));
+        }
+        let mut stored = self.entries()?;
+        let mut saved = Vec::with_capacity(entries.len());
+        for (stack, entry) in entries {
+            let stack_id = match stacks.values().find(|s| stack.is_same_as(s)) {
+                Some(s) => s.id,
+                None => {
+                    let id = self.last_stack_id.fetch_add(1, Ordering::SeqCst) + 1;
+                    stacks.insert(id, Stack { id, ..stack.clone() });
+                    id
+                }
+            };
+            let entry = Entry {
+                id: self.last_entry_id.fetch_add(1, Ordering::SeqCst) + 1,
+                stack_id,
+                ..entry.clone()
+            };
+            stored.insert(entry.id, entry.clone());
+            self.index(&entry)?;
+            saved.push(entry);
+        }
+        Ok(saved)
+    }
+
+    fn save_entries(&self, entries: &[Entry]) -> Result<Vec<Entry>> {
+        let mut stored = self.entries()?;
+        let mut saved = Vec::with_capacity(entries.len());
+        for entry in entries {
+            let mut entry = entry.clone();
+            entry.id = self.last_entry_id.fetch_add(1, Ordering::SeqCst) + 1;
+            stored.insert(entry.id, entry.clone());
+            self.index(&entry)?;
+            saved.push(entry);
+        }
+        Ok(saved)
+    }
+
+    fn update_entry(&self, entry: &Entry) -> Result<()> {
+        let mut entries = self.entries()?;
+        let old = entries.get_mut(&entry.id).ok_or_else(|| eyre!("Entry {} does not exist", entry.id))?;
+        if old.content != entry.content {
+            let revision = Revision {
+                id: self.last_revision_id.fetch_add(1, Ordering::SeqCst) + 1,
+                ..Revision::of(old)
+            };
+            self.revisions()?.insert(revision.id, revision);
+        }
+        *old = entry.clone();
+        drop(entries);
+        self.unindex(entry.id)?;
+        self.index(entry)
+    }
+
+    fn delete_entry(&self, entry: &Entry) -> Result<()> {
+        self.entries()?.remove(&entry.id);
+        self.revisions()?.retain(|_, r| r.entry_id != entry.id);
+        self.unindex(entry.id)
+    }
+
+    fn count_all_entries(&self) -> Result<u64> {
+        Ok(self.entries()?.len() as u64)
+    }
+
+    fn get_postings(&self, term: &str, prefix: bool) -> Result<Vec<Posting>> {
+        if self.is_locked()? {
+            return Err(eyre!("The journal is locked"));
+        }
+        let postings = self.postings()?;
+        let postings = postings
+            .range((term.to_string(), 0)..)
+            .take_while(|((t, _), _)| if prefix { t.starts_with(term) } else { t == term })
+            .map(|((term, entry_id), positions)| Posting {
+                term: term.clone(),
+                entry_id: *entry_id,
+                positions: positions.clone(),
+            })
+            .collect();
+        Ok(postings)
+    }
+
+    fn restore(&self, stacks: &[Stack], entries: &[Entry], revisions: &[Revision], trash: &[Trashed]) -> Result<()> {
+        let mut stored = self.stacks()?;
+        for stack in stacks {
+            stored.insert(stack.id, stack.clone());
+            self.last_stack_id.fetch_max(stack.id, Ordering::SeqCst);
+        }
+        drop(stored);
+        for entry in entries {
+            self.entries()?.insert(entry.id, entry.clone());
+            self.unindex(entry.id)?;
+            self.index(entry)?;
+            self.last_entry_id.fetch_max(entry.id, Ordering::SeqCst);
+        }
+        for revision in revisions {
+            self.revisions()?.insert(revision.id, revision.clone());
+            self.last_revision_id.fetch_max(revision.id, Ordering::SeqCst);
+        }
+        for trashed in trash {
+            self.trash()?.insert(trashed.id, trashed.clone());
+            self.last_trashed_id.fetch_max(trashed.id, Ordering::SeqCst);
+            self.last_stack_id.fetch_max(trashed.stack.id, Ordering::SeqCst);
+            for entry in &trashed.entries {
+                self.last_entry_id.fetch_max(entry.id, Ordering::SeqCst);
+            }
+        }
+        Ok(())
+    }
+
+    fn get_revisions(&self, entry_id: u32) -> Result<Vec<Revision>> {
+        Ok(self.revisions()?.values().filter(|r| r.entry_id == entry_id).cloned().collect())
+    }
+
+    fn get_all_revisions(&self) -> Result<Vec<Revision>> {
+        Ok(self.revisions()?.values().cloned().collect())
+    }
+
+    fn prune_revisions(&self, keep: usize) -> Result<usize> {
+        let mut revisions = self.revisions()?;
+        let mut kept = HashMap::new();
+        let before = revisions.len();
+        // Newest first, so the first `keep` of every entry stay.
+        let ids = revisions.values().rev().map(|r| (r.id, r.entry_id)).collect::<Vec<_>>();
+        for (id, entry_id) in ids {
+            let count = kept.entry(entry_id).or_insert(0);
+            *count += 1;
+            if *count > keep {
+                revisions.remove(&id);
+            }
+        }
+        Ok(before - revisions.len())
+    }
+
+    fn save_trashed(&self, trashed: &Trashed) -> Result<Trashed> {
+        let mut trashed = trashed.clone();
+        trashed.id = self.last_trashed_id.fetch_add(1, Ordering::SeqCst) + 1;
+        self.trash()?.insert(trashed.id, trashed.clone());
+        Ok(trashed)
+    }
+
+    fn get_trash(&self) -> Result<Vec<Trashed>> {
+        Ok(self.trash()?.values().cloned().collect())
+    }
+
+    fn remove_trashed_items(&self, ids: &[u32]) -> Result<()> {
+        let mut revisions = self.revisions()?;
+        let mut trash = self.trash()?;
+        for trashed in ids.iter().filter_map(|id| trash.remove(id)) {
+            revisions.retain(|_, r| trashed.entries.iter().all(|e| e.id != r.entry_id));
+        }
+        Ok(())
+    }
+
+    fn write_batch(&self, updated: &[Entry], trashed: &[Entry]) -> Result<Vec<Trashed>> {
+        let stacks = self.stacks()?;
+        let mut entries = self.entries()?;
+        // Everything is checked before anything is written.
+        if let Some(missing) = updated.iter().chain(trashed).find(|e| !entries.contains_key(&e.id)) {
+            return Err(eyre!("Entry {} does not exist", missing.id));
+        }
+        let homes = trashed
+            .iter()
+            .map(|e| stacks.get(&e.stack_id).cloned().ok_or_else(|| eyre!("Stack {} does not exist", e.stack_id)))
+            .collect::<Result<Vec<_>>>()?;
+
+        let mut revisions = self.revisions()?;
+        for entry in updated {
+            let old = entries.get_mut(&entry.id).expect("checked above");
+            if old.content != entry.content {
+                let revision = Revision {
+                    id: self.last_revision_id.fetch_add(1, Ordering::SeqCst) + 1,
+                    ..Revision::of(old)
+                };
+                revisions.insert(revision.id, revision);
+            }
+            *old = entry.clone();
+        }
+        let mut trash = self.trash()?;
+        let mut done = Vec::with_capacity(trashed.len());
+        for (entry, mut stack) in trashed.iter().zip(homes) {
+            stack.count = entries.values().filter(|e| e.stack_id == stack.id).count() as u64;
+            let item = Trashed {
+                id: self.last_trashed_id.fetch_add(1, Ordering::SeqCst) + 1,
+                ..Trashed::entry(stack, entry.clone())
+            };
+            trash.insert(item.id, item.clone());
+            // The revisions stay until the entry is purged from the trash.
+            entries.remove(&entry.id);
+            done.push(item);
+        }
+        drop((stacks, entries, revisions, trash));
+
+        for entry in updated {
+            self.unindex(entry.id)?;
+            self.index(entry)?;
+        }
+        for entry in trashed {
+            self.unindex(entry.id)?;
+        }
+        Ok(done)
+    }
+
+    fn trash_stack(&self, stack: &Stack) -> Result<Trashed> {
+        let mut stacks = self.stacks()?;
+        let mut entries = self.entries()?;
+        let trashed = Trashed {
+            id: self.last_trashed_id.fetch_add(1, Ordering::SeqCst) + 1,
+            ..Trashed::stack(stack.clone(), entries.values().filter(|e| e.stack_id == stack.id).cloned().collect())
+        };
+        self.trash()?.insert(trashed.id, trashed.clone());
+        entries.retain(|_, e| e.stack_id != stack.id);
+        self.postings()?.retain(|(_, id), _| trashed.entries.iter().all(|e| e.id != *id));
+        stacks.remove(&stack.id);
+        Ok(trashed)
+    }
+
+    fn restore_trashed(&self, id: u32) -> Result<Trashed> {
+        let mut stacks = self.stacks()?;
+        let mut entries = self.entries()?;
+        let mut trash = self.trash()?;
+        let trashed = trash.remove(&id).ok_or_else(|| eyre!("There is nothing with id {id} in the trash"))?;
+        let stack_id = match trashed.home(&stacks.values().cloned().collect::<Vec<_>>()) {
+            Some(stack) => stack.id,
+            None => {
+                stacks.insert(trashed.stack.id, trashed.stack.clone());
+                self.last_stack_id.fetch_max(trashed.stack.id, Ordering::SeqCst);
+                trashed.stack.id
+            }
+        };
+        for entry in &trashed.entries {
+            let entry = Entry { stack_id, ..entry.clone() };
+            self.unindex(entry.id)?;
+            self.index(&entry)?;
+            self.last_entry_id.fetch_max(entry.id, Ordering::SeqCst);
+            entries.insert(entry.id, entry);
+        }
+        Ok(trashed)
+    }
+
+    fn snapshot(&self, _to: &Path) -> Result<()> {
+        Err(eyre!("An in-memory journal can't be backed up"))
+    }
+
+    fn replace_with(&self, _snapshot: &Path) -> Result<()> {
+        Err(eyre!("An in-memory journal can't be restored from a backup"))
+    }
+
+    fn is_encrypted(&self) -> Result<bool> {
+        Ok(self.keyring()?.is_some())
+    }
+
+    fn is_locked(&self) -> Result<bool> {
+        Ok(self.is_encrypted()? && self.key()?.is_none())
+    }
+
+    fn unlock(&self, passphrase: &str) -> Result<()> {
+        let keyring = self.keyring()?.clone().ok_or_else(|| eyre!("The journal is not encrypted"))?;
+        *self.key()? = Some(keyring.unlock(passphrase)?);
+        Ok(())
+    }
+
+    fn lock(&self) {
+        if let Ok(mut key) = self.key() {
+            *key = None;
+        }
+    }
+
+    fn set_passphrase(&self, passphrase: &str) -> Result<()> {
+        if self.is_locked()? {
+            return Err(eyre!("The journal is locked"));
+        }
+        let key = self.key()?.clone();
+        let (keyring, key) = match key {
+            Some(key) => (Keyring::wrap(&key, passphrase)?, key),
+            None => Keyring::create(passphrase)?,
+        };
+        *self.keyring()? = Some(keyring);
+        *self.key()? = Some(key);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn test_delete_stack_removes_its_entries() -> Result<()> {
+        let db = MemoryDbReader::new();
+        let stack = db.save_stack(&Stack::named("Stack".to_string()))?;
+        db.save_entry(&Entry::new(stack.id, "in the stack".to_string()))?;
+        db.save_entry(&Entry::new(stack.id + 1, "somewhere else".to_string()))?;
+
+        db.delete_stack(&stack)?;
+
+        assert_eq!(db.get_stacks()?, vec![]);
+        assert_eq!(db.get_all_entries()?.len(), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_capture_files_entries_into_day_stacks() -> Result<()> {
+        let db = MemoryDbReader::new();
+        let monday = NaiveDate::from_ymd_opt(2026, 10, 12).unwrap();
+        let tuesday = monday.succ_opt().unwrap();
+
+        let first = db.capture("first".to_string(), monday)?;
+        let second = db.capture("second".to_string(), monday)?;
+        let third = db.capture("third".to_string(), tuesday)?;
+
+        assert_eq!(first.stack_id, second.stack_id);
+        assert_ne!(first.stack_id, third.stack_id);
+        assert_eq!(db.get_stacks()?.len(), 2);
+        assert_eq!(db.get_day_stack(monday)?.map(|s| s.count), Some(2));
+        assert_eq!(db.get_day_stack(monday)?.map(|s| s.name), Some("2026-10-12".to_string()));
+        Ok(())
+    }
+
+    #[test]
+    fn test_trash_and_restore() -> Result<()> {
+        let db = MemoryDbReader::new();
+        let stack = db.save_stack(&Stack::named("ideas".to_string()))?;
+        let entry = db.save_entry(&Entry::new(stack.id, "kept thought".to_string()))?;
+        let trashed = db.trash_stack(&db.get_stack(stack.id)?.unwrap())?;
+        assert_eq!((db.get_stacks()?.len(), db.count_all_entries()?), (0, 0));
+        assert_eq!(db.get_postings("kept", false)?, vec![]);
+
+        db.restore_trashed(trashed.id)?;
+        assert_eq!(db.get_trash()?, vec![]);
+        assert_eq!(db.get_entries(stack.id)?, vec![entry.clone()]);
+        assert_eq!(db.get_postings("kept", false)?.len(), 1);
+
+        let trashed = db.trash_entry(&entry)?;
+        db.delete_stack(&db.get_stack(stack.id)?.unwrap())?;
+        let recreated = db.save_stack(&Stack::named("ideas".to_string()))?;
+        db.restore_trashed(trashed.id)?;
+        assert_eq!(db.get_entries(recreated.id)?.len(), 1);
+        assert!(db.restore_trashed(trashed.id).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_trashed_entries_keep_their_history() -> Result<()> {
+        let db = MemoryDbReader::new();
+        let stack = db.save_stack(&Stack::named("ideas".to_string()))?;
+        let mut entry = db.save_entry(&Entry::new(stack.id, "draft".to_string()))?;
+        entry.set_content("final".to_string());
+        db.update_entry(&entry)?;
+
+        let trashed = db.trash_entry(&entry)?;
+        db.restore_trashed(trashed.id)?;
+        assert_eq!(db.get_revisions(entry.id)?.into_iter().map(|r| r.content).collect::<Vec<_>>(), vec!["draft"]);
+
+        let trashed = db.trash_stack(&db.get_stack(stack.id)?.unwrap())?;
+        db.restore_trashed(trashed.id)?;
+        assert_eq!(db.get_revisions(entry.id)?.len(), 1);
+
+        let trashed = db.trash_entry(&entry)?;
+        db.remove_trashed(trashed.id)?;
+        assert_eq!(db.get_revisions(entry.id)?, vec![]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_purge_trash_removes_old_items_at_once() -> Result<()> {
+        let db = MemoryDbReader::new();
+        let stack = db.save_stack(&Stack::named("ideas".to_string()))?;
+        let mut entries = Vec::new();
+        for content in ["one", "two", "three"] {
+            let mut entry = db.save_entry(&Entry::new(stack.id, content.to_string()))?;
+            entry.set_content(format!("{content}!"));
+            db.update_entry(&entry)?;
+            entries.push(entry);
+        }
+        let trashed = db.trash_entries(&entries[..2])?;
+        let cutoff = Utc::now() + chrono::Duration::try_seconds(1).unwrap();
+        assert_eq!(db.purge_trash(cutoff)?, 2);
+        assert_eq!(db.get_trash()?, vec![]);
+        assert_eq!(db.get_all_revisions()?.into_iter().map(|r| r.content).collect::<Vec<_>>(), vec!["three"]);
+
+        // Items that are gone already are skipped.
+        db.remove_trashed_items(&[trashed[0].id, trashed[1].id])?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_failed_batch_writes_nothing() -> Result<()> {
+        let db = MemoryDbReader::new();
+        let stack = db.save_stack(&Stack::named("ideas".to_string()))?;
+        let other = db.save_stack(&Stack::named("elsewhere".to_string()))?;
+        let first = db.save_entry(&Entry::new(stack.id, "one".to_string()))?;
+        let second = db.save_entry(&Entry::new(stack.id, "two".to_string()))?;
+        let gone = db.save_entry(&Entry::new(stack.id, "three".to_string()))?;
+        db.delete_entry(&gone)?;
+
+        assert!(db.merge_entries(&[first.clone(), second.clone(), gone.clone()]).is_err());
+        assert!(db.move_entries(&[first.clone(), gone.clone()], &other).is_err());
+        assert!(db.trash_entries(&[second.clone(), gone]).is_err());
+        assert_eq!(db.get_entries(stack.id)?, vec![first.clone(), second.clone()]);
+        assert_eq!(db.get_revisions(first.id)?, vec![]);
+        assert_eq!(db.get_trash()?, vec![]);
+
+        let merged = db.merge_entries(&[second, first])?;
+        assert_eq!(merged.content, "one\n\ntwo");
+        assert_eq!(db.get_entries(stack.id)?, vec![merged]);
+        assert_eq!(db.get_trash()?.len(), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_update_missing_entry_fails() {
+        let db = MemoryDbReader::new();
+        let entry = Entry::new(1, String::new());
+        assert!(db.update_entry(&entry).is_err());
+    }
+}
@@ -0,0 +1,1319 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::{Mutex, MutexGuard},
+    thread,
+    time::{Duration, Instant},
+};
+
+use chrono::{DateTime, NaiveDate, Utc};
+use color_eyre::eyre::{eyre, Result};
+use lazy_static::lazy_static;
+use native_db::{
+    db_type,
+    transaction::{RTransaction, RwTransaction},
+    *,
+};
+use native_model::{native_model, Model};
+use serde::{Deserialize, Serialize};
+
+use super::DbReader;
+use crate::{
+    crypto::{DataKey, Keyring},
+    models::{
+        entry::{Entry, EntryKey, EntryV1},
+        revision::{Revision, RevisionKey},
+        stack::{Stack, StackKey, StackV1, StackV2, StackV3},
+        trashed::Trashed,
+    },
+    search::{self, Posting},
+    utils::get_data_dir,
+};
+
+const DB_FILE: &str = "terajot.db";
+
+/// How long to wait for another terajot process to release the database.
+const LOCK_TIMEOUT: Duration = Duration::from_secs(5);
+const LOCK_RETRY_DELAY: Duration = Duration::from_millis(20);
+
+/// Bumped whenever the tokenizer changes, so the full-text index is rebuilt on open.
+const SEARCH_INDEX_VERSION: u32 = 1;
+/// The [`Sequence`] recording which version the full-text index was built with.
+const SEARCH_INDEX_SEQUENCE: &str = "search_index";
+/// There is at most one keyring, always stored under this id.
+const KEYRING_ID: u32 = 1;
+
+/// The last id handed out for a model.
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+#[native_model(id = 3, version = 1)]
+#[native_db]
+struct Sequence {
+    #[primary_key]
+    name: String,
+    last: u32,
+}
+
+/// A full-text index posting. The key starts with the term, so terms can be scanned by
+/// prefix, and the NUL separator keeps `foo` from matching `foobar` in exact lookups.
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+#[native_model(id = 4, version = 1)]
+#[native_db(primary_key(key))]
+struct IndexedTerm {
+    term: String,
+    entry_id: u32,
+    positions: Vec<u32>,
+}
+
+impl IndexedTerm {
+    fn key(&self) -> String {
+        format!("{}\0{:010}", self.term, self.entry_id)
+    }
+}
+
+/// The passphrase-wrapped key of an encrypted journal. Its presence is what makes the
+/// journal encrypted.
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+#[native_model(id = 5, version = 1)]
+#[native_db]
+struct StoredKeyring {
+    #[primary_key]
+    id: u32,
+    keyring: Keyring,
+}
+
+lazy_static! {
+    /// Every model stored by terajot, including the legacy versions that may still need
+    /// converting, has to be defined here before a database is opened.
+    static ref MODELS: DatabaseBuilder = {
+        let mut builder = DatabaseBuilder::new();
+        builder.define::<Sequence>().expect("failed to define the Sequence model");
+        builder.define::<StackV1>().expect("failed to define the StackV1 model");
+        builder.define::<StackV2>().expect("failed to define the StackV2 model");
+        builder.define::<StackV3>().expect("failed to define the StackV3 model");
+        builder.define::<Stack>().expect("failed to define the Stack model");
+        builder.define::<EntryV1>().expect("failed to define the EntryV1 model");
+        builder.define::<Entry>().expect("failed to define the Entry model");
+        builder.define::<IndexedTerm>().expect("failed to define the IndexedTerm model");
+        builder.define::<StoredKeyring>().expect("failed to define the StoredKeyring model");
+        builder.define::<Trashed>().expect("failed to define the Trashed model");
+        builder.define::<Revision>().expect("failed to define the Revision model");
+        builder
+    };
+}
+
+/// A [`DbReader`] backed by a native_db database file.
+///
+/// The file is only opened for the duration of each operation. redb locks it exclusively
+/// while it is open, so this lets several terajot processes (e.g. the TUI and a
+/// `terajot add` from a script) take turns instead of failing.
+///
+/// Once a passphrase is set, entry content is stored encrypted. The full-text index would
+/// give the words of every entry away, so encrypted journals don't keep one and search by
+/// decrypting the entries instead. Stack names are not encrypted.
+pub struct NativeDbReader {
+    path: PathBuf,
+    /// The key of an encrypted journal, while it is unlocked.
+    key: Mutex<Option<DataKey>>,
+}
+
+impl NativeDbReader {
+    /// Opens (or creates) the database in the data directory.
+    pub fn new() -> Result<Self> {
+        let directory = get_data_dir();
+        std::fs::create_dir_all(&directory)?;
+        Self::open(directory.join(DB_FILE))
+    }
+
+    /// Opens (or creates) the database at the given path.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let reader = Self {
+            path: path.as_ref().to_path_buf(),
+            key: Mutex::new(None),
+        };
+        Self::migrate(&reader.connect()?)?;
+        Ok(reader)
+    }
+
+    /// Opens the database file, waiting for other processes to release it.
+    fn connect(&self) -> Result<Database<'static>> {
+        let started = Instant::now();
+        loop {
+            match MODELS.create(&self.path) {
+                Err(db_type::Error::RedbDatabaseError(redb::DatabaseError::DatabaseAlreadyOpen))
+                    if started.elapsed() < LOCK_TIMEOUT =>
+                {
+                    thread::sleep(LOCK_RETRY_DELAY)
+                }
+                Err(db_type::Error::RedbDatabaseError(redb::DatabaseError::DatabaseAlreadyOpen)) => {
+                    return Err(eyre!("{} is locked by another terajot process", self.path.display()))
+                }
+                result => return Ok(result?),
+            }
+        }
+    }
+
+    /// Brings data written by older versions of terajot up to the current models.
+    ///
+    /// `RwTransaction::migrate` refuses to run as soon as more than one model holds
+    /// data, so every legacy model is converted on its own instead.
+    fn migrate(db: &Database) -> Result<()> {
+        let rw = db.rw_transaction()?;
+        rw.convert_all::<StackV1, Stack>()?;
+        rw.convert_all::<StackV2, Stack>()?;
+        rw.convert_all::<StackV3, Stack>()?;
+        rw.convert_all::<EntryV1, Entry>()?;
+        Self::build_search_index(&rw)?;
+        rw.commit()?;
+        Ok(())
+    }
+
+    /// Indexes every entry if the full-text index is missing or was built by another
+    /// version of the tokenizer.
+    fn build_search_index(rw: &RwTransaction) -> Result<()> {
+        let built: Option<Sequence> = rw.get().primary(SEARCH_INDEX_SEQUENCE.to_string())?;
+        if built.as_ref().is_some_and(|s| s.last == SEARCH_INDEX_VERSION) {
+            return Ok(());
+        }
+        let encrypted: Option<StoredKeyring> = rw.get().primary(KEYRING_ID)?;
+        if encrypted.is_some() {
+            return Ok(());
+        }
+
+        let stale: Vec<IndexedTerm> = rw.scan().primary()?.all().collect();
+        for term in stale {
+            rw.remove(term)?;
+        }
+        let entries: Vec<Entry> = rw.scan().primary()?.all().collect();
+        for entry in &entries {
+            Self::index_entry(rw, entry)?;
+        }
+
+        let version = Sequence {
+            name: SEARCH_INDEX_SEQUENCE.to_string(),
+            last: SEARCH_INDEX_VERSION,
+        };
+        match built {
+            Some(old) => rw.update(old, version)?,
+            None => rw.insert(version)?,
+        }
+        Ok(())
+    }
+
+    fn indexed_terms(entry: &Entry) -> impl Iterator<Item = IndexedTerm> {
+        search::postings(entry).into_iter().map(|p| IndexedTerm {
+            term: p.term,
+            entry_id: p.entry_id,
+            positions: p.positions,
+        })
+    }
+
+    fn index_entry(rw: &RwTransaction, entry: &Entry) -> Result<()> {
+        for term in Self::indexed_terms(entry) {
+            rw.insert(term)?;
+        }
+        Ok(())
+    }
+
+    /// Removes an entry from the full-text index. `entry` has to be the stored version,
+    /// as its postings are derived from its content.
+    fn unindex_entry(rw: &RwTransaction, entry: &Entry) -> Result<()> {
+        for term in Self::indexed_terms(entry) {
+            rw.remove(term)?;
+        }
+        Ok(())
+    }
+
+    /// Hands out the next id of a model. Running inside the write transaction that inserts
+    /// the model makes it collision free, as redb only allows one writer at a time.
+    ///
+    /// Databases created before ids were allocated start after their highest id.
+    fn next_id<T: Input>(rw: &RwTransaction, name: &str, id_of: impl Fn(&T) -> u32) -> Result<u32> {
+        let sequence: Option<Sequence> = rw.get().primary(name.to_string())?;
+        let last = match &sequence {
+            Some(sequence) => sequence.last,
+            None => rw.scan().primary::<T>()?.all().map(|m| id_of(&m)).max().unwrap_or(0),
+        };
+        let next = last.checked_add(1).ok_or_else(|| eyre!("Ran out of ids for {name}"))?;
+        let updated = Sequence {
+            name: name.to_string(),
+            last: next,
+        };
+        match sequence {
+            Some(old) => rw.update(old, updated)?,
+            None => rw.insert(updated)?,
+        }
+        Ok(next)
+    }
+
+    /// Makes sure ids handed out later come after `id`, which was written as is.
+    fn reserve_id<T: Input>(rw: &RwTransaction, name: &str, id: u32, id_of: impl Fn(&T) -> u32) -> Result<()> {
+        let sequence: Option<Sequence> = rw.get().primary(name.to_string())?;
+        let last = match &sequence {
+            Some(sequence) => sequence.last,
+            None => rw.scan().primary::<T>()?.all().map(|m| id_of(&m)).max().unwrap_or(0),
+        };
+        if id <= last {
+            return Ok(());
+        }
+        let updated = Sequence {
+            name: name.to_string(),
+            last: id,
+        };
+        match sequence {
+            Some(old) => rw.update(old, updated)?,
+            None => rw.insert(updated)?,
+        }
+        Ok(())
+    }
+
+    fn stored_key(&self) -> Result<MutexGuard<'_, Option<DataKey>>> {
+        self.key.lock().map_err(|e| eyre!("Poisoned key: {e}"))
+    }
+
+    /// Gets the key entries are encrypted with, `None` meaning they are stored as is.
+    fn data_key(&self, keyring: Option<StoredKeyring>) -> Result<Option<DataKey>> {
+        match keyring {
+            None => Ok(None),
+            Some(_) => self.stored_key()?.clone().map(Some).ok_or_else(|| eyre!("The journal is locked")),
+        }
+    }
+
+    fn read_key(&self, r: &RTransaction) -> Result<Option<DataKey>> {
+        self.data_key(r.get().primary(KEYRING_ID)?)
+    }
+
+    fn write_key(&self, rw: &RwTransaction) -> Result<Option<DataKey>> {
+        self.data_key(rw.get().primary(KEYRING_ID)?)
+    }
+
+    /// Encrypts the content of an entry, bound to its id so it can't be moved to another one.
+    fn seal(key: Option<&DataKey>, entry: &Entry) -> Result<Entry> {
+        let mut entry = entry.clone();
+        if let Some(key) = key {
+            entry.content = key.seal(&entry.content, &entry.id.to_le_bytes())?;
+        }
+        Ok(entry)
+    }
+
+    fn open_entry(key: Option<&DataKey>, mut entry: Entry) -> Result<Entry> {
+        if let Some(key) = key {
+            entry.content = key.open(&entry.content, &entry.id.to_le_bytes())?;
+        }
+        Ok(entry)
+    }
+
+    fn open_entries(key: Option<&DataKey>, entries: Vec<Entry>) -> Result<Vec<Entry>> {
+        entries.into_iter().map(|e| Self::open_entry(key, e)).collect()
+    }
+
+    /// Revisions are sealed like the entry they belong to.
+    fn seal_revision(key: Option<&DataKey>, revision: &Revision) -> Result<Revision> {
+        let mut revision = revision.clone();
+        if let Some(key) = key {
+            revision.content = key.seal(&revision.content, &revision.entry_id.to_le_bytes())?;
+        }
+        Ok(revision)
+    }
+
+    fn open_revision(key: Option<&DataKey>, mut revision: Revision) -> Result<Revision> {
+        if let Some(key) = key {
+            revision.content = key.open(&revision.content, &revision.entry_id.to_le_bytes())?;
+        }
+        Ok(revision)
+    }
+
+    fn remove_revisions(rw: &RwTransaction, entry_id: u32) -> Result<()> {
+        let revisions: Vec<Revision> =
+            rw.scan().secondary(RevisionKey::entry_key)?.start_with(entry_id).collect();
+        for revision in revisions {
+            rw.remove(revision)?;
+        }
+        Ok(())
+    }
+
+    /// Takes a stored entry out of the entries and the full-text index. Its revisions stay
+    /// under its id, so they come back when it is restored from the trash.
+    fn take_entry(rw: &RwTransaction, key: Option<&DataKey>, entry: Entry) -> Result<()> {
+        if key.is_none() {
+            Self::unindex_entry(rw, &entry)?;
+        }
+        rw.remove(entry)?;
+        Ok(())
+    }
+
+    /// Removes a stored entry for good, along with its revisions and its postings.
+    fn remove_entry(rw: &RwTransaction, key: Option<&DataKey>, entry: Entry) -> Result<()> {
+        Self::remove_revisions(rw, entry.id)?;
+        Self::take_entry(rw, key, entry)
+    }
+
+    /// Replaces a stored entry, keeping its previous content as a revision when it changes.
+    fn update_in(rw: &RwTransaction, key: Option<&DataKey>, entry: &Entry) -> Result<()> {
+        let old: Entry = rw
+            .get()
+            .primary(entry.id)?
+            .ok_or_else(|| eyre!("Entry {} does not exist", entry.id))?;
+        if Self::open_entry(key, old.clone())?.content != entry.content {
+            // `old` is sealed already, the way the revision has to be.
+            rw.insert(Revision {
+                id: Self::next_id::<Revision>(rw, "revision", |r| r.id)?,
+                ..Revision::of(&old)
+            })?;
+        }
+        if key.is_none() {
+            Self::unindex_entry(rw, &old)?;
+            Self::index_entry(rw, entry)?;
+        }
+        rw.update(old, Self::seal(key, entry)?)?;
+        Ok(())
+    }
+
+    /// Moves a stored entry to the trash, along with the stack it is in.
+    fn trash_in(rw: &RwTransaction, key: Option<&DataKey>, entry: &Entry) -> Result<Trashed> {
+        let old: Entry = rw
+            .get()
+            .primary(entry.id)?
+            .ok_or_else(|| eyre!("Entry {} does not exist", entry.id))?;
+        let mut stack: Stack = rw
+            .get()
+            .primary(entry.stack_id)?
+            .ok_or_else(|| eyre!("Stack {} does not exist", entry.stack_id))?;
+        stack.count = rw.scan().secondary::<Entry>(EntryKey::stack_key)?.start_with(stack.id).count() as u64;
+        let trashed = Self::insert_trashed(rw, key, &Trashed::entry(stack, entry.clone()))?;
+        Self::take_entry(rw, key, old)?;
+        Ok(trashed)
+    }
+
+    fn insert_trashed(rw: &RwTransaction, key: Option<&DataKey>, trashed: &Trashed) -> Result<Trashed> {
+        let mut trashed = trashed.clone();
+        trashed.id = Self::next_id::<Trashed>(rw, "trashed", |t| t.id)?;
+        rw.insert(Self::seal_trashed(key, &trashed)?)?;
+        Ok(trashed)
+    }
+
+    fn seal_trashed(key: Option<&DataKey>, trashed: &Trashed) -> Result<Trashed> {
+        let entries = trashed.entries.iter().map(|e| Self::seal(key, e)).collect::<Result<_>>()?;
+        Ok(Trashed {
+            entries,
+            ..trashed.clone()
+        })
+    }
+
+    fn open_trashed(key: Option<&DataKey>, trashed: Trashed) -> Result<Trashed> {
+        Ok(Trashed {
+            entries: Self::open_entries(key, trashed.entries)?,
+            ..trashed
+        })
+    }
+
+    fn count_entries(r: &RTransaction, stack_id: u32) -> Result<u64> {
+        let count = r.scan().secondary::<Entry>(EntryKey::stack_key)?.start_with(stack_id).count();
+        Ok(count as u64)
+    }
+
+    /// Writes stacks, entries, revisions and trashed items under their own ids, see
+    /// [`DbReader::restore`].
+    fn restore_in(
+        rw: &RwTransaction,
+        key: Option<&DataKey>,
+        stacks: &[Stack],
+        entries: &[Entry],
+        revisions: &[Revision],
+        trash: &[Trashed],
+    ) -> Result<()> {
+        for stack in stacks {
+            let old: Option<Stack> = rw.get().primary(stack.id)?;
+            match old {
+                Some(old) => rw.update(old, stack.clone())?,
+                None => rw.insert(stack.clone())?,
+            }
+        }
+        for entry in entries {
+            let old: Option<Entry> = rw.get().primary(entry.id)?;
+            if let Some(old) = old {
+                if key.is_none() {
+                    Self::unindex_entry(rw, &old)?;
+                }
+                rw.remove(old)?;
+            }
+            rw.insert(Self::seal(key, entry)?)?;
+            if key.is_none() {
+                Self::index_entry(rw, entry)?;
+            }
+        }
+        for revision in revisions {
+            let old: Option<Revision> = rw.get().primary(revision.id)?;
+            if let Some(old) = old {
+                rw.remove(old)?;
+            }
+            rw.insert(Self::seal_revision(key, revision)?)?;
+        }
+        for trashed in trash {
+            let old: Option<Trashed> = rw.get().primary(trashed.id)?;
+            if let Some(old) = old {
+                rw.remove(old)?;
+            }
+            rw.insert(Self::seal_trashed(key, trashed)?)?;
+        }
+
+        let trashed_stacks = trash.iter().map(|t| t.stack.id);
+        if let Some(last) = stacks.iter().map(|s| s.id).chain(trashed_stacks).max() {
+            Self::reserve_id::<Stack>(rw, "stack", last, |s| s.id)?;
+        }
+        let trashed_entries = trash.iter().flat_map(|t| t.entries.iter().map(|e| e.id));
+        if let Some(last) = entries.iter().map(|e| e.id).chain(trashed_entries).max() {
+            Self::reserve_id::<Entry>(rw, "entry", last, |e| e.id)?;
+        }
+        if let Some(last) = revisions.iter().map(|r| r.id).max() {
+            Self::reserve_id::<Revision>(rw, "revision", last, |r| r.id)?;
+        }
+        if let Some(last) = trash.iter().map(|t| t.id).max() {
+            Self::reserve_id::<Trashed>(rw, "trashed", last, |t| t.id)?;
+        }
+        Ok(())
+    }
+
+    fn insert_entry(rw: &RwTransaction, key: Option<&DataKey>, entry: &Entry) -> Result<Entry> {
+        let mut entry = entry.clone();
+        entry.id = Self::next_id::<Entry>(rw, "entry", |e| e.id)?;
+        rw.insert(Self::seal(key, &entry)?)?;
+        if key.is_none() {
+            Self::index_entry(rw, &entry)?;
+        }
+        Ok(entry)
+    }
+
+    fn insert_stack(rw: &RwTransaction, stack: &Stack) -> Result<Stack> {
+        let mut stack = stack.clone();
+        stack.id = Self::next_id::<Stack>(rw, "stack", |s| s.id)?;
+        rw.insert(stack.clone())?;
+        Ok(stack)
+    }
+}
+
+impl DbReader for NativeDbReader {
+    fn get_stacks(&self) -> Result<Vec<Stack>> {
+        let db = self.connect()?;
+        let r = db.r_transaction()?;
+        let mut stacks: Vec<Stack> = r.scan().primary()?.all().collect();
+        for stack in stacks.iter_mut() {
+            stack.count = Self::count_entries(&r, stack.id)?;
+        }
+        Ok(stacks)
+    }
+
+    fn get_stack(&self, id: u32) -> Result<Option<Stack>> {
+        let db = self.connect()?;
+        let r = db.r_transaction()?;
+        let mut stack: Option<Stack> = r.get().primary(id)?;
+        if let Some(stack) = stack.as_mut() {
+            stack.count = Self::count_entries(&r, stack.id)?;
+        }
+        Ok(stack)
+    }
+
+    fn get_day_stack(&self, day: NaiveDate) -> Result<Option<Stack>> {
+        let db = self.connect()?;
+        let r = db.r_transaction()?;
+        let mut stack: Option<Stack> = r.get().secondary(StackKey::day_key, day.format(Stack::DAY_FORMAT).to_string())?;
+        if let Some(stack) = stack.as_mut() {
+            stack.count = Self::count_entries(&r, stack.id)?;
+        }
+        Ok(stack)
+    }
+
+    fn get_or_create_day_stack(&self, day: NaiveDate) -> Result<Stack> {
+        let db = self.connect()?;
+        let rw = db.rw_transaction()?;
+        let existing: Option<Stack> = rw.get().secondary(StackKey::day_key, day.format(Stack::DAY_FORMAT).to_string())?;
+        let stack = match existing {
+            Some(mut stack) => {
+                stack.count = rw.scan().secondary::<Entry>(EntryKey::stack_key)?.start_with(stack.id).count() as u64;
+                stack
+            }
+            None => Self::insert_stack(&rw, &Stack::for_day(day))?,
+        };
+        rw.commit()?;
+        Ok(stack)
+    }
+
+    fn save_stack(&self, stack: &Stack) -> Result<Stack> {
+        let db = self.connect()?;
+        let rw = db.rw_transaction()?;
+        let stack = Self::insert_stack(&rw, stack)?;
+        rw.commit()?;
+        Ok(stack)
+    }
+
+    fn update_stack(&self, stack: &Stack) -> Result<()> {
+        let db = self.connect()?;
+        let rw = db.rw_transaction()?;
+        let old: Stack = rw
+            .get()
+            .primary(stack.id)?
+            .ok_or_else(|| eyre!("Stack {} does not exist", stack.id))?;
+        rw.update(old, stack.clone())?;
+        rw.commit()?;
+        Ok(())
+    }
+
+    fn delete_stack(&self, stack: &Stack) -> Result<()> {
+        let db = self.connect()?;
+        let rw = db.rw_transaction()?;
+        let key = self.write_key(&rw)?;
+        let entries: Vec<Entry> = rw
+            .scan()
+            .secondary(EntryKey::stack_key)?
+            .start_with(stack.id)
+            .collect();
+        for entry in entries {
+            Self::remove_entry(&rw, key.as_ref(), entry)?;
+        }
+        rw.remove(stack.clone())?;
+        rw.commit()?;
+        Ok(())
+    }
+
+    fn get_all_entries(&self) -> Result<Vec<Entry>> {
+        let db = self.connect()?;
+        let r = db.r_transaction()?;
+        let key = self.read_key(&r)?;
+        let entries = r.scan().primary()?.all().collect();
+        Self::open_entries(key.as_ref(), entries)
+    }
+
+    fn get_entries(&self, stack_id: u32) -> Result<Vec<Entry>> {
+        let db = self.connect()?;
+        let r = db.r_transaction()?;
+        let key = self.read_key(&r)?;
+        let entries = r.scan().secondary(EntryKey::stack_key)?.start_with(stack_id).collect();
+        Self::open_entries(key.as_ref(), entries)
+    }
+
+    fn get_entries_created_between(&self, start: DateTime<Utc>, end: DateTime<Utc>) -> Result<Vec<Entry>> {
+        let db = self.connect()?;
+        let r = db.r_transaction()?;
+        let key = self.read_key(&r)?;
+        let entries = r
+            .scan()
+            .secondary(EntryKey::created_key)?
+            .range((start.timestamp_micros(), 0_u32)..(end.timestamp_micros(), 0_u32))
+            .collect();
+        Self::open_entries(key.as_ref(), entries)
+    }
+
+    fn get_entry(&self, id: u32) -> Result<Option<Entry>> {
+        let db = self.connect()?;
+        let r = db.r_transaction()?;
+        let key = self.read_key(&r)?;
+        let entry: Option<Entry> = r.get().primary(id)?;
+        entry.map(|e| Self::open_entry(key.as_ref(), e)).transpose()
+    }
+
+    fn get_entries_by_ids(&self, ids: &[u32]) -> Result<Vec<Entry>> {
+        let db = self.connect()?;
+        let r = db.r_transaction()?;
+        let key = self.read_key(&r)?;
+        let mut entries = Vec::with_capacity(ids.len());
+        for id in ids {
+            let entry: Option<Entry> = r.get().primary(*id)?;
+            if let Some(entry) = entry {
+                entries.push(Self::open_entry(key.as_ref(), entry)?);
+            }
+        }
+        Ok(entries)
+    }
+
+    fn save_entry(&self, entry: &Entry) -> Result<Entry> {
+        let mut saved = self.save_entries(std::slice::from_ref(entry))?;
+        Ok(saved.remove(0))
+    }
+
+    fn save_entries(&self, entries: &[Entry]) -> Result<Vec<Entry>> {
+        let db = self.connect()?;
+        let rw = db.rw_transaction()?;
+        let key = self.write_key(&rw)?;
+        let saved = entries
+            .iter()
+            .map(|entry| Self::insert_entry(&rw, key.as_ref(), entry))
+            .collect::<Result<Vec<_>>>()?;
+        rw.commit()?;
+        Ok(saved)
+    }
+
+    fn save_entries_into(&self, entries: &[(Stack, Entry)]) -> Result<Vec<Entry>> {
+        let db = self.connect()?;
+        let rw = db.rw_transaction()?;
+        let key = self.write_key(&rw)?;
+        let mut stacks: Vec<Stack> = rw.scan().primary()?.all().collect();
+        let mut saved = Vec::with_capacity(entries.len());
+        for (stack, entry) in entries {
+            let stack_id = match stacks.iter().find(|s| stack.is_same_as(s)) {
+                Some(s) if s.is_smart() => return Err(eyre!("{} is a smart stack, entries can't be added to it", s.name)),
+                Some(s) => s.id,
+                None => {
+                    let created = Self::insert_stack(&rw, stack)?;
+                    let id = created.id;
+                    stacks.push(created);
+                    id
+                }
+            };
+            let entry = Entry {
+                stack_id,
+                ..entry.clone()
+            };
+            saved.push(Self::insert_entry(&rw, key.as_ref(), &entry)?);
+        }
+        rw.commit()?;
+        Ok(saved)
+    }
+
+    fn update_entry(&self, entry: &Entry) -> Result<()> {
+        let db = self.connect()?;
+        let rw = db.rw_transaction()?;
+        let key = self.write_key(&rw)?;
+        Self::update_in(&rw, key.as_ref(), entry)?;
+        rw.commit()?;
+        Ok(())
+    }
+
+    fn delete_entry(&self, entry: &Entry) -> Result<()> {
+        let db = self.connect()?;
+        let rw = db.rw_transaction()?;
+        let key = self.write_key(&rw)?;
+        let old: Option<Entry> = rw.get().primary(entry.id)?;
+        if let Some(old) = old {
+            Self::remove_entry(&rw, key.as_ref(), old)?;
+        }
+        rw.commit()?;
+        Ok(())
+    }
+
+    fn count_all_entries(&self) -> Result<u64> {
+        let db = self.connect()?;
+        let r = db.r_transaction()?;
+        Ok(r.len().primary::<Entry>()?)
+    }
+
+    fn get_postings(&self, term: &str, prefix: bool) -> Result<Vec<Posting>> {
+        let mut postings = self.get_postings_of(&[(term.to_string(), prefix)])?;
+        Ok(postings.pop().unwrap_or_default())
+    }
+
+    /// An encrypted journal has no index, so its entries are decrypted and tokenized once
+    /// for all the terms.
+    fn get_postings_of(&self, terms: &[(String, bool)]) -> Result<Vec<Vec<Posting>>> {
+        let db = self.connect()?;
+        let r = db.r_transaction()?;
+        if let Some(key) = self.read_key(&r)? {
+            let entries = r.scan().primary()?.all().collect();
+            let entries = Self::open_entries(Some(&key), entries)?;
+            let all = entries.iter().flat_map(search::postings).collect::<Vec<_>>();
+            let matching = |term: &str, prefix: bool| {
+                let matches = |t: &str| if prefix { t.starts_with(term) } else { t == term };
+                all.iter().filter(|p| matches(&p.term)).cloned().collect()
+            };
+            return Ok(terms.iter().map(|(term, prefix)| matching(term, *prefix)).collect());
+        }
+        let mut postings = Vec::with_capacity(terms.len());
+        for (term, prefix) in terms {
+            let start = if *prefix { term.to_string() } else { format!("{term}\0") };
+            let indexed: Vec<IndexedTerm> = r.scan().primary()?.start_with(start).collect();
+            postings.push(
+                indexed
+                    .into_iter()
+                    .map(|t| Posting {
+                        term: t.term,
+                        entry_id: t.entry_id,
+                        positions: t.positions,
+                    })
+                    .collect(),
+            );
+        }
+        Ok(postings)
+    }
+
+    fn restore(&self, stacks: &[Stack], entries: &[Entry], revisions: &[Revision], trash: &[Trashed]) -> Result<()> {
+        let db = self.connect()?;
+        let rw = db.rw_transaction()?;
+        let key = self.write_key(&rw)?;
+        Self::restore_in(&rw, key.as_ref(), stacks, entries, revisions, trash)?;
+        rw.commit()?;
+        Ok(())
+    }
+
+    fn get_revisions(&self, entry_id: u32) -> Result<Vec<Revision>> {
+        let db = self.connect()?;
+        let r = db.r_transaction()?;
+        let key = self.read_key(&r)?;
+        let revisions: Vec<Revision> = r.scan().secondary(RevisionKey::entry_key)?.start_with(entry_id).collect();
+        revisions.into_iter().map(|revision| Self::open_revision(key.as_ref(), revision)).collect()
+    }
+
+    fn get_all_revisions(&self) -> Result<Vec<Revision>> {
+        let db = self.connect()?;
+        let r = db.r_transaction()?;
+        let key = self.read_key(&r)?;
+        let revisions: Vec<Revision> = r.scan().primary()?.all().collect();
+        revisions.into_iter().map(|revision| Self::open_revision(key.as_ref(), revision)).collect()
+    }
+
+    fn prune_revisions(&self, keep: usize) -> Result<usize> {
+        let db = self.connect()?;
+        let rw = db.rw_transaction()?;
+        let mut revisions: Vec<Revision> = rw.scan().primary()?.all().collect();
+        revisions.reverse();
+        let mut kept = HashMap::new();
+        let mut pruned = 0;
+        for revision in revisions {
+            let count = kept.entry(revision.entry_id).or_insert(0);
+            *count += 1;
+            if *count > keep {
+                rw.remove(revision)?;
+                pruned += 1;
+            }
+        }
+        rw.commit()?;
+        Ok(pruned)
+    }
+
+    fn save_trashed(&self, trashed: &Trashed) -> Result<Trashed> {
+        let db = self.connect()?;
+        let rw = db.rw_transaction()?;
+        let key = self.write_key(&rw)?;
+        let trashed = Self::insert_trashed(&rw, key.as_ref(), trashed)?;
+        rw.commit()?;
+        Ok(trashed)
+    }
+
+    fn get_trash(&self) -> Result<Vec<Trashed>> {
+        let db = self.connect()?;
+        let r = db.r_transaction()?;
+        let key = self.read_key(&r)?;
+        let trash: Vec<Trashed> = r.scan().primary()?.all().collect();
+        trash.into_iter().map(|t| Self::open_trashed(key.as_ref(), t)).collect()
+    }
+
+    fn remove_trashed_items(&self, ids: &[u32]) -> Result<()> {
+        let db = self.connect()?;
+        let rw = db.rw_transaction()?;
+        for id in ids {
+            let old: Option<Trashed> = rw.get().primary(*id)?;
+            if let Some(old) = old {
+                for entry in &old.entries {
+                    Self::remove_revisions(&rw, entry.id)?;
+                }
+                rw.remove(old)?;
+            }
+        }
+        rw.commit()?;
+        Ok(())
+    }
+
+    fn write_batch(&self, updated: &[Entry], trashed: &[Entry]) -> Result<Vec<Trashed>> {
+        let db = self.connect()?;
+        let rw = db.rw_transaction()?;
+        let key = self.write_key(&rw)?;
+        for entry in updated {
+            Self::update_in(&rw, key.as_ref(), entry)?;
+        }
+        let trashed = trashed
+            .iter()
+            .map(|entry| Self::trash_in(&rw, key.as_ref(), entry))
+            .collect::<Result<Vec<_>>>()?;
+        // Dropping the transaction on an error above rolls back what was written so far.
+        rw.commit()?;
+        Ok(trashed)
+    }
+
+    fn trash_stack(&self, stack: &Stack) -> Result<Trashed> {
+        let db = self.connect()?;
+        let rw = db.rw_transaction()?;
+        let key = self.write_key(&rw)?;
+        let entries: Vec<Entry> = rw
+            .scan()
+            .secondary(EntryKey::stack_key)?
+            .start_with(stack.id)
+            .collect();
+        let opened = Self::open_entries(key.as_ref(), entries.clone())?;
+        let trashed = Self::insert_trashed(&rw, key.as_ref(), &Trashed::stack(stack.clone(), opened))?;
+        for entry in entries {
+            Self::take_entry(&rw, key.as_ref(), entry)?;
+        }
+        rw.remove(stack.clone())?;
+        rw.commit()?;
+        Ok(trashed)
+    }
+
+    fn restore_trashed(&self, id: u32) -> Result<Trashed> {
+        let db = self.connect()?;
+        let rw = db.rw_transaction()?;
+        let key = self.write_key(&rw)?;
+        let sealed: Trashed = rw
+            .get()
+            .primary(id)?
+            .ok_or_else(|| eyre!("There is nothing with id {id} in the trash"))?;
+        let trashed = Self::open_trashed(key.as_ref(), sealed.clone())?;
+        let stacks: Vec<Stack> = rw.scan().primary()?.all().collect();
+        let (restored, stack_id) = match trashed.home(&stacks) {
+            Some(stack) => (Vec::new(), stack.id),
+            None => (vec![trashed.stack.clone()], trashed.stack.id),
+        };
+        let entries = trashed.entries.iter().map(|e| Entry { stack_id, ..e.clone() }).collect::<Vec<_>>();
+        Self::restore_in(&rw, key.as_ref(), &restored, &entries, &[], &[])?;
+        rw.remove(sealed)?;
+        rw.commit()?;
+        Ok(trashed)
+    }
+
+    fn snapshot(&self, to: &Path) -> Result<()> {
+        // Holding the database keeps other processes out until the copy is complete.
+        let _db = self.connect()?;
+        std::fs::copy(&self.path, to)?;
+        Ok(())
+    }
+
+    fn replace_with(&self, snapshot: &Path) -> Result<()> {
+        let staging = self.path.with_extension("db.restoring");
+        std::fs::copy(snapshot, &staging)?;
+        // Opening the copy checks that it is a terajot database and brings it up to date.
+        if let Err(e) = Self::open(&staging) {
+            std::fs::remove_file(&staging)?;
+            return Err(eyre!("{} is not a usable terajot database: {e}", snapshot.display()));
+        }
+        // Waits for other processes to let go of the journal, then drops the handle before the
+        // rename, as a file that is still open can't be replaced on every platform. A process
+        // opening the journal in between works on the old database, which the rename replaces.
+        drop(self.connect()?);
+        std::fs::rename(&staging, &self.path)?;
+        // The restored journal may be encrypted with another passphrase, or not at all.
+        *self.stored_key()? = None;
+        Ok(())
+    }
+
+    fn is_encrypted(&self) -> Result<bool> {
+        let db = self.connect()?;
+        let r = db.r_transaction()?;
+        let keyring: Option<StoredKeyring> = r.get().primary(KEYRING_ID)?;
+        Ok(keyring.is_some())
+    }
+
+    fn is_locked(&self) -> Result<bool> {
+        Ok(self.is_encrypted()? && self.stored_key()?.is_none())
+    }
+
+    fn unlock(&self, passphrase: &str) -> Result<()> {
+        let db = self.connect()?;
+        let r = db.r_transaction()?;
+        let stored: Option<StoredKeyring> = r.get().primary(KEYRING_ID)?;
+        let stored = stored.ok_or_else(|| eyre!("The journal is not encrypted"))?;
+        *self.stored_key()? = Some(stored.keyring.unlock(passphrase)?);
+        Ok(())
+    }
+
+    fn lock(&self) {
+        if let Ok(mut key) = self.stored_key() {
+            *key = None;
+        }
+    }
+
+    /// Rewraps the key of an encrypted journal. A plain journal gets a fresh key, its
+    /// entries are encrypted and its full-text index is dropped, all in one transaction.
+    /// The file is then compacted, which shrinks the free space the plaintext was left in.
+    fn set_passphrase(&self, passphrase: &str) -> Result<()> {
+        let db = self.connect()?;
+        let rw = db.rw_transaction()?;
+        let old: Option<StoredKeyring> = rw.get().primary(KEYRING_ID)?;
+        let encrypting = old.is_none();
+        let key = match old {
+            Some(old) => {
+                let key = self.data_key(Some(old.clone()))?.expect("encrypted journals have a key");
+                let keyring = StoredKeyring {
+                    id: KEYRING_ID,
+                    keyring: Keyring::wrap(&key, passphrase)?,
+                };
+                rw.update(old, keyring)?;
+                key
+            }
+            None => {
+                let (keyring, key) = Keyring::create(passphrase)?;
+                let entries: Vec<Entry> = rw.scan().primary()?.all().collect();
+                for entry in entries {
+                    let sealed = Self::seal(Some(&key), &entry)?;
+                    rw.update(entry, sealed)?;
+                }
+                let revisions: Vec<Revision> = rw.scan().primary()?.all().collect();
+                for revision in revisions {
+                    let sealed = Self::seal_revision(Some(&key), &revision)?;
+                    rw.update(revision, sealed)?;
+                }
+                let trash: Vec<Trashed> = rw.scan().primary()?.all().collect();
+                for trashed in trash {
+                    let sealed = Self::seal_trashed(Some(&key), &trashed)?;
+                    rw.update(trashed, sealed)?;
+                }
+                let terms: Vec<IndexedTerm> = rw.scan().primary()?.all().collect();
+                for term in terms {
+                    rw.remove(term)?;
+                }
+                rw.insert(StoredKeyring {
+                    id: KEYRING_ID,
+                    keyring,
+                })?;
+                key
+            }
+        };
+        rw.commit()?;
+        *self.stored_key()? = Some(key);
+        drop(db);
+        if encrypting {
+            redb::Database::open(&self.path)?.compact()?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use chrono::TimeZone;
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn test_entries_are_scoped_to_their_stack() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let file = dir.path().join("native.db");
+        let db = NativeDbReader::open(&file)?;
+        for i in 1..=4 {
+            db.save_entry(&Entry::new(i % 2, format!("Entry {i}")))?;
+        }
+
+        let ids = db.get_entries(1)?.iter().map(|e| e.id).collect::<Vec<_>>();
+        assert_eq!(ids, vec![1, 3]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_migrates_entries_without_timestamps() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let file = dir.path().join("migrate.db");
+        {
+            let db = MODELS.create(&file)?;
+            let rw = db.rw_transaction()?;
+            rw.insert(EntryV1 {
+                id: 7,
+                stack_id: 1,
+                content: "captured before timestamps".to_string(),
+            })?;
+            rw.commit()?;
+        }
+
+        let db = NativeDbReader::open(&file)?;
+        let entry = db.get_entry(7)?.expect("entry should survive the migration");
+        assert_eq!(entry.content, "captured before timestamps");
+        assert_eq!(entry.created_at, entry.updated_at);
+        assert_eq!(db.get_entries(1)?.len(), 1);
+        // New ids continue after the ones that were assigned by hand.
+        assert_eq!(db.save_entry(&Entry::new(1, String::new()))?.id, 8);
+        Ok(())
+    }
+
+    #[test]
+    fn test_entries_created_between() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let file = dir.path().join("created.db");
+        let db = NativeDbReader::open(&file)?;
+        let day = |d: u32| Utc.with_ymd_and_hms(2026, 10, d, 12, 0, 0).unwrap();
+        for d in 1..=5 {
+            let mut entry = Entry::new(1, format!("Entry {d}"));
+            entry.created_at = day(d);
+            db.save_entry(&entry)?;
+        }
+
+        let ids = db
+            .get_entries_created_between(day(2), day(4))?
+            .iter()
+            .map(|e| e.id)
+            .collect::<Vec<_>>();
+        assert_eq!(ids, vec![2, 3]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_day_stacks_are_looked_up_by_day() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let file = dir.path().join("day.db");
+        let db = NativeDbReader::open(&file)?;
+        let day = NaiveDate::from_ymd_opt(2026, 10, 18).unwrap();
+        db.save_stack(&Stack::named("Inbox".to_string()))?;
+
+        let entry = db.capture("first".to_string(), day)?;
+        let again = db.capture("second".to_string(), day)?;
+
+        let stack = db.get_day_stack(day)?.expect("the day stack should have been created");
+        assert_eq!(stack.id, 2);
+        assert_eq!(stack.count, 2);
+        assert_eq!(entry.stack_id, stack.id);
+        assert_eq!(again.stack_id, stack.id);
+        Ok(())
+    }
+
+    #[test]
+    fn test_migrated_stacks_derive_their_count() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let file = dir.path().join("count.db");
+        {
+            let db = MODELS.create(&file)?;
+            let rw = db.rw_transaction()?;
+            rw.insert(StackV2 {
+                id: 1,
+                name: "Drifted".to_string(),
+                count: 255,
+                day: None,
+            })?;
+            rw.insert(StackV3 {
+                id: 2,
+                name: "Plain".to_string(),
+                day: None,
+            })?;
+            rw.commit()?;
+        }
+
+        let db = NativeDbReader::open(&file)?;
+        db.save_entry(&Entry::new(1, "only entry".to_string()))?;
+        let stack = db.get_stack(1)?;
+        assert_eq!(stack.map(|s| (s.name, s.count)), Some(("Drifted".to_string(), 1)));
+        let stack = db.get_stack(2)?;
+        assert_eq!(stack.map(|s| (s.name, s.query)), Some(("Plain".to_string(), None)));
+        Ok(())
+    }
+
+    #[test]
+    fn test_search_index_is_built_for_existing_entries() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let file = dir.path().join("search.db");
+        {
+            let db = MODELS.create(&file)?;
+            let rw = db.rw_transaction()?;
+            rw.insert(EntryV1 {
+                id: 1,
+                stack_id: 1,
+                content: "foo foobar".to_string(),
+            })?;
+            rw.commit()?;
+        }
+
+        let db = NativeDbReader::open(&file)?;
+        let terms = |term, prefix| -> Result<Vec<(String, u32)>> {
+            let postings = db.get_postings(term, prefix)?;
+            Ok(postings.into_iter().map(|p| (p.term, p.entry_id)).collect())
+        };
+        assert_eq!(terms("foo", false)?, vec![("foo".to_string(), 1)]);
+        assert_eq!(terms("foo", true)?.len(), 2);
+
+        let mut entry = db.get_entry(1)?.unwrap();
+        entry.set_content("bar".to_string());
+        db.update_entry(&entry)?;
+        db.save_entry(&Entry::new(1, "foo".to_string()))?;
+        assert_eq!(terms("foo", true)?, vec![("foo".to_string(), 2)]);
+        assert_eq!(terms("bar", false)?, vec![("bar".to_string(), 1)]);
+        assert_eq!(db.count_all_entries()?, 2);
+
+        // Reopening doesn't index twice.
+        let db = NativeDbReader::open(&file)?;
+        assert_eq!(db.get_postings("foo", false)?.len(), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_encrypted_entries() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let file = dir.path().join("encrypted.db");
+        let db = NativeDbReader::open(&file)?;
+        db.save_entry(&Entry::new(1, "meet Alice at noon".to_string()))?;
+        db.set_passphrase("first")?;
+        db.save_entry(&Entry::new(1, "Alice called".to_string()))?;
+        assert_eq!(search_ids(&db, "alice")?, vec![1, 2]);
+        let terms = [("alice".to_string(), false), ("noo".to_string(), true)];
+        let postings = db.get_postings_of(&terms)?;
+        assert_eq!(postings.iter().map(Vec::len).collect::<Vec<_>>(), vec![2, 1]);
+        let found = db.get_entries_by_ids(&[2, 7, 1])?.into_iter().map(|e| e.content).collect::<Vec<_>>();
+        assert_eq!(found, vec!["Alice called", "meet Alice at noon"]);
+
+        let raw: Vec<Entry> = db.connect()?.r_transaction()?.scan().primary()?.all().collect();
+        assert!(raw.iter().all(|e| !e.content.contains("Alice")));
+        let terms: Vec<IndexedTerm> = db.connect()?.r_transaction()?.scan().primary()?.all().collect();
+        assert_eq!(terms, Vec::new());
+
+        let db = NativeDbReader::open(&file)?;
+        assert!(db.is_locked()?);
+        assert!(db.get_entries(1).is_err());
+        assert!(db.unlock("second").is_err());
+        db.unlock("first")?;
+        db.set_passphrase("second")?;
+        db.lock();
+        assert!(db.save_entry(&Entry::new(1, "while locked".to_string())).is_err());
+
+        db.unlock("second")?;
+        let contents = db.get_entries(1)?.into_iter().map(|e| e.content).collect::<Vec<_>>();
+        assert_eq!(contents, vec!["meet Alice at noon", "Alice called"]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_trash_is_sealed_and_restored() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let file = dir.path().join("trash.db");
+        let db = NativeDbReader::open(&file)?;
+        let stack = db.save_stack(&Stack::named("ideas".to_string()))?;
+        let entry = db.save_entry(&Entry::new(stack.id, "meet Alice at noon".to_string()))?;
+        let trashed = db.trash_stack(&db.get_stack(stack.id)?.unwrap())?;
+        assert_eq!(trashed.description(), "ideas with 1 entries");
+        assert_eq!((db.get_stacks()?, search_ids(&db, "alice")?), (vec![], vec![]));
+
+        db.set_passphrase("secret")?;
+        let raw: Vec<Trashed> = db.connect()?.r_transaction()?.scan().primary()?.all().collect();
+        assert!(!raw[0].entries[0].content.contains("Alice"));
+
+        let db = NativeDbReader::open(&file)?;
+        assert!(db.get_trash().is_err());
+        db.unlock("secret")?;
+        db.restore_trashed(trashed.id)?;
+        assert_eq!(db.get_trash()?, vec![]);
+        assert_eq!(db.get_entries(stack.id)?, vec![entry]);
+        assert!(db.restore_trashed(trashed.id).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_edits_keep_sealed_revisions() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let file = dir.path().join("revisions.db");
+        let db = NativeDbReader::open(&file)?;
+        let mut entry = db.save_entry(&Entry::new(1, "draft".to_string()))?;
+        db.set_passphrase("secret")?;
+        for content in ["second draft", "final", "final"] {
+            entry.set_content(content.to_string());
+            db.update_entry(&entry)?;
+        }
+        let contents = |db: &NativeDbReader| -> Result<Vec<String>> {
+            Ok(db.get_revisions(entry.id)?.into_iter().map(|r| r.content).collect())
+        };
+        assert_eq!(contents(&db)?, vec!["draft", "second draft"]);
+        let raw: Vec<Revision> = db.connect()?.r_transaction()?.scan().primary()?.all().collect();
+        assert!(raw.iter().all(|r| !r.content.contains("draft")));
+
+        let restored = db.restore_revision(&db.get_revisions(entry.id)?[0])?;
+        assert_eq!(db.get_entry(entry.id)?, Some(restored));
+        assert_eq!(contents(&db)?, vec!["draft", "second draft", "final"]);
+        assert_eq!(db.prune_revisions(2)?, 1);
+        assert_eq!(contents(&db)?, vec!["second draft", "final"]);
+        assert_eq!(db.get_all_revisions()?, db.get_revisions(entry.id)?);
+
+        let mut dumped = Vec::new();
+        crate::dump::dump(&db, &mut dumped)?;
+        let copy_file = dir.path().join("revisions-copy.db");
+        let copy = NativeDbReader::open(&copy_file)?;
+        // The entry's stack was never saved, so it has to be there already.
+        copy.save_stack(&Stack::named("drafts".to_string()))?;
+        crate::dump::restore(&copy, crate::dump::read(dumped.as_slice())?)?;
+        assert_eq!(contents(&copy)?, vec!["second draft", "final"]);
+
+        db.delete_entry(&entry)?;
+        assert_eq!(contents(&db)?, Vec::<String>::new());
+        Ok(())
+    }
+
+    #[test]
+    fn test_trashed_entries_keep_their_history() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let file = dir.path().join("trashed-history.db");
+        let db = NativeDbReader::open(&file)?;
+        let stack = db.save_stack(&Stack::named("ideas".to_string()))?;
+        let mut entry = db.save_entry(&Entry::new(stack.id, "draft".to_string()))?;
+        db.set_passphrase("secret")?;
+        entry.set_content("final".to_string());
+        db.update_entry(&entry)?;
+
+        let trashed = db.trash_entry(&entry)?;
+        db.restore_trashed(trashed.id)?;
+        assert_eq!(db.get_revisions(entry.id)?.into_iter().map(|r| r.content).collect::<Vec<_>>(), vec!["draft"]);
+
+        let trashed = db.trash_stack(&db.get_stack(stack.id)?.unwrap())?;
+        db.restore_trashed(trashed.id)?;
+        assert_eq!(db.get_revisions(entry.id)?.len(), 1);
+
+        let trashed = db.trash_entry(&entry)?;
+        db.remove_trashed(trashed.id)?;
+        assert_eq!(db.get_revisions(entry.id)?, vec![]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_failed_batch_is_rolled_back() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let file = dir.path().join("batch.db");
+        let db = NativeDbReader::open(&file)?;
+        let stack = db.save_stack(&Stack::named("ideas".to_string()))?;
+        let first = db.save_entry(&Entry::new(stack.id, "one".to_string()))?;
+        let second = db.save_entry(&Entry::new(stack.id, "two".to_string()))?;
+        let gone = db.save_entry(&Entry::new(stack.id, "three".to_string()))?;
+        db.delete_entry(&gone)?;
+
+        assert!(db.merge_entries(&[first.clone(), second.clone(), gone]).is_err());
+        assert_eq!(db.get_entries(stack.id)?, vec![first.clone(), second]);
+        assert_eq!(db.get_revisions(first.id)?, vec![]);
+        assert_eq!(db.get_trash()?, vec![]);
+
+        db.save_stack(&Stack::smart("drafts".to_string(), "draft".to_string()))?;
+        let day = Stack::for_day(NaiveDate::from_ymd_opt(2026, 10, 18).unwrap());
+        let entries = [
+            (day.clone(), Entry::new(0, "new day".to_string())),
+            (Stack::named("drafts".to_string()), Entry::new(0, "draft".to_string())),
+        ];
+        assert!(db.save_entries_into(&entries).is_err());
+        assert_eq!(db.get_stacks()?.len(), 2);
+        assert_eq!(db.count_all_entries()?, 2);
+        let saved = db.save_entries_into(&entries[..1])?;
+        assert_eq!(db.get_entries(saved[0].stack_id)?, saved);
+        Ok(())
+    }
+
+    fn search_ids(db: &NativeDbReader, word: &str) -> Result<Vec<u32>> {
+        let mut ids = db.get_postings(word, false)?.into_iter().map(|p| p.entry_id).collect::<Vec<_>>();
+        ids.sort();
+        Ok(ids)
+    }
+
+    #[test]
+    fn test_concurrent_writers_get_distinct_ids() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let file = dir.path().join("concurrent.db");
+        let db = Arc::new(NativeDbReader::open(&file)?);
+        let day = NaiveDate::from_ymd_opt(2026, 10, 18).unwrap();
+
+        let writers = (0..4)
+            .map(|w| {
+                let db = db.clone();
+                thread::spawn(move || -> Result<()> {
+                    for i in 0..10 {
+                        db.capture(format!("writer {w} entry {i}"), day)?;
+                    }
+                    Ok(())
+                })
+            })
+            .collect::<Vec<_>>();
+        for writer in writers {
+            writer.join().expect("writer panicked")?;
+        }
+
+        let mut ids = db.get_all_entries()?.iter().map(|e| e.id).collect::<Vec<_>>();
+        ids.dedup();
+        assert_eq!(ids.len(), 40);
+        assert_eq!(db.get_stacks()?.len(), 1);
+        Ok(())
+    }
+}
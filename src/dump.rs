@@ -0,0 +1,500 @@
+//! A lossless dump of the journal as JSON Lines, independent of the database layout.
+//!
+//! The first line is a header naming the format and its version, followed by one record
+//! per line: every stack, then every entry, then the revisions of entries, then
+//! everything in the trash, each ordered by id so that dumps of the same journal diff
+//! cleanly. Records are tagged with their
+//! `type`, which leaves room for more kinds of records in later versions.
+
+use std::{
+    collections::{HashMap, HashSet},
+    io::{BufRead, Write},
+};
+
+use color_eyre::eyre::{eyre, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    db_reader::DbReader,
+    models::{entry::Entry, revision::Revision, stack::Stack, trashed::Trashed},
+};
+
+const FORMAT: &str = "terajot-dump";
+/// Bumped whenever a change to the records would make older versions misread them.
+pub const VERSION: u32 = 2;
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq)]
+struct Header {
+    format: String,
+    version: u32,
+}
+
+/// A line of a dump after the header.
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Record {
+    Stack(Stack),
+    Entry(Entry),
+    /// Since version 2.
+    Revision(Revision),
+    /// Since version 2.
+    Trashed(Trashed),
+}
+
+/// How many records a dump or restore went through.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Summary {
+    pub stacks: usize,
+    pub entries: usize,
+    pub revisions: usize,
+    pub trashed: usize,
+    /// Records that were already in the journal as they are in the dump.
+    pub unchanged: usize,
+}
+
+/// Writes every stack, entry, revision and trashed item. Entry content is written decrypted.
+pub fn dump(db: &dyn DbReader, out: &mut impl Write) -> Result<Summary> {
+    let mut stacks = db.get_stacks()?;
+    stacks.sort_by_key(|s| s.id);
+    let mut entries = db.get_all_entries()?;
+    entries.sort_by_key(|e| e.id);
+    let mut revisions = db.get_all_revisions()?;
+    revisions.sort_by_key(|r| r.id);
+    let mut trash = db.get_trash()?;
+    trash.sort_by_key(|t| t.id);
+
+    let header = Header {
+        format: FORMAT.to_string(),
+        version: VERSION,
+    };
+    writeln!(out, "{}", serde_json::to_string(&header)?)?;
+    let summary = Summary {
+        stacks: stacks.len(),
+        entries: entries.len(),
+        revisions: revisions.len(),
+        trashed: trash.len(),
+        unchanged: 0,
+    };
+    let records = stacks
+        .into_iter()
+        .map(Record::Stack)
+        .chain(entries.into_iter().map(Record::Entry))
+        .chain(revisions.into_iter().map(Record::Revision))
+        .chain(trash.into_iter().map(Record::Trashed));
+    for record in records {
+        writeln!(out, "{}", serde_json::to_string(&record)?)?;
+    }
+    Ok(summary)
+}
+
+/// Reads a whole dump, checking its header first.
+pub fn read(input: impl BufRead) -> Result<Vec<Record>> {
+    let mut lines = input.lines().enumerate().filter(|(_, line)| !matches!(line, Ok(l) if l.trim().is_empty()));
+    let header = match lines.next() {
+        Some((_, line)) => serde_json::from_str::<Header>(&line?).ok(),
+        None => None,
+    };
+    match header {
+        Some(header) if header.format == FORMAT => match header.version {
+            0 => return Err(eyre!("Unknown dump version 0")),
+            version if version > VERSION => {
+                return Err(eyre!(
+                    "The dump has version {version}, this terajot only reads up to version {VERSION}. Update terajot to restore it"
+                ))
+            }
+            _ => {}
+        },
+        _ => {
+            return Err(eyre!(
+                "Not a terajot dump, the first line should be a header like {{\"format\":\"{FORMAT}\",\"version\":{VERSION}}}"
+            ))
+        }
+    }
+
+    lines
+        .map(|(number, line)| serde_json::from_str(&line?).map_err(|e| eyre!("Line {}: {e}", number + 1)))
+        .collect()
+}
+
+/// Writes the records of a dump into the journal, so restoring the same dump again
+/// changes nothing.
+///
+/// Records keep their ids in an empty journal and in the one they were dumped from,
+/// replacing what has the same id. Into another journal, stacks are matched by day or
+/// name and entries by stack and creation time, and records that don't match anything
+/// are given fresh ids when theirs are taken. Revisions follow their entry and are matched
+/// by when they were saved. Trashed items are matched by what they hold and when they were
+/// deleted, and the ids they would be restored under are kept free.
+pub fn restore(db: &dyn DbReader, records: Vec<Record>) -> Result<Summary> {
+    let mut stacks = Vec::new();
+    let mut entries = Vec::new();
+    let mut revisions = Vec::new();
+    let mut trash = Vec::new();
+    for record in records {
+        match record {
+            Record::Stack(stack) => stacks.push(stack),
+            Record::Entry(entry) => entries.push(entry),
+            Record::Revision(revision) => revisions.push(revision),
+            Record::Trashed(trashed) => trash.push(trashed),
+        }
+    }
+
+    let mut summary = Summary::default();
+    let existing_stacks = db.get_stacks()?;
+    let existing_trash = db.get_trash()?.into_iter().map(|t| (t.id, t)).collect::<HashMap<_, _>>();
+    let mut taken = existing_stacks.iter().map(|s| s.id).collect::<HashSet<_>>();
+    let mut last = taken
+        .iter()
+        .chain(stacks.iter().map(|s| &s.id))
+        .chain(trash.iter().map(|t| &t.stack.id))
+        .chain(existing_trash.values().map(|t| &t.stack.id))
+        .copied()
+        .max()
+        .unwrap_or(0);
+    let mut last_stack = || {
+        last += 1;
+        last
+    };
+    let mut stack_ids = HashMap::new();
+    let mut changed_stacks = Vec::new();
+    for mut stack in stacks {
+        let same = |s: &&Stack| {
+            s.day == stack.day && (s.day.is_some() || (s.name == stack.name && s.is_smart() == stack.is_smart()))
+        };
+        let matching = existing_stacks
+            .iter()
+            .filter(same)
+            .min_by_key(|s| s.id != stack.id)
+            .map(|s| s.id);
+        let id = match matching {
+            Some(id) => id,
+            None if !taken.contains(&stack.id) => stack.id,
+            None => last_stack(),
+        };
+        taken.insert(id);
+        stack_ids.insert(stack.id, id);
+        stack.id = id;
+        summary.stacks += 1;
+        match existing_stacks.iter().find(|s| s.id == id) {
+            Some(existing) if Stack { count: 0, ..existing.clone() } == stack => summary.unchanged += 1,
+            _ => changed_stacks.push(stack),
+        }
+    }
+
+    let mut existing_entries = HashMap::new();
+    for entry in db.get_all_entries()? {
+        existing_entries.insert(entry.id, entry);
+    }
+    let mut by_creation = HashMap::<_, Vec<u32>>::new();
+    for entry in existing_entries.values() {
+        by_creation.entry((entry.stack_id, entry.created_at)).or_default().push(entry.id);
+    }
+    by_creation.values_mut().for_each(|ids| ids.sort_unstable());
+    let trashed_entries = |trash: &[Trashed]| trash.iter().flat_map(|t| t.entries.iter().map(|e| e.id)).collect::<Vec<_>>();
+    let mut last = existing_entries
+        .keys()
+        .chain(entries.iter().map(|e| &e.id))
+        .chain(&trashed_entries(&trash))
+        .chain(&trashed_entries(&existing_trash.values().cloned().collect::<Vec<_>>()))
+        .copied()
+        .max()
+        .unwrap_or(0);
+    // Ids given to records of the dump, which never share one.
+    let mut assigned = HashSet::new();
+    let mut changed_entries = Vec::new();
+    let mut entry_ids = HashMap::new();
+    for mut entry in entries {
+        let dumped_id = entry.id;
+        entry.stack_id = match stack_ids.get(&entry.stack_id) {
+            Some(id) => *id,
+            None if taken.contains(&entry.stack_id) => entry.stack_id,
+            None => {
+                return Err(eyre!(
+                    "Entry {} belongs to stack {}, which is neither in the dump nor in the journal",
+                    entry.id,
+                    entry.stack_id
+                ))
+            }
+        };
+        // An entry moved to another stack since the dump still has its id and creation
+        // time. Only when the id belongs to another entry is it matched by creation time.
+        let own_id = match existing_entries.get(&entry.id) {
+            _ if assigned.contains(&entry.id) => false,
+            Some(existing) => existing.created_at == entry.created_at,
+            None => true,
+        };
+        let same_creation = || {
+            let ids = by_creation.get(&(entry.stack_id, entry.created_at))?;
+            ids.iter().find(|id| !assigned.contains(*id)).copied()
+        };
+        entry.id = match same_creation() {
+            _ if own_id => entry.id,
+            Some(id) => id,
+            None => {
+                last += 1;
+                last
+            }
+        };
+        assigned.insert(entry.id);
+        entry_ids.insert(dumped_id, entry.id);
+        summary.entries += 1;
+        match existing_entries.insert(entry.id, entry.clone()) {
+            Some(existing) if existing == entry => summary.unchanged += 1,
+            _ => changed_entries.push(entry),
+        }
+    }
+
+    // Entries in the trash go back under their own ids, which nothing else may have then.
+    let mut taken_entries = existing_entries.keys().copied().collect::<HashSet<_>>();
+    taken_entries.extend(trashed_entries(&existing_trash.values().cloned().collect::<Vec<_>>()));
+    let mut last_trashed = existing_trash.keys().chain(trash.iter().map(|t| &t.id)).copied().max().unwrap_or(0);
+    // Ids of trashed items and their entries may have been moved aside by an earlier restore.
+    let same_trash = |a: &Trashed, b: &Trashed| {
+        a.deleted_at == b.deleted_at
+            && a.whole_stack == b.whole_stack
+            && (&a.stack.name, a.stack.day, &a.stack.query) == (&b.stack.name, b.stack.day, &b.stack.query)
+            && a.entries.len() == b.entries.len()
+            && a.entries.iter().zip(&b.entries).all(|(a, b)| a.created_at == b.created_at && a.content == b.content)
+    };
+    let mut matched_trash = HashSet::new();
+    let mut changed_trash = Vec::new();
+    for mut trashed in trash {
+        summary.trashed += 1;
+        let matching = existing_trash
+            .values()
+            .filter(|t| !matched_trash.contains(&t.id) && same_trash(t, &trashed))
+            .min_by_key(|t| (t.id != trashed.id, t.id));
+        if let Some(existing) = matching {
+            matched_trash.insert(existing.id);
+            entry_ids.extend(trashed.entries.iter().zip(&existing.entries).map(|(d, e)| (d.id, e.id)));
+            summary.unchanged += 1;
+            continue;
+        }
+        if existing_trash.contains_key(&trashed.id) {
+            last_trashed += 1;
+            trashed.id = last_trashed;
+        }
+        trashed.stack.id = match stack_ids.get(&trashed.stack.id) {
+            Some(id) => *id,
+            None if !taken.contains(&trashed.stack.id) => trashed.stack.id,
+            None => last_stack(),
+        };
+        if trashed.whole_stack {
+            taken.insert(trashed.stack.id);
+        }
+        for entry in &mut trashed.entries {
+            let dumped_id = entry.id;
+            entry.stack_id = trashed.stack.id;
+            if !taken_entries.insert(entry.id) {
+                last += 1;
+                entry.id = last;
+                taken_entries.insert(entry.id);
+            }
+            entry_ids.insert(dumped_id, entry.id);
+        }
+        changed_trash.push(trashed);
+    }
+
+    // Revisions follow their entry, which may be in the trash.
+    let existing_revisions = db.get_all_revisions()?;
+    let saved = existing_revisions
+        .iter()
+        .map(|r| (r.entry_id, r.saved_at, r.content.clone()))
+        .collect::<HashSet<_>>();
+    let mut revision_ids = existing_revisions.iter().map(|r| r.id).collect::<HashSet<_>>();
+    let mut last_revision = revision_ids.iter().chain(revisions.iter().map(|r| &r.id)).copied().max().unwrap_or(0);
+    let mut changed_revisions = Vec::new();
+    for mut revision in revisions {
+        revision.entry_id = match entry_ids.get(&revision.entry_id) {
+            Some(id) => *id,
+            None if taken_entries.contains(&revision.entry_id) => revision.entry_id,
+            None => {
+                return Err(eyre!(
+                    "Revision {} belongs to entry {}, which is neither in the dump nor in the journal",
+                    revision.id,
+                    revision.entry_id
+                ))
+            }
+        };
+        summary.revisions += 1;
+        if saved.contains(&(revision.entry_id, revision.saved_at, revision.content.clone())) {
+            summary.unchanged += 1;
+            continue;
+        }
+        if !revision_ids.insert(revision.id) {
+            last_revision += 1;
+            revision.id = last_revision;
+            revision_ids.insert(revision.id);
+        }
+        changed_revisions.push(revision);
+    }
+
+    db.restore(&changed_stacks, &changed_entries, &changed_revisions, &changed_trash)?;
+    Ok(summary)
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+    use crate::db_reader::MemoryDbReader;
+
+    fn sample_db() -> Result<MemoryDbReader> {
+        let db = MemoryDbReader::new();
+        let ideas = db.save_stack(&Stack::named("ideas".to_string()))?;
+        db.save_stack(&Stack::smart("drafts".to_string(), "draft".to_string()))?;
+        let day = db.get_or_create_day_stack(chrono::NaiveDate::from_ymd_opt(2026, 10, 18).unwrap())?;
+        let mut draft = db.save_entry(&Entry::new(ideas.id, "a draft".to_string()))?;
+        draft.set_content("a draft\nover two lines".to_string());
+        db.update_entry(&draft)?;
+        db.save_entry(&Entry::new(day.id, "today".to_string()))?;
+        let mut thrown = db.save_entry(&Entry::new(ideas.id, "thrown".to_string()))?;
+        thrown.set_content("thrown away".to_string());
+        db.update_entry(&thrown)?;
+        db.trash_entry(&thrown)?;
+        Ok(db)
+    }
+
+    fn dumped(db: &dyn DbReader) -> Result<String> {
+        let mut out = Vec::new();
+        dump(db, &mut out)?;
+        Ok(String::from_utf8(out)?)
+    }
+
+    #[test]
+    fn test_dump_and_restore_round_trip() -> Result<()> {
+        let db = sample_db()?;
+        let text = dumped(&db)?;
+        let lines = text.lines().collect::<Vec<_>>();
+        assert_eq!(lines[0], "{\"format\":\"terajot-dump\",\"version\":2}");
+        assert_eq!(lines[1], "{\"type\":\"stack\",\"id\":1,\"name\":\"ideas\",\"day\":null,\"query\":null}");
+        assert!(lines[6].starts_with("{\"type\":\"revision\",\"id\":1,\"entry_id\":1,\"content\":\"a draft\","));
+        assert!(lines[7].starts_with("{\"type\":\"revision\",\"id\":2,\"entry_id\":3,\"content\":\"thrown\","));
+        assert!(lines[8].starts_with("{\"type\":\"trashed\",\"id\":1,"));
+        assert_eq!(lines.len(), 9);
+
+        let restored = MemoryDbReader::new();
+        let summary = restore(&restored, read(text.as_bytes())?)?;
+        assert_eq!(
+            summary,
+            Summary {
+                stacks: 3,
+                entries: 2,
+                revisions: 2,
+                trashed: 1,
+                unchanged: 0
+            }
+        );
+        assert_eq!(dumped(&restored)?, text);
+        // Past the entry in the trash, which is restored under its own id.
+        assert_eq!(restored.save_entry(&Entry::new(1, "new".to_string()))?.id, 4);
+
+        let again = restore(&restored, read(text.as_bytes())?)?;
+        assert_eq!(again.unchanged, 8);
+        assert_eq!(restored.count_all_entries()?, 3);
+        assert_eq!(restored.get_revisions(1)?.len(), 1);
+        assert_eq!(restored.restore_trashed(1)?.entries[0].content, "thrown away");
+        assert_eq!(restored.get_entry(3)?.map(|e| e.content), Some("thrown away".to_string()));
+        assert_eq!(restored.get_revisions(3)?[0].content, "thrown");
+        Ok(())
+    }
+
+    #[test]
+    fn test_restore_into_another_journal() -> Result<()> {
+        let text = dumped(&sample_db()?)?;
+        let db = MemoryDbReader::new();
+        let day = db.get_or_create_day_stack(chrono::NaiveDate::from_ymd_opt(2026, 10, 18).unwrap())?;
+        let mine = db.save_entry(&Entry::new(day.id, "mine".to_string()))?;
+
+        // Only the day stack was already there.
+        let summary = restore(&db, read(text.as_bytes())?)?;
+        assert_eq!(summary.unchanged, 1);
+        let mut names = db.get_stacks()?.into_iter().map(|s| (s.id, s.name)).collect::<Vec<_>>();
+        names.sort();
+        assert_eq!(names, vec![(1, "2026-10-18".to_string()), (2, "drafts".to_string()), (4, "ideas".to_string())]);
+        let contents = |id| -> Result<Vec<String>> { Ok(db.get_entries(id)?.into_iter().map(|e| e.content).collect()) };
+        assert_eq!(contents(day.id)?, vec!["mine", "today"]);
+        assert_eq!(contents(4)?, vec!["a draft\nover two lines"]);
+        assert_eq!(db.get_entry(mine.id)?.map(|e| e.content), Some("mine".to_string()));
+
+        let again = restore(&db, read(text.as_bytes())?)?;
+        assert_eq!(again.unchanged, 8);
+        assert_eq!(db.count_all_entries()?, 3);
+        let history = db.get_revisions(4)?.into_iter().map(|r| r.content).collect::<Vec<_>>();
+        assert_eq!(history, vec!["a draft"]);
+
+        // The trashed entry goes back to the stack it was restored into.
+        let trashed = db.get_trash()?.pop().unwrap();
+        assert_eq!(trashed.stack.id, 4);
+        let thrown = db.restore_trashed(trashed.id)?.entries.pop().unwrap();
+        assert!(contents(4)?.contains(&"thrown away".to_string()));
+        assert_eq!(db.get_revisions(thrown.id)?[0].content, "thrown");
+        assert_eq!(db.count_all_entries()?, 4);
+        Ok(())
+    }
+
+    #[test]
+    fn test_restoring_twice_keeps_colliding_trash_once() -> Result<()> {
+        let text = dumped(&sample_db()?)?;
+        let db = MemoryDbReader::new();
+        let stack = db.save_stack(&Stack::named("notes".to_string()))?;
+        let mine = db.save_entry(&Entry::new(stack.id, "mine".to_string()))?;
+        assert_eq!(db.trash_entry(&mine)?.id, 1);
+
+        let summary = restore(&db, read(text.as_bytes())?)?;
+        assert_eq!(summary.unchanged, 0);
+        assert_eq!(db.get_trash()?.len(), 2);
+
+        let again = restore(&db, read(text.as_bytes())?)?;
+        assert_eq!(again.unchanged, 8);
+        assert_eq!(db.get_trash()?.len(), 2);
+        assert_eq!(db.get_all_revisions()?.len(), 2);
+        Ok(())
+    }
+
+    #[test]
+    fn test_entries_created_at_once_keep_their_ids() -> Result<()> {
+        let db = MemoryDbReader::new();
+        let stack = db.save_stack(&Stack::named("bulk".to_string()))?;
+        let created_at = chrono::Utc::now();
+        for i in 0..5 {
+            db.save_entry(&Entry {
+                created_at,
+                ..Entry::new(stack.id, format!("entry {i}"))
+            })?;
+        }
+        let text = dumped(&db)?;
+
+        let restored = MemoryDbReader::new();
+        restore(&restored, read(text.as_bytes())?)?;
+        assert_eq!(restored.count_all_entries()?, 5);
+        assert_eq!(dumped(&restored)?, text);
+
+        let again = restore(&restored, read(text.as_bytes())?)?;
+        assert_eq!(again.unchanged, 6);
+        assert_eq!(restored.count_all_entries()?, 5);
+
+        // Into a journal where the ids belong to other entries, each record still gets its own.
+        let other = MemoryDbReader::new();
+        let mine = other.save_stack(&Stack::named("mine".to_string()))?;
+        for i in 0..5 {
+            other.save_entry(&Entry::new(mine.id, format!("mine {i}")))?;
+        }
+        restore(&other, read(text.as_bytes())?)?;
+        assert_eq!(other.count_all_entries()?, 10);
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_checks_the_version() {
+        let error = |text: &str| read(text.as_bytes()).err().map(|e| e.to_string()).unwrap_or_default();
+        assert!(error("{\"id\":1}\n").starts_with("Not a terajot dump"));
+        assert!(error("{\"format\":\"terajot-dump\",\"version\":3}\n").starts_with("The dump has version 3"));
+        assert_eq!(
+            error("{\"format\":\"terajot-dump\",\"version\":1}\n{\"type\":\"tag\"}\n"),
+            "Line 2: unknown variant `tag`, expected one of `stack`, `entry`, `revision`, `trashed` at line 1 column 13"
+        );
+        assert!(read("{\"format\":\"terajot-dump\",\"version\":1}\n".as_bytes()).is_ok());
+    }
+}
@@ -0,0 +1,207 @@
+use std::{
+    collections::HashSet,
+    fs,
+    path::Path,
+};
+
+use chrono::{Local, NaiveDate, SecondsFormat};
+use color_eyre::eyre::{eyre, Result};
+
+use crate::{
+    calendar::Calendar,
+    db_reader::DbReader,
+    models::{entry::Entry, stack::Stack},
+};
+
+/// Names Windows refuses as file names, whatever the extension.
+const RESERVED_NAMES: [&str; 22] = [
+    "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8", "COM9", "LPT1",
+    "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+const MAX_NAME_LENGTH: usize = 100;
+
+/// Renders a stack as a single Markdown document, with a heading for every day and
+/// the entries in the order they were captured.
+pub fn stack_to_markdown(stack: &Stack, entries: &[Entry], calendar: &Calendar) -> String {
+    let mut entries = entries.iter().collect::<Vec<_>>();
+    entries.sort_by_key(|e| e.created_key());
+
+    let mut markdown = format!("# {}\n", stack.name);
+    let mut current_day: Option<NaiveDate> = None;
+    for entry in entries {
+        let day = calendar.day_of(entry.created_at);
+        if current_day != Some(day) {
+            markdown.push_str(&format!("\n## {}\n", day.format(Stack::DAY_FORMAT)));
+            current_day = Some(day);
+        }
+        let time = entry.created_at.with_timezone(&Local).format("%H:%M");
+        markdown.push_str(&format!("\n### {time}\n\n{}\n", entry.content.trim_end()));
+    }
+    markdown
+}
+
+/// Renders an entry as a Markdown document with YAML front matter.
+pub fn entry_to_markdown(stack: &Stack, entry: &Entry) -> String {
+    let timestamp = |at: chrono::DateTime<chrono::Utc>| at.to_rfc3339_opts(SecondsFormat::Secs, true);
+    format!(
+        "---\nid: {}\nstack: {}\ncreated_at: {}\nupdated_at: {}\n---\n\n{}\n",
+        entry.id,
+        // A JSON string is a valid double-quoted YAML scalar.
+        serde_json::Value::from(stack.name.as_str()),
+        timestamp(entry.created_at),
+        timestamp(entry.updated_at),
+        entry.content.trim_end(),
+    )
+}
+
+/// What `export_markdown` wrote.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct ExportSummary {
+    pub stacks: usize,
+    pub entries: usize,
+}
+
+/// Writes the whole journal as Markdown under `dir`: a folder per stack holding a file
+/// per entry, or with `single_file` a file per stack with a heading for every day.
+///
+/// Names only depend on the stack name and the entry id and creation time, so exporting
+/// again overwrites the same files. Smart stacks are skipped since their entries are
+/// already exported with the stacks they belong to.
+pub fn export_markdown(db: &dyn DbReader, calendar: &Calendar, dir: &Path, single_file: bool) -> Result<ExportSummary> {
+    fs::create_dir_all(dir).map_err(|e| eyre!("Could not create {}: {e}", dir.display()))?;
+    let mut stacks = db.get_stacks()?;
+    stacks.retain(|s| !s.is_smart());
+    stacks.sort_by_key(|s| s.id);
+
+    let mut summary = ExportSummary::default();
+    let mut taken = HashSet::new();
+    for stack in stacks {
+        // The oldest stack keeps the plain name when two names are cleaned up the same way.
+        let mut name = safe_file_name(&stack.name);
+        if !taken.insert(name.to_lowercase()) {
+            name = format!("{name}-{}", stack.id);
+            taken.insert(name.to_lowercase());
+        }
+
+        let entries = db.get_entries(stack.id)?;
+        if single_file {
+            write(&dir.join(format!("{name}.md")), &stack_to_markdown(&stack, &entries, calendar))?;
+        } else {
+            let folder = dir.join(&name);
+            fs::create_dir_all(&folder).map_err(|e| eyre!("Could not create {}: {e}", folder.display()))?;
+            for entry in &entries {
+                write(&folder.join(entry_file_name(entry)), &entry_to_markdown(&stack, entry))?;
+            }
+        }
+        summary.stacks += 1;
+        summary.entries += entries.len();
+    }
+    Ok(summary)
+}
+
+/// Names entry files by creation time in UTC, so that they sort chronologically and
+/// don't change with the timezone, followed by the id to keep them unique.
+fn entry_file_name(entry: &Entry) -> String {
+    format!("{}-{}.md", entry.created_at.format("%Y-%m-%dT%H%MZ"), entry.id)
+}
+
+/// Turns a stack name into something every common filesystem accepts, keeping it
+/// readable: separators and other special characters become `-`.
+fn safe_file_name(name: &str) -> String {
+    let mut safe = String::new();
+    for c in name.chars() {
+        let c = match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '-',
+            c if c.is_whitespace() || c.is_control() => '-',
+            c => c,
+        };
+        if !(c == '-' && safe.ends_with('-')) {
+            safe.push(c);
+        }
+    }
+    let mut safe = safe.trim_matches(['-', '.']).chars().take(MAX_NAME_LENGTH).collect::<String>();
+    if safe.is_empty() {
+        safe = "stack".to_string();
+    }
+    if RESERVED_NAMES.contains(&safe.to_uppercase().as_str()) {
+        safe.push('-');
+    }
+    safe
+}
+
+fn write(path: &Path, content: &str) -> Result<()> {
+    fs::write(path, content).map_err(|e| eyre!("Could not write {}: {e}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{TimeZone, Utc};
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+    use crate::{config::JournalConfig, db_reader::MemoryDbReader};
+
+    #[test]
+    fn test_stack_to_markdown_groups_by_day() -> color_eyre::eyre::Result<()> {
+        let calendar = Calendar::new(&JournalConfig {
+            day_rollover_hour: 0,
+            timezone: Some("UTC".to_string()),
+            ..Default::default()
+        })?;
+        let entry = |id, day, content: &str| Entry {
+            id,
+            created_at: Utc.with_ymd_and_hms(2026, 10, day, 12, 0, 0).unwrap(),
+            ..Entry::new(1, content.to_string())
+        };
+        let entries = vec![entry(3, 18, "third"), entry(1, 17, "first\n"), entry(2, 17, "second")];
+
+        let markdown = stack_to_markdown(&Stack::named("ideas".to_string()), &entries, &calendar);
+        let headings = markdown.lines().filter(|l| l.starts_with('#')).filter(|l| !l.starts_with("###"));
+        assert_eq!(headings.collect::<Vec<_>>(), vec!["# ideas", "## 2026-10-17", "## 2026-10-18"]);
+        let contents = markdown.lines().filter(|l| !l.is_empty() && !l.starts_with('#'));
+        assert_eq!(contents.collect::<Vec<_>>(), vec!["first", "second", "third"]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_safe_file_name() {
+        assert_eq!(safe_file_name("2026-10-18"), "2026-10-18");
+        assert_eq!(safe_file_name("work / clients: Acme?"), "work-clients-Acme");
+        assert_eq!(safe_file_name("../.."), "stack");
+        assert_eq!(safe_file_name("con"), "con-");
+        assert_eq!(safe_file_name("idées"), "idées");
+    }
+
+    #[test]
+    fn test_export_markdown() -> Result<()> {
+        let db = MemoryDbReader::new();
+        let work = db.save_stack(&Stack::named("work/acme".to_string()))?;
+        let other = db.save_stack(&Stack::named("work:acme".to_string()))?;
+        db.save_stack(&Stack::smart("all".to_string(), "invoice".to_string()))?;
+        let entry = db.save_entry(&Entry {
+            created_at: Utc.with_ymd_and_hms(2026, 10, 18, 9, 5, 0).unwrap(),
+            ..Entry::new(work.id, "invoice \"42\"\n".to_string())
+        })?;
+        db.save_entry(&Entry::new(other.id, "other".to_string()))?;
+
+        let dir = tempfile::tempdir()?;
+        let calendar = Calendar::default();
+        let summary = export_markdown(&db, &calendar, dir.path(), false)?;
+        assert_eq!(summary, ExportSummary { stacks: 2, entries: 2 });
+        let markdown = fs::read_to_string(dir.path().join("work-acme").join("2026-10-18T0905Z-1.md"))?;
+        let updated = entry.updated_at.to_rfc3339_opts(SecondsFormat::Secs, true);
+        assert_eq!(
+            markdown,
+            format!(
+                "---\nid: 1\nstack: \"work/acme\"\ncreated_at: 2026-10-18T09:05:00Z\nupdated_at: {updated}\n---\n\ninvoice \"42\"\n"
+            )
+        );
+        assert_eq!(fs::read_dir(dir.path().join("work-acme-2"))?.count(), 1);
+
+        export_markdown(&db, &calendar, dir.path(), true)?;
+        assert!(fs::read_to_string(dir.path().join("work-acme.md"))?.starts_with("# work/acme\n"));
+        assert!(dir.path().join("work-acme-2.md").exists());
+        assert!(!dir.path().join("all.md").exists());
+        Ok(())
+    }
+}
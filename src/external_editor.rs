@@ -0,0 +1,104 @@
+use std::{env, fs, path::Path, process::Command};
+
+use color_eyre::eyre::{eyre, Result};
+
+use crate::utils::get_data_dir;
+
+/// Editor used when neither `$VISUAL` nor `$EDITOR` is set.
+const FALLBACK_EDITOR: &str = "vi";
+
+/// Lets the user edit `content` in `$VISUAL` or `$EDITOR` and returns the edited text.
+///
+/// This blocks until the editor exits, so the terminal has to be released first. The
+/// content is only ever written inside the data directory, readable by the user alone.
+pub fn edit(content: &str) -> Result<String> {
+    let command = editor_command(env::var("VISUAL").ok(), env::var("EDITOR").ok())?;
+    edit_with(&command, content, &get_data_dir().join("edits"))
+}
+
+/// Splits the configured editor into a program and its arguments, so values like
+/// `code --wait` work. Empty variables are skipped like unset ones.
+fn editor_command(visual: Option<String>, editor: Option<String>) -> Result<Vec<String>> {
+    let configured = [visual, editor].into_iter().flatten().find(|c| !c.trim().is_empty());
+    let command = shell_words::split(configured.as_deref().unwrap_or(FALLBACK_EDITOR))?;
+    if command.is_empty() {
+        return Err(eyre!("No editor is configured"));
+    }
+    Ok(command)
+}
+
+/// Writes `content` to a file in a fresh private directory under `parent`, so swap and
+/// backup files the editor leaves next to it are removed along with it.
+fn edit_with(command: &[String], content: &str, parent: &Path) -> Result<String> {
+    fs::create_dir_all(parent)?;
+    let dir = tempfile::Builder::new().prefix("edit-").tempdir_in(parent)?;
+    let mut builder = tempfile::Builder::new();
+    builder.prefix("terajot-").suffix(".md");
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(dir.path(), fs::Permissions::from_mode(0o700))?;
+        builder.permissions(fs::Permissions::from_mode(0o600));
+    }
+    let file = builder.tempfile_in(dir.path())?;
+    fs::write(file.path(), content)?;
+
+    let status = Command::new(&command[0]).args(&command[1..]).arg(file.path()).status()?;
+    if !status.success() {
+        return Err(eyre!("{} exited with {status}", command[0]));
+    }
+
+    let mut edited = fs::read_to_string(file.path())?;
+    // Most editors end the file with a newline the entry didn't have.
+    if !content.ends_with('\n') && edited.ends_with('\n') {
+        edited.pop();
+    }
+    file.close()?;
+    dir.close()?;
+    Ok(edited)
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn test_editor_command_prefers_visual() -> Result<()> {
+        let command = editor_command(Some("code --wait".to_string()), Some("vim".to_string()))?;
+        assert_eq!(command, vec!["code", "--wait"]);
+        assert_eq!(editor_command(Some(" ".to_string()), Some("hx".to_string()))?, vec!["hx"]);
+        assert_eq!(editor_command(None, None)?, vec![FALLBACK_EDITOR]);
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_edit_with_reads_back_changes() -> Result<()> {
+        let parent = tempfile::tempdir()?;
+        let command = shell_words::split(r#"sh -c 'echo " and more" >> "$1"' sh"#)?;
+        assert_eq!(edit_with(&command, "some text", parent.path())?, "some text and more");
+        assert_eq!(fs::read_dir(parent.path())?.count(), 0);
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_edit_with_keeps_the_file_private() -> Result<()> {
+        let parent = tempfile::tempdir()?;
+        let command = shell_words::split(r#"sh -c 'stat -c %a "$1" "$(dirname "$1")" > "$1"' sh"#)?;
+        assert_eq!(edit_with(&command, "secret", parent.path())?, "600\n700");
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_edit_with_failing_editor() -> Result<()> {
+        let command = vec!["false".to_string()];
+        let parent = tempfile::tempdir()?;
+        assert!(edit_with(&command, "some text", parent.path()).is_err());
+        assert_eq!(fs::read_dir(parent.path())?.count(), 0);
+        Ok(())
+    }
+}
@@ -0,0 +1,447 @@
+//! Importing journals kept as plain-text and Markdown files.
+//!
+//! Every directory becomes a stack, named after its path relative to the imported
+//! directory, and every file an entry, or several when it is split. Front matter such as
+//! the one written by `terajot export markdown` provides the stack and the dates;
+//! otherwise entries are dated by the modification time of their file.
+
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    path::{Path, PathBuf},
+};
+
+use chrono::{DateTime, Duration, NaiveDate, NaiveDateTime, Utc};
+use color_eyre::eyre::{eyre, Result};
+use lazy_static::lazy_static;
+use regex::Regex;
+
+use crate::{
+    calendar::Calendar,
+    db_reader::DbReader,
+    models::{entry::Entry, stack::Stack},
+};
+
+const EXTENSIONS: [&str; 4] = ["md", "markdown", "txt", "text"];
+const DATE_TIME_FORMATS: [&str; 4] = ["%Y-%m-%d %H:%M:%S", "%Y-%m-%dT%H:%M:%S", "%Y-%m-%d %H:%M", "%Y-%m-%dT%H:%M"];
+
+lazy_static! {
+    /// A heading starting with a day, optionally followed by a time and a title.
+    static ref DATED_HEADING: Regex =
+        Regex::new(r"^#{1,6}\s+(\d{4}-\d{2}-\d{2})(?:[ T](\d{1,2}:\d{2}))?\s*(.*)$").unwrap();
+    /// A heading that is only a time, dating entries under the last dated heading.
+    static ref TIME_HEADING: Regex = Regex::new(r"^#{1,6}\s+(\d{1,2}:\d{2})\s*$").unwrap();
+}
+
+/// How files are read.
+#[derive(Debug, Clone, Default)]
+pub struct ImportOptions {
+    /// Puts everything into this stack, regardless of directories and front matter.
+    pub stack: Option<String>,
+    /// Splits files into several entries on lines equal to it.
+    pub separator: Option<String>,
+    /// Splits files into several entries on headings starting with a day.
+    pub dated_headings: bool,
+}
+
+/// An entry found while reading the files.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FoundEntry {
+    pub stack: String,
+    pub source: PathBuf,
+    pub content: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    /// Whether the stack already has an entry with the same content.
+    pub duplicate: bool,
+}
+
+/// What importing a path would do, worked out before anything is written so that a dry
+/// run shows exactly what a real one does.
+#[derive(Debug, Default)]
+pub struct ImportPlan {
+    pub entries: Vec<FoundEntry>,
+    /// Files that were not imported because they are not text or Markdown.
+    pub ignored: Vec<PathBuf>,
+}
+
+impl ImportPlan {
+    /// Reads the files under `path`, checking them against the entries already in the journal.
+    pub fn new(db: &dyn DbReader, calendar: &Calendar, path: &Path, options: &ImportOptions) -> Result<Self> {
+        let mut plan = Self::default();
+        let root = fs::canonicalize(path).map_err(|e| eyre!("Could not read {}: {e}", path.display()))?;
+        if root.is_dir() {
+            plan.read_dir(calendar, &root, "", &file_name(&root), options)?;
+        } else {
+            let name = root.parent().map(file_name).unwrap_or_else(|| "imported".to_string());
+            plan.read_file(calendar, &root, &name, options)?;
+        }
+
+        let mut contents: HashMap<String, HashSet<String>> = HashMap::new();
+        for entry in &mut plan.entries {
+            if !contents.contains_key(&entry.stack) {
+                contents.insert(entry.stack.clone(), existing_contents(db, &entry.stack)?);
+            }
+            let stack = contents.get_mut(&entry.stack).expect("it was just inserted");
+            entry.duplicate = !stack.insert(entry.content.clone());
+        }
+        Ok(plan)
+    }
+
+    /// Saves the entries that are not duplicates in a single write, creating their stacks
+    /// on demand.
+    pub fn apply(&self, db: &dyn DbReader) -> Result<()> {
+        let entries = self
+            .new_entries()
+            .map(|found| {
+                let stack = match day_of_stack(&found.stack) {
+                    Some(day) => Stack::for_day(day),
+                    None => Stack::named(found.stack.clone()),
+                };
+                let entry = Entry {
+                    created_at: found.created_at,
+                    updated_at: found.updated_at,
+                    ..Entry::new(0, found.content.clone())
+                };
+                (stack, entry)
+            })
+            .collect::<Vec<_>>();
+        db.save_entries_into(&entries)?;
+        Ok(())
+    }
+
+    /// Gets the entries that are not in the journal yet.
+    pub fn new_entries(&self) -> impl Iterator<Item = &FoundEntry> {
+        self.entries.iter().filter(|e| !e.duplicate)
+    }
+
+    /// Counts the stacks receiving new entries.
+    pub fn stack_count(&self) -> usize {
+        self.new_entries().map(|e| &e.stack).collect::<HashSet<_>>().len()
+    }
+
+    /// Reads a directory, whose files go into the stack named `relative`, its path
+    /// relative to the imported directory, or `root` for the imported directory itself.
+    fn read_dir(
+        &mut self,
+        calendar: &Calendar,
+        dir: &Path,
+        relative: &str,
+        root: &str,
+        options: &ImportOptions,
+    ) -> Result<()> {
+        let mut paths = fs::read_dir(dir)
+            .map_err(|e| eyre!("Could not read {}: {e}", dir.display()))?
+            .map(|entry| entry.map(|e| e.path()))
+            .collect::<Result<Vec<_>, _>>()?;
+        paths.sort();
+        for path in paths {
+            let name = file_name(&path);
+            if name.starts_with('.') {
+                continue;
+            }
+            if path.is_dir() {
+                let relative = match relative {
+                    "" => name,
+                    _ => format!("{relative}/{name}"),
+                };
+                self.read_dir(calendar, &path, &relative, root, options)?;
+            } else {
+                let stack = if relative.is_empty() { root } else { relative };
+                self.read_file(calendar, &path, stack, options)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn read_file(&mut self, calendar: &Calendar, path: &Path, stack: &str, options: &ImportOptions) -> Result<()> {
+        let extension = path.extension().and_then(|e| e.to_str()).unwrap_or_default().to_lowercase();
+        if !EXTENSIONS.contains(&extension.as_str()) {
+            self.ignored.push(path.to_path_buf());
+            return Ok(());
+        }
+        let text = fs::read_to_string(path).map_err(|e| eyre!("Could not read {}: {e}", path.display()))?;
+        let modified = fs::metadata(path)?.modified().map(DateTime::<Utc>::from).unwrap_or_else(|_| Utc::now());
+
+        let (front_matter, body) = split_front_matter(&text);
+        let date = |keys: &[&str]| -> Result<Option<DateTime<Utc>>> {
+            match keys.iter().find_map(|key| front_matter.get(*key)) {
+                Some(value) => parse_date(value, calendar)
+                    .map(Some)
+                    .ok_or_else(|| eyre!("{}: can't read the date `{value}`", path.display())),
+                None => Ok(None),
+            }
+        };
+        let created_at = date(&["created_at", "created", "date"])?.unwrap_or(modified);
+        let updated_at = date(&["updated_at", "updated", "modified"])?.unwrap_or(created_at);
+        let stack = match (&options.stack, front_matter.get("stack")) {
+            (Some(stack), _) | (None, Some(stack)) => stack.clone(),
+            (None, None) => stack.to_string(),
+        };
+
+        let chunks = match &options.separator {
+            Some(separator) => split_on_separator(body, separator),
+            None => vec![body.to_string()],
+        };
+        // Entries sharing the date of their file are a millisecond apart, so they keep the
+        // order they had in it.
+        let mut offset = 0;
+        for chunk in chunks {
+            let parts = match options.dated_headings {
+                true => split_on_dated_headings(&chunk, calendar),
+                false => vec![(None, chunk)],
+            };
+            for (dated, content) in parts {
+                let content = content.trim_start_matches(['\n', '\r']).trim_end();
+                if content.trim().is_empty() {
+                    continue;
+                }
+                let (created_at, updated_at) = match dated {
+                    Some(dated) => (dated, dated),
+                    None => {
+                        let shift = Duration::try_milliseconds(offset).expect("a few milliseconds fit in a duration");
+                        offset += 1;
+                        (created_at + shift, updated_at + shift)
+                    }
+                };
+                self.entries.push(FoundEntry {
+                    stack: stack.clone(),
+                    source: path.to_path_buf(),
+                    content: content.to_string(),
+                    created_at,
+                    updated_at,
+                    duplicate: false,
+                });
+            }
+        }
+        Ok(())
+    }
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default()
+}
+
+/// Gets the day of a day stack from its name.
+fn day_of_stack(name: &str) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(name, Stack::DAY_FORMAT).ok()
+}
+
+fn existing_contents(db: &dyn DbReader, name: &str) -> Result<HashSet<String>> {
+    let stack = match day_of_stack(name) {
+        Some(day) => db.get_day_stack(day)?,
+        None => db.get_stack_by_name(name)?,
+    };
+    match stack {
+        Some(stack) if stack.is_smart() => Err(eyre!("{name} is a smart stack, entries can't be added to it")),
+        Some(stack) => Ok(db.get_entries(stack.id)?.into_iter().map(|e| e.content.trim_end().to_string()).collect()),
+        None => Ok(HashSet::new()),
+    }
+}
+
+/// Separates YAML front matter from the rest of a file. Only `key: value` lines are
+/// read, which covers the dates and names journals keep there. A leading block with
+/// any other line is content, e.g. entries split on `---`.
+fn split_front_matter(text: &str) -> (HashMap<String, String>, &str) {
+    let mut front_matter = HashMap::new();
+    let Some(rest) = text.strip_prefix("---\n").or_else(|| text.strip_prefix("---\r\n")) else {
+        return (front_matter, text);
+    };
+    let mut offset = 0;
+    for line in rest.split_inclusive('\n') {
+        offset += line.len();
+        let line = line.trim_end();
+        if line == "---" || line == "..." {
+            return (front_matter, &rest[offset..]);
+        }
+        if line.trim().is_empty() {
+            continue;
+        }
+        match line.split_once(':') {
+            Some((key, value)) if !key.trim().is_empty() => {
+                front_matter.insert(key.trim().to_string(), unquote(value.trim()));
+            }
+            _ => break,
+        }
+    }
+    (HashMap::new(), text)
+}
+
+fn unquote(value: &str) -> String {
+    if value.len() >= 2 && value.starts_with('"') && value.ends_with('"') {
+        serde_json::from_str(value).unwrap_or_else(|_| value[1..value.len() - 1].to_string())
+    } else if value.len() >= 2 && value.starts_with('\'') && value.ends_with('\'') {
+        value[1..value.len() - 1].replace("''", "'")
+    } else {
+        value.to_string()
+    }
+}
+
+/// Reads a moment written in RFC 3339, as a local date and time, or as a day.
+fn parse_date(value: &str, calendar: &Calendar) -> Option<DateTime<Utc>> {
+    if let Ok(at) = DateTime::parse_from_rfc3339(value) {
+        return Some(at.with_timezone(&Utc));
+    }
+    if let Some(at) = DATE_TIME_FORMATS.iter().find_map(|f| NaiveDateTime::parse_from_str(value, f).ok()) {
+        return Some(calendar.from_local(at));
+    }
+    NaiveDate::parse_from_str(value, Stack::DAY_FORMAT).ok().map(|day| calendar.start_of(day))
+}
+
+fn split_on_separator(text: &str, separator: &str) -> Vec<String> {
+    let mut chunks = vec![String::new()];
+    for line in text.split_inclusive('\n') {
+        if line.trim_end() == separator {
+            chunks.push(String::new());
+        } else {
+            chunks.last_mut().expect("there is always a chunk").push_str(line);
+        }
+    }
+    chunks
+}
+
+/// Splits text at headings starting with a day, and at headings that are only a time
+/// once a day is known. Headings that are only a date are dropped, others are kept as
+/// the first line of their entry. Text before the first dated heading is kept undated,
+/// unless it is only headings, like the title of a file exported with `--single-file`.
+fn split_on_dated_headings(text: &str, calendar: &Calendar) -> Vec<(Option<DateTime<Utc>>, String)> {
+    let mut parts = vec![(None, String::new())];
+    let mut day = None;
+    for line in text.split_inclusive('\n') {
+        let heading = line.trim_end();
+        let (date, time, title) = if let Some(captures) = DATED_HEADING.captures(heading) {
+            let Ok(date) = NaiveDate::parse_from_str(&captures[1], Stack::DAY_FORMAT) else {
+                parts.last_mut().expect("there is always a part").1.push_str(line);
+                continue;
+            };
+            (date, captures.get(2).map(|t| t.as_str().to_string()), captures[3].to_string())
+        } else if let (Some(captures), Some(day)) = (TIME_HEADING.captures(heading), day) {
+            (day, Some(captures[1].to_string()), String::new())
+        } else {
+            parts.last_mut().expect("there is always a part").1.push_str(line);
+            continue;
+        };
+
+        day = Some(date);
+        let at = time
+            .and_then(|t| chrono::NaiveTime::parse_from_str(&t, "%H:%M").ok())
+            .map(|t| calendar.from_local(date.and_time(t)))
+            .unwrap_or_else(|| calendar.start_of(date));
+        let content = if title.is_empty() { String::new() } else { line.to_string() };
+        parts.push((Some(at), content));
+    }
+
+    let preamble = &parts[0].1;
+    if preamble.lines().all(|l| l.trim().is_empty() || l.starts_with('#')) {
+        parts.remove(0);
+    }
+    parts
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+    use crate::{config::JournalConfig, db_reader::MemoryDbReader};
+
+    fn utc_calendar() -> Result<Calendar> {
+        Calendar::new(&JournalConfig {
+            timezone: Some("UTC".to_string()),
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn test_split_on_dated_headings() -> Result<()> {
+        let text = "# ideas\n\n## 2026-10-17\n\n### 09:30\n\nfirst\n\n### 18:00\n\nsecond\n\n## 2026-10-18 Rome\n\nthird\n";
+        let parts = split_on_dated_headings(text, &utc_calendar()?)
+            .into_iter()
+            .map(|(at, content)| (at.map(|a| a.to_rfc3339()), content.trim().to_string()))
+            .filter(|(_, content)| !content.is_empty())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            parts,
+            vec![
+                (Some("2026-10-17T09:30:00+00:00".to_string()), "first".to_string()),
+                (Some("2026-10-17T18:00:00+00:00".to_string()), "second".to_string()),
+                (Some("2026-10-18T00:00:00+00:00".to_string()), "## 2026-10-18 Rome\n\nthird".to_string()),
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_split_front_matter() {
+        let (front_matter, body) = split_front_matter("---\ntitle: Rome\n\ndate: 2019-03-04\n---\nbody\n");
+        assert_eq!(front_matter.get("title").map(String::as_str), Some("Rome"));
+        assert_eq!(body, "body\n");
+
+        let text = "---\nfirst entry\n---\nsecond entry\n";
+        let (front_matter, body) = split_front_matter(text);
+        assert!(front_matter.is_empty());
+        assert_eq!(body, text);
+    }
+
+    #[test]
+    fn test_chunks_of_a_file_keep_their_order() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        fs::write(dir.path().join("notes.txt"), "---\nfirst\n---\nsecond\n---\nthird\n")?;
+        let options = ImportOptions {
+            separator: Some("---".to_string()),
+            ..Default::default()
+        };
+        let plan = ImportPlan::new(&MemoryDbReader::new(), &utc_calendar()?, dir.path(), &options)?;
+        let found = plan.entries.iter().map(|e| e.content.as_str()).collect::<Vec<_>>();
+        assert_eq!(found, vec!["first", "second", "third"]);
+        assert!(plan.entries.windows(2).all(|w| w[0].created_at < w[1].created_at));
+        Ok(())
+    }
+
+    #[test]
+    fn test_import_directories_with_front_matter() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let journal = dir.path().join("journal");
+        fs::create_dir_all(journal.join("travel"))?;
+        fs::write(journal.join("loose.txt"), "a loose thought\n")?;
+        fs::write(journal.join("photo.jpg"), "")?;
+        fs::write(
+            journal.join("travel").join("rome.md"),
+            "---\ncreated_at: 2019-03-04 10:00\nstack: \"trips/italy\"\n---\n\nday one\n---\nday two\n",
+        )?;
+
+        let db = MemoryDbReader::new();
+        let calendar = utc_calendar()?;
+        let options = ImportOptions {
+            separator: Some("---".to_string()),
+            ..Default::default()
+        };
+        let plan = ImportPlan::new(&db, &calendar, &journal, &options)?;
+        let found = plan.entries.iter().map(|e| (e.stack.as_str(), e.content.as_str())).collect::<Vec<_>>();
+        assert_eq!(found, vec![("journal", "a loose thought"), ("trips/italy", "day one"), ("trips/italy", "day two")]);
+        assert_eq!(plan.entries[1].created_at, Utc.with_ymd_and_hms(2019, 3, 4, 10, 0, 0).unwrap());
+        assert_eq!(plan.ignored, vec![journal.join("photo.jpg")]);
+
+        plan.apply(&db)?;
+        assert_eq!(db.count_all_entries()?, 3);
+        let again = ImportPlan::new(&db, &calendar, &journal, &options)?;
+        assert!(again.entries.iter().all(|e| e.duplicate));
+        again.apply(&db)?;
+        assert_eq!(db.count_all_entries()?, 3);
+
+        // Nothing is written when one of the stacks can't take entries.
+        fs::write(journal.join("travel").join("paris.md"), "---
+stack: drafts
+---
+day three
+")?;
+        fs::write(journal.join("later.txt"), "a later thought
+")?;
+        let failing = ImportPlan::new(&db, &calendar, &journal, &options)?;
+        db.save_stack(&Stack::smart("drafts".to_string(), "draft".to_string()))?;
+        assert!(failing.apply(&db).is_err());
+        assert_eq!(db.count_all_entries()?, 3);
+        Ok(())
+    }
+}
@@ -3,15 +3,25 @@
 #![allow(unused_variables)]
 
 pub mod action;
+pub mod backup;
 pub mod app;
+pub mod calendar;
 pub mod cli;
+pub mod commands;
 pub mod components;
 pub mod config;
+pub mod crypto;
 pub mod mode;
+pub mod query;
+pub mod search;
 pub mod tui;
 pub mod utils;
 pub mod models;
 pub mod db_reader;
+pub mod dump;
+pub mod export;
+pub mod external_editor;
+pub mod import;
 
 use clap::Parser;
 use cli::Cli;
@@ -28,6 +38,10 @@ async fn tokio_main() -> Result<()> {
   initialize_panic_handler()?;
 
   let args = Cli::parse();
+  if let Some(command) = args.command {
+    return commands::run(command, args.passphrase_fd);
+  }
+
   let mut app = App::new(args.tick_rate, args.frame_rate)?;
   app.run().await?;
 
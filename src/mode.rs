@@ -4,4 +4,6 @@ use serde::{Deserialize, Serialize};
 pub enum Mode {
   #[default]
   Home,
+  /// Text is being typed, so keys go to the focused component instead of keybindings.
+  Insert,
 }
@@ -4,4 +4,6 @@ use native_model::{native_model, Model};
 use serde::{Deserialize, Serialize};
 
 pub mod stack;
-pub mod entry;
\ No newline at end of file
+pub mod entry;
+pub mod revision;
+pub mod trashed;
\ No newline at end of file
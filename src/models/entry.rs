@@ -5,61 +5,82 @@ use native_model::{native_model, Model};
 use ratatui::text::Text;
 use serde::{Deserialize, Serialize};
 
-#[derive(Serialize, Deserialize, PartialEq, Debug)]
-#[native_model(id = 2, version = 1)]
-#[native_db]
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+#[native_model(id = 2, version = 2, from = EntryV1)]
+#[native_db(secondary_key(stack_key, unique), secondary_key(created_key, unique))]
 pub struct Entry {
     #[primary_key]
     pub id: u32,
-    #[secondary_key]
     pub stack_id: u32,
     pub content: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
 }
 
 impl Entry {
-    pub fn save(model: &Entry) -> Result<()> {
-        Ok(())
+    /// Creates an entry captured right now. Its id is assigned when it is saved.
+    pub fn new(stack_id: u32, content: String) -> Self {
+        let now = Utc::now();
+        Self {
+            id: 0,
+            stack_id,
+            content,
+            created_at: now,
+            updated_at: now,
+        }
+    }
+
+    /// Replaces the content and bumps `updated_at`.
+    pub fn set_content(&mut self, content: String) {
+        self.content = content;
+        self.updated_at = Utc::now();
     }
 
-    pub fn update(&mut self) -> Result<()> {
-        Ok(())
+    /// Secondary keys have to be unique, so entries are indexed by `(stack_id, id)`
+    /// and looked up by the `stack_id` prefix.
+    pub fn stack_key(&self) -> (u32, u32) {
+        (self.stack_id, self.id)
     }
 
-    pub fn get_all(id: &u32) -> Vec<Entry> {
-        let entries = vec![
-            Entry {
-                id: 1,
-                stack_id: id.clone(),
-                content: "Lorem ipsum dolor sit amet, consectetur adipiscing elit.".to_string(),
-            },
-            Entry {
-                id: 2,
-                stack_id: id.clone(),
-                content: "Lorem ipsum dolor sit amet, consectetur adipiscing elit. Nulla id imperdiet dolor. Proin a dolor sit amet erat viverra condimentum. Praesent maximus efficitur ante, a cursus dui suscipit id."
-                    .to_string(),
-            },
-            Entry {
-                id: 3,
-                stack_id: id.clone(),
-                content: "Quisque et mattis ex. Phasellus dignissim dignissim congue. Nam nec maximus elit, vitae rutrum leo. Nullam ultrices lobortis leo, in ultricies justo viverra vulputate. Nulla non accumsan lectus. Mauris ac sapien auctor, posuere orci nec, maximus quam. In commodo, diam id tristique pellentesque, mauris justo semper nisl, facilisis venenatis enim ligula nec eros. Suspendisse potenti. Nunc id condimentum magna. Etiam blandit eleifend neque ac posuere. Maecenas magna metus, sodales vitae lorem nec, facilisis consectetur purus. Sed mollis vel enim at imperdiet. Maecenas nulla velit, iaculis sed pretium ultricies, auctor sit amet orci."
-                    .to_string(),
-            },
-            Entry {
-                id: 4,
-                stack_id: id.clone(),
-                content: "Duis aute irure dolor."
-                    .to_string(),
-            },
-        ];
+    /// Indexes entries by creation time, with the id breaking ties.
+    pub fn created_key(&self) -> (i64, u32) {
+        (self.created_at.timestamp_micros(), self.id)
+    }
+}
+
+/// The original entry layout, without timestamps.
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+#[native_model(id = 2, version = 1)]
+#[native_db(secondary_key(stack_key, unique))]
+pub struct EntryV1 {
+    #[primary_key]
+    pub id: u32,
+    pub stack_id: u32,
+    pub content: String,
+}
 
-        entries
+impl EntryV1 {
+    pub fn stack_key(&self) -> (u32, u32) {
+        (self.stack_id, self.id)
     }
+}
 
-    pub fn get_by_id(id: u32) -> Option<Result<Entry>> {
-        None
+impl From<EntryV1> for Entry {
+    /// The capture time of an old entry is unknown, so it is considered captured when migrated.
+    fn from(entry: EntryV1) -> Self {
+        Entry {
+            id: entry.id,
+            ..Entry::new(entry.stack_id, entry.content)
+        }
     }
+}
 
-    pub fn delete(&self) -> Result<()> {
-        Ok(())
+impl From<Entry> for EntryV1 {
+    fn from(entry: Entry) -> Self {
+        EntryV1 {
+            id: entry.id,
+            stack_id: entry.stack_id,
+            content: entry.content,
+        }
     }
 }
@@ -0,0 +1,37 @@
+use chrono::{DateTime, Utc};
+use native_db::*;
+use native_model::{native_model, Model};
+use serde::{Deserialize, Serialize};
+
+use super::entry::Entry;
+
+/// The content an entry had before it was edited.
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+#[native_model(id = 7, version = 1)]
+#[native_db(secondary_key(entry_key, unique))]
+pub struct Revision {
+    #[primary_key]
+    pub id: u32,
+    pub entry_id: u32,
+    pub content: String,
+    /// When this content was saved, i.e. the `updated_at` of the entry back then.
+    pub saved_at: DateTime<Utc>,
+}
+
+impl Revision {
+    /// Keeps the content of `entry` as it is now. Its id is assigned when it is saved.
+    pub fn of(entry: &Entry) -> Self {
+        Self {
+            id: 0,
+            entry_id: entry.id,
+            content: entry.content.clone(),
+            saved_at: entry.updated_at,
+        }
+    }
+
+    /// Revisions are indexed by `(entry_id, id)` and looked up by the `entry_id` prefix,
+    /// which lists them oldest first.
+    pub fn entry_key(&self) -> (u32, u32) {
+        (self.entry_id, self.id)
+    }
+}
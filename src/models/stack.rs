@@ -1,19 +1,26 @@
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, NaiveDate, Utc};
 use color_eyre::eyre::Result;
 use native_db::*;
 use native_model::{native_model, Model};
 use ratatui::text::Text;
 use serde::{Deserialize, Serialize};
 
-
 #[derive(Serialize, Deserialize, PartialEq, Debug)]
-#[native_model(id = 1, version = 1)]
-#[native_db]
+#[native_model(id = 1, version = 4, from = StackV3)]
+#[native_db(secondary_key(day_key, unique, optional))]
 pub struct Stack {
     #[primary_key]
     pub id: u32,
     pub name: String,
-    pub count: u8,
+    /// The day this stack collects entries for. Named stacks have no day.
+    pub day: Option<NaiveDate>,
+    /// The saved search of a smart stack. Smart stacks hold no entries of their own and
+    /// list whatever currently matches the query instead.
+    pub query: Option<String>,
+    /// Number of entries in the stack. It is derived from the entry index whenever
+    /// the stack is read and is never stored, so it can't drift.
+    #[serde(skip)]
+    pub count: u64,
 }
 
 impl Clone for Stack {
@@ -21,56 +28,200 @@ impl Clone for Stack {
         Self {
             id: self.id,
             name: self.name.clone(),
+            day: self.day,
+            query: self.query.clone(),
             count: self.count,
         }
     }
 }
 
 impl Stack {
-    pub fn save(model: &Stack) -> Result<()> {
-        Ok(())
-    }
-
-    pub fn update(&mut self) -> Result<()> {
-        Ok(())
-    }
-
-    pub fn get_all() -> Vec<Stack> {
-        let stacks = vec![
-            Stack {
-                id: 1,
-                name: "Stack 1".to_string(),
-                count: 1,
-            },
-            Stack {
-                id: 2,
-                name: "Stack 2".to_string(),
-                count: 5,
-            },
-            Stack {
-                id: 3,
-                name: "Stack 3".to_string(),
-                count: 3,
-            },
-            Stack {
-                id: 4,
-                name: "Stack 4".to_string(),
-                count: 8,
-            },
-        ];
-
-        return stacks;
-    }
-
-    pub fn get_by_id(id: u32) -> Option<Result<Stack>> {
-        return Some(Ok(Stack {
+    pub const DAY_FORMAT: &str = "%Y-%m-%d";
+
+    /// Creates a named stack that is not tied to a day. Its id is assigned when it is saved.
+    pub fn named(name: String) -> Self {
+        Self {
+            id: 0,
+            name,
+            day: None,
+            query: None,
+            count: 0,
+        }
+    }
+
+    /// Creates a smart stack listing the entries matching `query`. Its id is assigned
+    /// when it is saved.
+    pub fn smart(name: String, query: String) -> Self {
+        Self {
+            id: 0,
+            name,
+            day: None,
+            query: Some(query),
+            count: 0,
+        }
+    }
+
+    /// Creates the stack collecting the entries of `day`. Its id is assigned when it is saved.
+    pub fn for_day(day: NaiveDate) -> Self {
+        Self {
             id: 0,
-            name: "My Stack".to_string(),
-            count: 5,
-        }));
+            name: day.format(Self::DAY_FORMAT).to_string(),
+            day: Some(day),
+            query: None,
+            count: 0,
+        }
+    }
+
+    pub fn is_smart(&self) -> bool {
+        self.query.is_some()
+    }
+
+    /// Whether `other` is where entries meant for this stack go: the stack of the same
+    /// day, or the one with the same name.
+    pub fn is_same_as(&self, other: &Stack) -> bool {
+        match self.day {
+            Some(day) => other.day == Some(day),
+            None => other.name == self.name,
+        }
+    }
+
+    /// Sorts smart stacks first, then named stacks, then day stacks with the most recent
+    /// day on top.
+    pub fn sort_for_display(stacks: &mut [Stack]) {
+        stacks.sort_by(|a, b| {
+            b.is_smart()
+                .cmp(&a.is_smart())
+                .then(b.day.is_none().cmp(&a.day.is_none()))
+                .then(b.day.cmp(&a.day))
+                .then(a.name.cmp(&b.name))
+        });
+    }
+
+    /// Indexes day stacks by their day; `YYYY-MM-DD` sorts chronologically.
+    pub fn day_key(&self) -> Option<String> {
+        self.day.map(|d| d.format(Self::DAY_FORMAT).to_string())
+    }
+}
+
+/// The stack layout before smart stacks.
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+#[native_model(id = 1, version = 3, from = StackV2)]
+#[native_db(secondary_key(day_key, unique, optional))]
+pub struct StackV3 {
+    #[primary_key]
+    pub id: u32,
+    pub name: String,
+    pub day: Option<NaiveDate>,
+}
+
+impl StackV3 {
+    pub fn day_key(&self) -> Option<String> {
+        self.day.map(|d| d.format(Stack::DAY_FORMAT).to_string())
+    }
+}
+
+impl From<StackV3> for Stack {
+    fn from(stack: StackV3) -> Self {
+        Stack {
+            id: stack.id,
+            name: stack.name,
+            day: stack.day,
+            query: None,
+            count: 0,
+        }
     }
+}
+
+impl From<Stack> for StackV3 {
+    fn from(stack: Stack) -> Self {
+        StackV3 {
+            id: stack.id,
+            name: stack.name,
+            day: stack.day,
+        }
+    }
+}
+
+/// The stack layout that stored a manually maintained count.
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+#[native_model(id = 1, version = 2, from = StackV1)]
+#[native_db(secondary_key(day_key, unique, optional))]
+pub struct StackV2 {
+    #[primary_key]
+    pub id: u32,
+    pub name: String,
+    pub count: u8,
+    pub day: Option<NaiveDate>,
+}
+
+impl StackV2 {
+    pub fn day_key(&self) -> Option<String> {
+        self.day.map(|d| d.format(Stack::DAY_FORMAT).to_string())
+    }
+}
+
+impl From<StackV2> for StackV3 {
+    fn from(stack: StackV2) -> Self {
+        StackV3 {
+            id: stack.id,
+            name: stack.name,
+            day: stack.day,
+        }
+    }
+}
+
+impl From<StackV3> for StackV2 {
+    fn from(stack: StackV3) -> Self {
+        StackV2 {
+            id: stack.id,
+            name: stack.name,
+            // The count is derived since v3, so there is nothing meaningful to store.
+            count: 0,
+            day: stack.day,
+        }
+    }
+}
+
+impl From<StackV2> for Stack {
+    fn from(stack: StackV2) -> Self {
+        StackV3::from(stack).into()
+    }
+}
+
+/// The original stack layout, before stacks were keyed by day.
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+#[native_model(id = 1, version = 1)]
+#[native_db]
+pub struct StackV1 {
+    #[primary_key]
+    pub id: u32,
+    pub name: String,
+    pub count: u8,
+}
+
+impl From<StackV1> for StackV2 {
+    fn from(stack: StackV1) -> Self {
+        StackV2 {
+            id: stack.id,
+            name: stack.name,
+            count: stack.count,
+            day: None,
+        }
+    }
+}
+
+impl From<StackV2> for StackV1 {
+    fn from(stack: StackV2) -> Self {
+        StackV1 {
+            id: stack.id,
+            name: stack.name,
+            count: stack.count,
+        }
+    }
+}
 
-    pub fn delete(&self) -> Result<()> {
-        Ok(())
+impl From<StackV1> for Stack {
+    fn from(stack: StackV1) -> Self {
+        StackV2::from(stack).into()
     }
 }
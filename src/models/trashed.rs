@@ -0,0 +1,69 @@
+use chrono::{DateTime, Utc};
+use native_db::*;
+use native_model::{native_model, Model};
+use serde::{Deserialize, Serialize};
+
+use super::{entry::Entry, stack::Stack};
+
+/// A deleted stack along with its entries, or a deleted entry, kept until it is restored
+/// or purged.
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+#[native_model(id = 6, version = 1)]
+#[native_db]
+pub struct Trashed {
+    #[primary_key]
+    pub id: u32,
+    pub deleted_at: DateTime<Utc>,
+    /// The deleted stack, or the stack the deleted entry was in.
+    pub stack: Stack,
+    /// Whether the whole stack was deleted, rather than only `entries`.
+    pub whole_stack: bool,
+    pub entries: Vec<Entry>,
+}
+
+impl Trashed {
+    /// Trashes a stack with its entries. Its id is assigned when it is saved.
+    pub fn stack(stack: Stack, entries: Vec<Entry>) -> Self {
+        Self {
+            id: 0,
+            deleted_at: Utc::now(),
+            stack,
+            whole_stack: true,
+            entries,
+        }
+    }
+
+    /// Trashes a single entry. Its id is assigned when it is saved.
+    pub fn entry(stack: Stack, entry: Entry) -> Self {
+        Self {
+            id: 0,
+            deleted_at: Utc::now(),
+            stack,
+            whole_stack: false,
+            entries: vec![entry],
+        }
+    }
+
+    /// Finds the stack the entries go back to among `stacks`: the stack of the same day,
+    /// the stack itself, or one recreated since under the same name. When there is none,
+    /// the stack has to be restored too.
+    pub fn home<'a>(&self, stacks: &'a [Stack]) -> Option<&'a Stack> {
+        if let Some(day) = self.stack.day {
+            return stacks.iter().find(|s| s.day == Some(day));
+        }
+        stacks.iter().find(|s| s.id == self.stack.id).or_else(|| {
+            stacks
+                .iter()
+                .find(|s| s.name == self.stack.name && s.is_smart() == self.stack.is_smart())
+        })
+    }
+
+    /// Describes what was deleted in a line.
+    pub fn description(&self) -> String {
+        if self.whole_stack {
+            return format!("{} with {} entries", self.stack.name, self.entries.len());
+        }
+        let first_line = self.entries.iter().flat_map(|e| e.content.lines()).find(|l| !l.trim().is_empty());
+        format!("{} (in {})", first_line.unwrap_or_default().trim(), self.stack.name)
+    }
+}
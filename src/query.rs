@@ -0,0 +1,468 @@
+//! The query language used to search entries, e.g.
+//! `stack:work after:2026-09-01 before:2026-10-01 /invoice \d+/ -draft`.
+//!
+//! - `word`, `"some words"` and `prefix*` match the content through the full-text index.
+//! - `/pattern/` matches the content against a regular expression; `/pattern/i` ignores case.
+//! - `stack:name` keeps entries of the stack with that name or id. Repeated, any of them match.
+//! - `after:day` and `before:day` keep entries created on or after, or strictly before, a
+//!   day. A day is written `YYYY-MM-DD`, `today`, `yesterday` or `7d` for 7 days ago.
+//! - `-` in front of any of these excludes what it matches.
+
+use std::{collections::HashSet, fmt};
+
+use chrono::{Days, NaiveDate};
+use color_eyre::eyre::{eyre, Result};
+use regex::{Regex, RegexBuilder};
+
+use crate::{
+    calendar::Calendar,
+    db_reader::DbReader,
+    models::{entry::Entry, stack::Stack},
+    search::{self, SearchQuery, SearchTerm},
+};
+
+/// A mistake in a query, pointing at where it was found.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SyntaxError {
+    /// Position in the query, counted in characters from 1.
+    pub column: usize,
+    pub message: String,
+}
+
+impl fmt::Display for SyntaxError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "column {}: {}", self.column, self.message)
+    }
+}
+
+impl std::error::Error for SyntaxError {}
+
+/// A day in a query, resolved when the query runs so saved queries stay relative.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DaySpec {
+    Date(NaiveDate),
+    DaysAgo(u64),
+}
+
+impl DaySpec {
+    fn parse(value: &str) -> Option<Self> {
+        match value {
+            "today" => Some(Self::DaysAgo(0)),
+            "yesterday" => Some(Self::DaysAgo(1)),
+            _ => match value.strip_suffix('d') {
+                Some(days) => days.parse().ok().map(Self::DaysAgo),
+                None => NaiveDate::parse_from_str(value, "%Y-%m-%d").ok().map(Self::Date),
+            },
+        }
+    }
+
+    pub fn resolve(&self, today: NaiveDate) -> NaiveDate {
+        match self {
+            Self::Date(date) => *date,
+            Self::DaysAgo(days) => today.checked_sub_days(Days::new(*days)).unwrap_or(NaiveDate::MIN),
+        }
+    }
+}
+
+/// A parsed query.
+#[derive(Debug, Clone, Default)]
+pub struct Query {
+    /// Terms that have to be in the content. These rank the results.
+    pub text: SearchQuery,
+    pub excluded_text: Vec<SearchTerm>,
+    pub patterns: Vec<Regex>,
+    pub excluded_patterns: Vec<Regex>,
+    pub stacks: Vec<String>,
+    pub excluded_stacks: Vec<String>,
+    pub after: Option<DaySpec>,
+    pub before: Option<DaySpec>,
+}
+
+impl Query {
+    pub fn parse(query: &str) -> Result<Self, SyntaxError> {
+        Parser::new(query).parse()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.text.is_empty()
+            && self.excluded_text.is_empty()
+            && self.patterns.is_empty()
+            && self.excluded_patterns.is_empty()
+            && self.stacks.is_empty()
+            && self.excluded_stacks.is_empty()
+            && self.after.is_none()
+            && self.before.is_none()
+    }
+
+    /// Finds the matching entries. Full-text matches come best first, other queries
+    /// list the newest entries first.
+    ///
+    /// Candidates are read through the narrowest index the query allows: the full-text
+    /// index, then the creation time index, then the stack index. The rest of the query
+    /// filters them.
+    pub fn run(&self, db: &dyn DbReader, calendar: &Calendar) -> Result<Vec<Entry>> {
+        let stacks = db.get_stacks()?;
+        let included = self.stack_ids(&stacks, &self.stacks)?;
+        let excluded = self.stack_ids(&stacks, &self.excluded_stacks)?;
+        let today = calendar.today();
+        let start = self.after.map(|day| calendar.start_of(day.resolve(today)));
+        let end = self.before.map(|day| calendar.start_of(day.resolve(today)));
+
+        let ranked = !self.text.is_empty();
+        let candidates = if ranked {
+            search::search(db, &self.text)?.into_iter().map(|hit| hit.entry).collect()
+        } else if start.is_some() || end.is_some() {
+            let start = start.unwrap_or(chrono::DateTime::<chrono::Utc>::MIN_UTC);
+            let end = end.unwrap_or(chrono::DateTime::<chrono::Utc>::MAX_UTC);
+            db.get_entries_created_between(start, end)?
+        } else if let Some(included) = &included {
+            let mut entries = Vec::new();
+            for id in included {
+                entries.extend(db.get_entries(*id)?);
+            }
+            entries
+        } else {
+            db.get_all_entries()?
+        };
+
+        let mut entries = candidates
+            .into_iter()
+            .filter(|e| included.as_ref().is_none_or(|ids| ids.contains(&e.stack_id)))
+            .filter(|e| excluded.as_ref().is_none_or(|ids| !ids.contains(&e.stack_id)))
+            .filter(|e| start.is_none_or(|start| e.created_at >= start))
+            .filter(|e| end.is_none_or(|end| e.created_at < end))
+            .filter(|e| self.patterns.iter().all(|p| p.is_match(&e.content)))
+            .filter(|e| !self.excluded_patterns.iter().any(|p| p.is_match(&e.content)))
+            .filter(|e| !self.excluded_text.iter().any(|t| contains(&e.content, t)))
+            .collect::<Vec<_>>();
+        if !ranked {
+            entries.sort_by_key(|e| std::cmp::Reverse(e.created_key()));
+        }
+        Ok(entries)
+    }
+
+    /// Resolves stack names or ids, ignoring case. `None` means no stack was given.
+    fn stack_ids(&self, stacks: &[Stack], names: &[String]) -> Result<Option<HashSet<u32>>> {
+        if names.is_empty() {
+            return Ok(None);
+        }
+        let mut ids = HashSet::new();
+        for name in names {
+            let stack = stacks
+                .iter()
+                .find(|s| s.name.to_lowercase() == name.to_lowercase())
+                .or_else(|| stacks.iter().find(|s| s.id.to_string() == *name))
+                .ok_or_else(|| eyre!("There is no stack named {name}"))?;
+            ids.insert(stack.id);
+        }
+        Ok(Some(ids))
+    }
+}
+
+/// Gets the entries listed in a stack, oldest first, or the live matches of its query
+/// for a smart stack.
+pub fn stack_entries(db: &dyn DbReader, calendar: &Calendar, stack: &Stack) -> Result<Vec<Entry>> {
+    match &stack.query {
+        Some(query) => Query::parse(query)
+            .map_err(|e| eyre!("The query of {} is invalid, {e}", stack.name))?
+            .run(db, calendar),
+        None => {
+            let mut entries = db.get_entries(stack.id)?;
+            entries.sort_by_key(|e| e.created_key());
+            Ok(entries)
+        }
+    }
+}
+
+/// Counts the current matches of smart stacks, which the database can't know.
+///
+/// A query that no longer runs, e.g. because a stack it names was renamed, counts
+/// nothing; opening its stack tells why.
+pub fn count_smart_stacks(db: &dyn DbReader, calendar: &Calendar, stacks: &mut [Stack]) {
+    for stack in stacks.iter_mut().filter(|s| s.is_smart()) {
+        stack.count = stack_entries(db, calendar, stack).map_or(0, |entries| entries.len() as u64);
+    }
+}
+
+/// Checks a term against content that didn't go through the index.
+fn contains(content: &str, term: &SearchTerm) -> bool {
+    let tokens = search::tokenize(content);
+    match term {
+        SearchTerm::Word(word) => tokens.iter().any(|t| t == word),
+        SearchTerm::Prefix(prefix) => tokens.iter().any(|t| t.starts_with(prefix.as_str())),
+        SearchTerm::Phrase(words) => tokens.windows(words.len()).any(|w| w == words.as_slice()),
+    }
+}
+
+struct Parser {
+    chars: Vec<char>,
+    position: usize,
+}
+
+impl Parser {
+    const FILTERS: [&'static str; 3] = ["stack", "after", "before"];
+
+    fn new(query: &str) -> Self {
+        Self {
+            chars: query.chars().collect(),
+            position: 0,
+        }
+    }
+
+    fn error<T>(&self, column: usize, message: impl Into<String>) -> Result<T, SyntaxError> {
+        Err(SyntaxError {
+            column: column + 1,
+            message: message.into(),
+        })
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.position).copied()
+    }
+
+    /// Reads up to the next whitespace.
+    fn word(&mut self) -> String {
+        let start = self.position;
+        while self.peek().is_some_and(|c| !c.is_whitespace()) {
+            self.position += 1;
+        }
+        self.chars[start..self.position].iter().collect()
+    }
+
+    /// Reads up to an unescaped `delimiter`, the opening one having been read already.
+    fn delimited(&mut self, delimiter: char, what: &str) -> Result<String, SyntaxError> {
+        let start = self.position - 1;
+        let mut text = String::new();
+        loop {
+            match self.peek() {
+                None => return self.error(start, format!("{what} is never closed")),
+                Some(c) if c == delimiter => {
+                    self.position += 1;
+                    return Ok(text);
+                }
+                // Only the delimiter needs escaping; other escapes are kept for the regex.
+                Some('\\') if self.chars.get(self.position + 1) == Some(&delimiter) => {
+                    text.push(delimiter);
+                    self.position += 2;
+                }
+                Some(c) => {
+                    text.push(c);
+                    self.position += 1;
+                }
+            }
+        }
+    }
+
+    fn parse(mut self) -> Result<Query, SyntaxError> {
+        let mut query = Query::default();
+        while let Some(c) = self.peek() {
+            if c.is_whitespace() {
+                self.position += 1;
+                continue;
+            }
+            let start = self.position;
+            let negated = c == '-' && self.chars.get(start + 1).is_some_and(|c| !c.is_whitespace());
+            if negated {
+                self.position += 1;
+            }
+
+            match self.peek() {
+                Some('"') => {
+                    self.position += 1;
+                    let phrase = self.delimited('"', "The quote")?;
+                    if let Some(term) = Self::term(&phrase, false) {
+                        Self::push(&mut query, negated, term);
+                    }
+                }
+                Some('/') => {
+                    self.position += 1;
+                    let pattern = self.delimited('/', "The regular expression")?;
+                    let ignore_case = self.peek() == Some('i');
+                    if ignore_case {
+                        self.position += 1;
+                    }
+                    let regex = match RegexBuilder::new(&pattern).case_insensitive(ignore_case).build() {
+                        Ok(regex) => regex,
+                        Err(e) => return self.error(start, format!("Invalid regular expression: {e}")),
+                    };
+                    match negated {
+                        false => query.patterns.push(regex),
+                        true => query.excluded_patterns.push(regex),
+                    }
+                }
+                _ => match self.filter_key() {
+                    Some(key) => {
+                        let value = match self.peek() {
+                            Some('"') => {
+                                self.position += 1;
+                                self.delimited('"', "The quote")?
+                            }
+                            _ => self.word(),
+                        };
+                        self.filter(&mut query, negated, start, &key, value)?;
+                    }
+                    None => {
+                        let word = self.word();
+                        if let Some(term) = Self::term(word.trim_end_matches('*'), word.ends_with('*')) {
+                            Self::push(&mut query, negated, term);
+                        }
+                    }
+                },
+            }
+        }
+        Ok(query)
+    }
+
+    /// Reads the `key` of a `key:value` filter. Words that only look like one, such as
+    /// URLs, are left to be read as text.
+    fn filter_key(&mut self) -> Option<String> {
+        let start = self.position;
+        let length = self.chars[start..].iter().take_while(|c| c.is_ascii_alphabetic()).count();
+        let colon = start + length;
+        let is_filter = length > 0
+            && self.chars.get(colon) == Some(&':')
+            && self.chars.get(colon + 1..colon + 3) != Some(&['/', '/'][..]);
+        if !is_filter {
+            return None;
+        }
+        self.position = colon + 1;
+        Some(self.chars[start..colon].iter().collect())
+    }
+
+    fn filter(
+        &self,
+        query: &mut Query,
+        negated: bool,
+        start: usize,
+        key: &str,
+        value: String,
+    ) -> Result<(), SyntaxError> {
+        if value.is_empty() {
+            return self.error(start, format!("{key}: needs a value"));
+        }
+        match key {
+            "stack" if negated => query.excluded_stacks.push(value),
+            "stack" => query.stacks.push(value),
+            "after" | "before" if negated => return self.error(start, format!("{key}: can't be negated")),
+            "after" | "before" => {
+                let Some(day) = DaySpec::parse(&value) else {
+                    return self.error(
+                        start,
+                        format!("Invalid day `{value}` in {key}:, expected YYYY-MM-DD, today, yesterday or a number of days like 7d"),
+                    );
+                };
+                match key {
+                    "after" => query.after = Some(day),
+                    _ => query.before = Some(day),
+                }
+            }
+            _ => {
+                return self.error(
+                    start,
+                    format!("Unknown filter `{key}:`, expected one of {}", Self::FILTERS.map(|f| format!("{f}:")).join(" ")),
+                )
+            }
+        }
+        Ok(())
+    }
+
+    /// Turns text into a search term. Text that splits into several words, like
+    /// `e-mail`, has to match as a phrase.
+    fn term(text: &str, prefix: bool) -> Option<SearchTerm> {
+        let mut words = search::tokenize(text);
+        match (words.len(), prefix) {
+            (0, _) => None,
+            (1, true) => words.pop().map(SearchTerm::Prefix),
+            (1, false) => words.pop().map(SearchTerm::Word),
+            _ => Some(SearchTerm::Phrase(words)),
+        }
+    }
+
+    fn push(query: &mut Query, negated: bool, term: SearchTerm) {
+        match negated {
+            false => query.text.terms.push(term),
+            true => query.excluded_text.push(term),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{TimeZone, Utc};
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+    use crate::{config::JournalConfig, db_reader::MemoryDbReader};
+
+    fn error(query: &str) -> SyntaxError {
+        Query::parse(query).expect_err("the query should not parse")
+    }
+
+    #[test]
+    fn test_parse() -> Result<()> {
+        let query = Query::parse(r#"stack:work -stack:"side project" after:2026-09-01 before:7d /invoice \d+/i -draft "the end" http://example.com"#)?;
+        assert_eq!(query.stacks, vec!["work".to_string()]);
+        assert_eq!(query.excluded_stacks, vec!["side project".to_string()]);
+        assert_eq!(query.after, Some(DaySpec::Date(NaiveDate::from_ymd_opt(2026, 9, 1).unwrap())));
+        assert_eq!(query.before, Some(DaySpec::DaysAgo(7)));
+        assert_eq!(query.patterns.iter().map(|p| p.as_str()).collect::<Vec<_>>(), vec![r"invoice \d+"]);
+        assert!(query.patterns[0].is_match("Invoice 12"));
+        assert_eq!(query.excluded_text, vec![SearchTerm::Word("draft".to_string())]);
+        assert_eq!(
+            query.text.terms,
+            vec![
+                SearchTerm::Phrase(vec!["the".to_string(), "end".to_string()]),
+                SearchTerm::Phrase(vec!["http".to_string(), "example.com".to_string()]),
+            ]
+        );
+        assert_eq!(Query::parse(r"/a\/b/")?.patterns[0].as_str(), "a/b");
+        assert!(Query::parse("  - ")?.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_syntax_errors() {
+        assert_eq!(error(r#"bob "the"#).to_string(), "column 5: The quote is never closed");
+        assert_eq!(error("a /b(").to_string(), "column 3: The regular expression is never closed");
+        assert_eq!(error("a /b(/").column, 3);
+        assert_eq!(
+            error("tag:x").to_string(),
+            "column 1: Unknown filter `tag:`, expected one of stack: after: before:"
+        );
+        assert_eq!(error("x after:last-week").column, 3);
+        assert_eq!(error("-before:today").message, "before: can't be negated");
+        assert_eq!(error("stack:").message, "stack: needs a value");
+    }
+
+    #[test]
+    fn test_run_filters_candidates() -> Result<()> {
+        let db = MemoryDbReader::new();
+        let work = db.save_stack(&Stack::named("Work".to_string()))?;
+        let home = db.save_stack(&Stack::named("home".to_string()))?;
+        let add = |stack_id: u32, day: u32, content: &str| -> Result<u32> {
+            let mut entry = Entry::new(stack_id, content.to_string());
+            entry.created_at = Utc.with_ymd_and_hms(2026, 9, day, 12, 0, 0).unwrap();
+            Ok(db.save_entry(&entry)?.id)
+        };
+        let sent = add(work.id, 2, "Sent invoice 12 to Bob")?;
+        let draft = add(work.id, 3, "Invoice 13, still a draft")?;
+        let groceries = add(home.id, 4, "Invoice 14 for the groceries")?;
+        add(work.id, 30, "Invoice 15")?;
+
+        let calendar = Calendar::new(&JournalConfig {
+            day_rollover_hour: 0,
+            timezone: Some("UTC".to_string()),
+            ..Default::default()
+        })?;
+        let ids = |query: &str| -> Result<Vec<u32>> {
+            let entries = Query::parse(query)?.run(&db, &calendar)?;
+            Ok(entries.into_iter().map(|e| e.id).collect())
+        };
+        assert_eq!(ids(r"stack:work after:2026-09-01 before:2026-09-30 /invoice \d+/i -draft")?, vec![sent]);
+        assert_eq!(ids("after:2026-09-02 before:2026-09-05")?, vec![groceries, draft, sent]);
+        assert_eq!(ids("invoice -stack:WORK")?, vec![groceries]);
+        assert_eq!(ids("stack:home stack:work -/Invoice 1[45]/")?, vec![draft, sent]);
+        assert!(ids("stack:missing").is_err());
+        Ok(())
+    }
+}
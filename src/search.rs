@@ -0,0 +1,269 @@
+use std::collections::{BTreeMap, HashMap};
+
+use color_eyre::eyre::Result;
+use unicode_segmentation::UnicodeSegmentation;
+
+use crate::{db_reader::DbReader, models::entry::Entry};
+
+/// Where a term occurs in an entry, counted in words.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Posting {
+    pub term: String,
+    pub entry_id: u32,
+    pub positions: Vec<u32>,
+}
+
+/// Splits text into the lowercased words that are indexed.
+pub fn tokenize(text: &str) -> Vec<String> {
+    text.unicode_words().map(str::to_lowercase).collect()
+}
+
+/// Gets the postings of every distinct term of an entry.
+pub fn postings(entry: &Entry) -> Vec<Posting> {
+    let mut positions: BTreeMap<String, Vec<u32>> = BTreeMap::new();
+    for (position, term) in tokenize(&entry.content).into_iter().enumerate() {
+        positions.entry(term).or_default().push(position as u32);
+    }
+    positions
+        .into_iter()
+        .map(|(term, positions)| Posting {
+            term,
+            entry_id: entry.id,
+            positions,
+        })
+        .collect()
+}
+
+/// A part of a search query that every matching entry has to contain.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SearchTerm {
+    Word(String),
+    /// Any word starting with this, written `word*`.
+    Prefix(String),
+    /// These words next to each other, written `"some words"`.
+    Phrase(Vec<String>),
+}
+
+/// The full-text part of a [`Query`](crate::query::Query).
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SearchQuery {
+    pub terms: Vec<SearchTerm>,
+}
+
+impl SearchQuery {
+    pub fn is_empty(&self) -> bool {
+        self.terms.is_empty()
+    }
+}
+
+/// An entry matching a search, with how well it matched.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SearchHit {
+    pub entry: Entry,
+    pub score: f64,
+}
+
+/// Finds the entries containing every term of the query, best matches first.
+///
+/// Entries are ranked by tf-idf: terms that are rare across the journal weigh more, and
+/// matches in short entries count more than the same matches in long ones.
+pub fn search(db: &dyn DbReader, query: &SearchQuery) -> Result<Vec<SearchHit>> {
+    if query.is_empty() {
+        return Ok(Vec::new());
+    }
+    let total = db.count_all_entries()? as f64;
+
+    // Every word is looked up at once, which spares an encrypted journal from being
+    // decrypted for each of them.
+    let lookups = query
+        .terms
+        .iter()
+        .flat_map(|term| match term {
+            SearchTerm::Word(word) => vec![(word.clone(), false)],
+            SearchTerm::Prefix(prefix) => vec![(prefix.clone(), true)],
+            SearchTerm::Phrase(words) => words.iter().map(|w| (w.clone(), false)).collect(),
+        })
+        .collect::<Vec<_>>();
+    let postings = lookups.iter().cloned().zip(db.get_postings_of(&lookups)?).collect::<HashMap<_, _>>();
+    let lookup = |term: &str, prefix: bool| postings.get(&(term.to_string(), prefix)).map_or(&[][..], Vec::as_slice);
+
+    let mut scores: Option<HashMap<u32, f64>> = None;
+    for term in &query.terms {
+        let frequencies = term_frequencies(term, lookup);
+        let idf = (1.0 + total / frequencies.len().max(1) as f64).ln();
+        scores = Some(match scores {
+            None => frequencies
+                .into_iter()
+                .map(|(id, tf)| (id, (1.0 + (tf as f64).ln()) * idf))
+                .collect(),
+            Some(scores) => scores
+                .into_iter()
+                .filter_map(|(id, score)| {
+                    let tf = *frequencies.get(&id)?;
+                    Some((id, score + (1.0 + (tf as f64).ln()) * idf))
+                })
+                .collect(),
+        });
+    }
+
+    let scores = scores.unwrap_or_default();
+    let ids = scores.keys().copied().collect::<Vec<_>>();
+    // The index can be ahead of a stale read, so entries that are gone are skipped.
+    let mut hits = db
+        .get_entries_by_ids(&ids)?
+        .into_iter()
+        .map(|entry| {
+            let length = tokenize(&entry.content).len().max(1) as f64;
+            SearchHit {
+                score: scores[&entry.id] / length.sqrt(),
+                entry,
+            }
+        })
+        .collect::<Vec<_>>();
+    hits.sort_by(|a, b| b.score.total_cmp(&a.score).then(b.entry.created_key().cmp(&a.entry.created_key())));
+    Ok(hits)
+}
+
+/// Counts how often a term occurs in each entry containing it, from the postings
+/// `lookup` finds for a word or prefix.
+fn term_frequencies<'a>(term: &SearchTerm, lookup: impl Fn(&str, bool) -> &'a [Posting]) -> HashMap<u32, usize> {
+    match term {
+        SearchTerm::Word(word) => lookup(word, false).iter().map(|p| (p.entry_id, p.positions.len())).collect(),
+        SearchTerm::Prefix(prefix) => {
+            let mut frequencies = HashMap::new();
+            for posting in lookup(prefix, true) {
+                *frequencies.entry(posting.entry_id).or_default() += posting.positions.len();
+            }
+            frequencies
+        }
+        SearchTerm::Phrase(words) => {
+            let positions: Vec<HashMap<u32, &Vec<u32>>> = words
+                .iter()
+                .map(|word| lookup(word, false).iter().map(|p| (p.entry_id, &p.positions)).collect())
+                .collect();
+            let (first, others) = positions.split_first().expect("phrases have several words");
+            first
+                .iter()
+                .filter_map(|(id, starts)| {
+                    let occurrences = starts
+                        .iter()
+                        .filter(|start| {
+                            others.iter().zip(1..).all(|(word, offset)| {
+                                word.get(id).is_some_and(|p| p.binary_search(&(**start + offset)).is_ok())
+                            })
+                        })
+                        .count();
+                    (occurrences > 0).then_some((*id, occurrences))
+                })
+                .collect()
+        }
+    }
+}
+
+/// Picks the line of an entry that best shows why it matched.
+pub fn snippet(entry: &Entry, query: &SearchQuery) -> String {
+    let words = query
+        .terms
+        .iter()
+        .flat_map(|t| match t {
+            SearchTerm::Word(word) | SearchTerm::Prefix(word) => vec![word.clone()],
+            SearchTerm::Phrase(words) => words.clone(),
+        })
+        .collect::<Vec<_>>();
+    let mut lines = entry.content.lines().filter(|l| !l.trim().is_empty());
+    let first = lines.clone().next().unwrap_or_default();
+    lines
+        .find(|line| tokenize(line).iter().any(|token| words.iter().any(|w| token.starts_with(w.as_str()))))
+        .unwrap_or(first)
+        .trim()
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+    use crate::{db_reader::MemoryDbReader, query::Query};
+
+    fn parse(query: &str) -> Result<SearchQuery> {
+        Ok(Query::parse(query)?.text)
+    }
+
+    fn ids(db: &dyn DbReader, query: &str) -> Result<Vec<u32>> {
+        let hits = search(db, &parse(query)?)?;
+        Ok(hits.into_iter().map(|h| h.entry.id).collect())
+    }
+
+    fn sample_db() -> Result<MemoryDbReader> {
+        let db = MemoryDbReader::new();
+        for content in [
+            "Call Bob about the invoice",
+            "The invoice from Bob was paid. Invoice closed, finally an invoice done",
+            "Bob called back",
+            "Paid the invoice for the new bike, then went for a long ride through the hills to see the view",
+        ] {
+            db.save_entry(&Entry::new(1, content.to_string()))?;
+        }
+        Ok(db)
+    }
+
+    #[test]
+    fn test_parse() -> Result<()> {
+        let query = parse(r#"bob "the Invoice" inv* e-mail"#)?;
+        assert_eq!(
+            query.terms,
+            vec![
+                SearchTerm::Word("bob".to_string()),
+                SearchTerm::Phrase(vec!["the".to_string(), "invoice".to_string()]),
+                SearchTerm::Prefix("inv".to_string()),
+                SearchTerm::Phrase(vec!["e".to_string(), "mail".to_string()]),
+            ]
+        );
+        assert!(parse(r#"bob "the"#).is_err());
+        assert!(parse("  ")?.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_ranks_frequent_matches_in_short_entries_first() -> Result<()> {
+        let db = sample_db()?;
+        assert_eq!(ids(&db, "invoice")?, vec![2, 1, 4]);
+        let mut both = ids(&db, "bob invoice")?;
+        both.sort();
+        assert_eq!(both, vec![1, 2]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_phrase_and_prefix() -> Result<()> {
+        let db = sample_db()?;
+        assert_eq!(ids(&db, r#""the invoice""#)?, vec![1, 2, 4]);
+        assert_eq!(ids(&db, r#""invoice the""#)?, Vec::<u32>::new());
+        assert_eq!(ids(&db, "call*")?, vec![3, 1]);
+        assert_eq!(ids(&db, "cal")?, Vec::<u32>::new());
+        Ok(())
+    }
+
+    #[test]
+    fn test_index_follows_updates_and_deletes() -> Result<()> {
+        let db = sample_db()?;
+        let mut entry = db.get_entry(3)?.unwrap();
+        entry.set_content("Alice called back".to_string());
+        db.update_entry(&entry)?;
+        assert_eq!(ids(&db, "bob")?.len(), 2);
+        assert_eq!(ids(&db, "alice")?, vec![3]);
+
+        db.delete_entry(&entry)?;
+        assert_eq!(ids(&db, "alice")?, Vec::<u32>::new());
+        Ok(())
+    }
+
+    #[test]
+    fn test_snippet_shows_matching_line() -> Result<()> {
+        let entry = Entry::new(1, "Groceries\n\n  milk, eggs\nbread".to_string());
+        assert_eq!(snippet(&entry, &parse("egg*")?), "milk, eggs");
+        assert_eq!(snippet(&entry, &parse("cheese")?), "Groceries");
+        Ok(())
+    }
+}